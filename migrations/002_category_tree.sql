@@ -0,0 +1,8 @@
+-- Allow categories to be nested (e.g. Electronics > Laptops > Gaming)
+ALTER TABLE categories
+    ADD COLUMN parent_id UUID REFERENCES categories(id) ON DELETE RESTRICT;
+
+ALTER TABLE categories
+    ADD CONSTRAINT categories_parent_not_self CHECK (parent_id IS NULL OR parent_id <> id);
+
+CREATE INDEX idx_categories_parent_id ON categories(parent_id);
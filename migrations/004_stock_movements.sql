@@ -0,0 +1,21 @@
+-- Inventory ledger: products.stock is kept as a projection of these movements
+CREATE TYPE stock_movement_kind AS ENUM ('receipt', 'sale', 'adjustment', 'return');
+
+CREATE TABLE stock_movements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    kind stock_movement_kind NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity <> 0),
+    balance_after INTEGER NOT NULL CHECK (balance_after >= 0),
+    reason TEXT,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_stock_movements_product_id ON stock_movements(product_id, created_at);
+
+-- Opening balances so the ledger matches existing stock levels
+INSERT INTO stock_movements (product_id, kind, quantity, balance_after, reason)
+SELECT id, 'adjustment', stock, stock, 'Opening balance'
+FROM products
+WHERE stock > 0;
@@ -0,0 +1,26 @@
+-- Payments and refunds recorded per provider
+CREATE TYPE payment_kind AS ENUM ('payment', 'refund');
+CREATE TYPE payment_status AS ENUM ('pending', 'succeeded', 'failed');
+
+CREATE TABLE payments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    provider_ref VARCHAR(255) NOT NULL,
+    kind payment_kind NOT NULL,
+    status payment_status NOT NULL DEFAULT 'pending',
+    amount DECIMAL(12,2) NOT NULL CHECK (amount >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (provider, provider_ref, kind)
+);
+
+-- Provider webhook events already handled, so redeliveries are ignored
+CREATE TABLE payment_webhook_events (
+    provider VARCHAR(50) NOT NULL,
+    event_id VARCHAR(255) NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (provider, event_id)
+);
+
+CREATE INDEX idx_payments_order_id ON payments(order_id);
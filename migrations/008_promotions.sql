@@ -0,0 +1,39 @@
+-- Promotions: automatic discounts or coupon codes, scoped to the cart, a product or a category tree
+CREATE TYPE promotion_discount_type AS ENUM ('percentage', 'fixed');
+CREATE TYPE promotion_scope AS ENUM ('cart', 'product', 'category');
+
+CREATE TABLE promotions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    code VARCHAR(50) UNIQUE,
+    discount_type promotion_discount_type NOT NULL,
+    discount_value DECIMAL(10,2) NOT NULL CHECK (discount_value > 0),
+    scope promotion_scope NOT NULL DEFAULT 'cart',
+    product_id UUID REFERENCES products(id) ON DELETE CASCADE,
+    category_id UUID REFERENCES categories(id) ON DELETE CASCADE,
+    min_spend DECIMAL(12,2) CHECK (min_spend >= 0),
+    starts_at TIMESTAMPTZ,
+    ends_at TIMESTAMPTZ,
+    usage_limit INTEGER CHECK (usage_limit > 0),
+    per_user_limit INTEGER CHECK (per_user_limit > 0),
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (discount_type <> 'percentage' OR discount_value <= 100),
+    CHECK (scope <> 'product' OR product_id IS NOT NULL),
+    CHECK (scope <> 'category' OR category_id IS NOT NULL),
+    CHECK (ends_at IS NULL OR starts_at IS NULL OR ends_at > starts_at)
+);
+
+CREATE TABLE promotion_redemptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    promotion_id UUID NOT NULL REFERENCES promotions(id) ON DELETE CASCADE,
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    discount DECIMAL(12,2) NOT NULL CHECK (discount >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_promotion_redemptions_promotion_id ON promotion_redemptions(promotion_id, user_id);
+
+ALTER TABLE carts ADD COLUMN coupon_code VARCHAR(50);
+ALTER TABLE orders ADD COLUMN discount DECIMAL(12,2) NOT NULL DEFAULT 0 CHECK (discount >= 0);
@@ -0,0 +1,49 @@
+-- Supported currencies and the number of minor-unit decimals used when rounding
+CREATE TABLE currencies (
+    code CHAR(3) PRIMARY KEY CHECK (code = UPPER(code)),
+    name VARCHAR(50) NOT NULL,
+    decimals SMALLINT NOT NULL CHECK (decimals BETWEEN 0 AND 4)
+);
+
+INSERT INTO currencies (code, name, decimals) VALUES
+    ('USD', 'US Dollar', 2),
+    ('EUR', 'Euro', 2),
+    ('GBP', 'Pound Sterling', 2),
+    ('CAD', 'Canadian Dollar', 2),
+    ('CHF', 'Swiss Franc', 2),
+    ('NGN', 'Naira', 2),
+    ('JPY', 'Yen', 0),
+    ('KRW', 'Won', 0),
+    ('KWD', 'Kuwaiti Dinar', 3);
+
+-- Existing prices were entered in US dollars
+ALTER TABLE products ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' REFERENCES currencies(code);
+
+-- 1 unit of base_currency = rate units of quote_currency
+CREATE TABLE exchange_rates (
+    base_currency CHAR(3) NOT NULL REFERENCES currencies(code) ON DELETE CASCADE,
+    quote_currency CHAR(3) NOT NULL REFERENCES currencies(code) ON DELETE CASCADE,
+    rate DECIMAL(20,10) NOT NULL CHECK (rate > 0),
+    updated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (base_currency, quote_currency),
+    CHECK (base_currency <> quote_currency)
+);
+
+-- Rate from one currency to another, using the inverse pair when only that is stored.
+-- NULL when no rate is known.
+CREATE FUNCTION exchange_rate(from_currency CHAR(3), to_currency CHAR(3)) RETURNS DECIMAL AS $$
+    SELECT CASE
+        WHEN from_currency = to_currency THEN 1::DECIMAL
+        ELSE COALESCE(
+            (SELECT rate FROM exchange_rates WHERE base_currency = from_currency AND quote_currency = to_currency),
+            (SELECT 1 / rate FROM exchange_rates WHERE base_currency = to_currency AND quote_currency = from_currency)
+        )
+    END
+$$ LANGUAGE sql STABLE;
+
+-- Convert an amount and round it to the target currency's decimals
+CREATE FUNCTION convert_price(amount DECIMAL, from_currency CHAR(3), to_currency CHAR(3)) RETURNS DECIMAL AS $$
+    SELECT ROUND(amount * exchange_rate(from_currency, to_currency),
+                 (SELECT decimals FROM currencies WHERE code = to_currency))
+$$ LANGUAGE sql STABLE;
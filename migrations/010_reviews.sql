@@ -0,0 +1,24 @@
+-- Reviews wait for moderation before they are shown or counted
+CREATE TYPE review_status AS ENUM ('pending', 'approved', 'hidden');
+
+CREATE TABLE reviews (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    comment TEXT NOT NULL,
+    status review_status NOT NULL DEFAULT 'pending',
+    verified_purchase BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (product_id, user_id)
+);
+
+CREATE INDEX idx_reviews_product_id ON reviews(product_id, status, created_at);
+CREATE INDEX idx_reviews_status ON reviews(status);
+
+-- Aggregates over approved reviews, kept in sync on every moderation change
+ALTER TABLE products ADD COLUMN average_rating DECIMAL(3,2);
+ALTER TABLE products ADD COLUMN review_count INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_products_average_rating ON products(average_rating);
@@ -0,0 +1,7 @@
+-- Each refresh token is one device session
+ALTER TABLE refresh_tokens ADD COLUMN device_label VARCHAR(100);
+ALTER TABLE refresh_tokens ADD COLUMN user_agent TEXT;
+ALTER TABLE refresh_tokens ADD COLUMN ip_address VARCHAR(45);
+ALTER TABLE refresh_tokens ADD COLUMN last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX idx_refresh_tokens_user_created ON refresh_tokens(user_id, created_at);
@@ -0,0 +1,8 @@
+-- Refresh tokens are now "<selector>.<verifier>"; only the selector and a keyed hash
+-- of the verifier are stored. Legacy plaintext tokens cannot be converted, so every
+-- existing session is ended and users sign in again.
+DELETE FROM refresh_tokens;
+
+ALTER TABLE refresh_tokens DROP COLUMN token;
+ALTER TABLE refresh_tokens ADD COLUMN selector VARCHAR(32) NOT NULL UNIQUE;
+ALTER TABLE refresh_tokens ADD COLUMN verifier_hash VARCHAR(64) NOT NULL;
@@ -0,0 +1,13 @@
+-- Access tokens revoked before they expire. `id` is either the token's jti or a
+-- session id (sid), which revokes every access token issued for that session.
+CREATE TABLE revoked_tokens (
+    id UUID PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_revoked_tokens_expires_at ON revoked_tokens(expires_at);
+
+-- Access tokens issued before this instant are rejected (logout everywhere, password change, demotion)
+ALTER TABLE users ADD COLUMN tokens_valid_after TIMESTAMPTZ;
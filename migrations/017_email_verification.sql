@@ -0,0 +1,19 @@
+-- Verified addresses carry a timestamp; a changed address waits in pending_email until confirmed.
+-- Accounts created before verification existed are treated as verified.
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN pending_email VARCHAR(255);
+UPDATE users SET email_verified_at = created_at;
+
+-- Emailed verification links, stored as a selector plus a keyed verifier hash.
+-- `email` is the address the link proves ownership of (the current or the pending one).
+CREATE TABLE email_verification_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    selector VARCHAR(32) NOT NULL UNIQUE,
+    verifier_hash VARCHAR(64) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_email_verification_tokens_user_id ON email_verification_tokens(user_id);
@@ -0,0 +1,13 @@
+-- Failed login counters shared by every server instance, keyed by email and by client IP
+CREATE TYPE throttle_scope AS ENUM ('email', 'ip');
+
+CREATE TABLE login_throttles (
+    scope throttle_scope NOT NULL,
+    key VARCHAR(255) NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    PRIMARY KEY (scope, key)
+);
+
+CREATE INDEX idx_login_throttles_locked_until ON login_throttles(locked_until);
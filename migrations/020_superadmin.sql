@@ -0,0 +1,9 @@
+-- Superadmins manage roles; the admin binary's --superadmin flag creates or promotes the first one
+ALTER TYPE user_role ADD VALUE IF NOT EXISTS 'superadmin';
+
+-- Suspended users cannot log in or refresh their sessions
+CREATE TYPE user_status AS ENUM ('active', 'suspended');
+
+ALTER TABLE users ADD COLUMN status user_status NOT NULL DEFAULT 'active';
+
+CREATE INDEX idx_users_role ON users(role);
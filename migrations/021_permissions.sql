@@ -0,0 +1,41 @@
+-- Roles are named permission sets. Every user gets the set named by users.role, plus any
+-- extra roles assigned in user_roles (e.g. a "content-editor" on a regular account).
+CREATE TABLE roles (
+    name VARCHAR(50) PRIMARY KEY,
+    description VARCHAR(255),
+    builtin BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE role_permissions (
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    permission VARCHAR(100) NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+CREATE TABLE user_roles (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(50) NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role)
+);
+
+CREATE INDEX idx_user_roles_role ON user_roles(role);
+
+INSERT INTO roles (name, description, builtin) VALUES
+    ('user', 'Every customer account', TRUE),
+    ('admin', 'Store administration', TRUE),
+    ('superadmin', 'Administration including roles', TRUE);
+
+-- Admins keep what the blanket admin check allowed; managing roles is left to superadmins
+INSERT INTO role_permissions (role, permission)
+SELECT 'admin', permission FROM UNNEST(ARRAY[
+    'products:write', 'products:images', 'products:delete', 'inventory:read', 'inventory:write',
+    'categories:write', 'categories:delete', 'orders:read', 'orders:manage', 'orders:refund',
+    'currencies:manage', 'promotions:manage', 'reviews:moderate', 'lockouts:manage',
+    'users:read', 'users:manage'
+]) AS permission;
+
+INSERT INTO role_permissions (role, permission)
+SELECT 'superadmin', permission FROM role_permissions WHERE role = 'admin'
+UNION ALL SELECT 'superadmin', 'roles:manage';
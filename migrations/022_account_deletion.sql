@@ -0,0 +1,2 @@
+-- Deleted accounts keep their row so orders and reviews stay intact, with the PII anonymised
+ALTER TYPE user_status ADD VALUE IF NOT EXISTS 'deleted';
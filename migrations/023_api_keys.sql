@@ -0,0 +1,18 @@
+-- API keys for machine clients. A key reads `<prefix>.<secret>`: the prefix is stored for the
+-- lookup and shown in listings, the secret only as a keyed hash. Keys act as their user, limited
+-- to the listed permissions.
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    prefix VARCHAR(64) NOT NULL UNIQUE,
+    key_hash VARCHAR(64) NOT NULL,
+    permissions VARCHAR(100)[] NOT NULL DEFAULT '{}',
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id, created_at);
@@ -0,0 +1,5 @@
+-- Support staff may log in as a customer; granted to the roles that already manage users
+INSERT INTO role_permissions (role, permission) VALUES
+    ('admin', 'users:impersonate'),
+    ('superadmin', 'users:impersonate')
+ON CONFLICT DO NOTHING;
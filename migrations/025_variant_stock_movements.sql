@@ -0,0 +1,12 @@
+-- Variant stock goes through the same ledger; for those rows balance_after is the variant's stock
+ALTER TABLE stock_movements
+    ADD COLUMN variant_id UUID REFERENCES product_variants(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_stock_movements_variant_id ON stock_movements(variant_id, created_at)
+    WHERE variant_id IS NOT NULL;
+
+-- Opening balances so the ledger matches existing variant stock levels
+INSERT INTO stock_movements (product_id, variant_id, kind, quantity, balance_after, reason)
+SELECT product_id, id, 'adjustment', stock, stock, 'Opening balance'
+FROM product_variants
+WHERE stock > 0;
@@ -0,0 +1,7 @@
+-- Carts and orders are priced in one currency; every line is converted into it with convert_price().
+-- Payments and refunds are in their order's currency. Existing rows were priced in US dollars.
+ALTER TABLE carts ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' REFERENCES currencies(code);
+ALTER TABLE orders ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' REFERENCES currencies(code);
+
+-- Currency of a promotion's fixed discount and minimum spend, converted into the cart's currency
+ALTER TABLE promotions ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD' REFERENCES currencies(code);
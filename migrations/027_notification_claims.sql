@@ -0,0 +1,3 @@
+-- A dispatcher claims a batch of the outbox before sending it, so several instances never
+-- send the same notification. A claim that is never released (e.g. a crash) lapses.
+ALTER TABLE notifications ADD COLUMN claimed_until TIMESTAMPTZ;
@@ -0,0 +1,3 @@
+-- Whether the session that created the key had passed a second factor. Keys act with this
+-- instead of always counting as two-factor; existing keys must be reissued to regain it.
+ALTER TABLE api_keys ADD COLUMN mfa_verified BOOLEAN NOT NULL DEFAULT FALSE;
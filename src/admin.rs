@@ -1,11 +1,15 @@
 use tests3::utils::auth::hash_password;
 use tests3::db::db_con::create_pool;
 use tests3::db::userq::find_by_email;
+use tests3::models::user::UserRole;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
 
     dotenvy::dotenv().ok();
+    // `--superadmin` creates a superadmin instead, or promotes the existing account
+    let superadmin = std::env::args().any(|arg| arg == "--superadmin");
+    let role = if superadmin { UserRole::SuperAdmin } else { UserRole::Admin };
 
     // Create database pool
     let db_pool = create_pool().await?;
@@ -18,22 +22,32 @@ async fn main() -> anyhow::Result<()> {
 
     let hashed_password = hash_password(&admin_password)?;
 
-    if find_by_email(&db_pool, &admin_email).await?.is_some() {
-        return Err(anyhow::anyhow!("Admin user with email {} already exists", admin_email));
+    if let Some(user) = find_by_email(&db_pool, &admin_email).await? {
+        if !superadmin {
+            return Err(anyhow::anyhow!("Admin user with email {} already exists", admin_email));
+        }
+
+        sqlx::query!("UPDATE users SET role = $2 WHERE id = $1", user.id, role as UserRole)
+            .execute(&db_pool)
+            .await?;
+
+        println!("{} promoted to superadmin", admin_email);
+        return Ok(());
     }
 
     sqlx::query!(
         r#"
-        INSERT INTO users (username, email, password_hash, role)
-        VALUES ($1, $2, $3, 'admin')
+        INSERT INTO users (username, email, password_hash, role, email_verified_at)
+        VALUES ($1, $2, $3, $4, NOW())
         "#,
         admin_username.trim(),
         admin_email.trim(),
-        hashed_password
+        hashed_password,
+        role as UserRole
     )
     .execute(&db_pool)
     .await?;
 
-    println!("Admin user created successfully");
+    println!("{} user created successfully", if superadmin { "Superadmin" } else { "Admin" });
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,108 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::api_key::{format_api_key, ApiKey};
+use crate::utils::token::{parse_split_token, SplitToken, TokenHasher};
+use anyhow::Result;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn create_api_key_db(
+    pool: &DatabasePool,
+    user_id: Uuid,
+    name: &str,
+    token: &SplitToken,
+    permissions: &[String],
+    created_by: Uuid,
+    mfa_verified: bool,
+    expires_at: Option<OffsetDateTime>,
+) -> Result<ApiKey> {
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, permissions, created_by, mfa_verified, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, user_id, name, prefix, key_hash, permissions, created_by, mfa_verified, expires_at, last_used_at, revoked_at, created_at
+        "#,
+        user_id,
+        name,
+        format_api_key(&token.selector),
+        token.verifier_hash,
+        permissions,
+        created_by,
+        mfa_verified,
+        expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(api_key)
+}
+
+/// The user's keys, including revoked and expired ones, newest first
+pub async fn find_user_api_keys(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<ApiKey>> {
+    let api_keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, user_id, name, prefix, key_hash, permissions, created_by, mfa_verified, expires_at, last_used_at, revoked_at, created_at
+        FROM api_keys
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(api_keys)
+}
+
+/// Find a key by its presented value (without the `sk_` prefix) and check its secret.
+/// Revoked and expired keys are returned too; callers check `is_active`.
+pub async fn find_api_key(pool: &DatabasePool, hasher: &TokenHasher, token: &str) -> Result<Option<ApiKey>> {
+    let Some((selector, verifier)) = parse_split_token(token) else {
+        return Ok(None);
+    };
+
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT id, user_id, name, prefix, key_hash, permissions, created_by, mfa_verified, expires_at, last_used_at, revoked_at, created_at
+        FROM api_keys
+        WHERE prefix = $1
+        "#,
+        format_api_key(selector)
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(api_key.filter(|k| hasher.verify_token_verifier(verifier, &k.key_hash)))
+}
+
+/// Record a use of the key. Callers skip it while `ApiKey::is_due_for_touch` is false; the
+/// condition here stops concurrent requests from writing it more than once a minute.
+pub async fn touch_api_key(pool: &DatabasePool, api_key_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE api_keys SET last_used_at = NOW()
+        WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+        "#,
+        api_key_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Revoke one of the user's keys; false if there is no such active key
+pub async fn revoke_api_key(pool: &DatabasePool, user_id: Uuid, api_key_id: Uuid) -> Result<bool> {
+    let revoked = sqlx::query!(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        api_key_id,
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(revoked.rows_affected() > 0)
+}
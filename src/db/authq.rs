@@ -1,29 +1,42 @@
 use uuid::Uuid;
 use time::{OffsetDateTime, Duration};
 use sqlx::Result;
-use crate::models::auth::RefreshToken;
+use crate::models::auth::{ClientInfo, EmailVerificationToken, PasswordResetToken, RefreshToken, Session};
+use crate::models::user::{User, UserRole, UserStatus};
 use crate::db::db_con::DatabasePool;
+use crate::utils::token::{parse_split_token, SplitToken, TokenHasher};
 
-/// Create a new refresh token
+/// Create a new refresh token, starting a new family (device session)
 pub async fn create_refresh_token(
         pool: &DatabasePool,
         user_id: Uuid,
-        token: &String,
+        token: &SplitToken,
         expires_in_days: i64,
+        device_label: Option<&str>,
+        mfa_verified: bool,
+        client: &ClientInfo,
     ) -> Result<RefreshToken> {
         //let pool: DatabasePool = create_pool().await.unwrap();
         let expires_at = OffsetDateTime::now_utc() + Duration::days(expires_in_days);
+        let id = Uuid::new_v4();
 
         let refresh_token = sqlx::query_as!(
             RefreshToken,
             r#"
-            INSERT INTO refresh_tokens (user_id, token, expires_at)
-            VALUES ($1, $2, $3)
-            RETURNING id, user_id, token, expires_at, created_at
+            INSERT INTO refresh_tokens (id, family_id, user_id, selector, verifier_hash, expires_at, device_label, user_agent, ip_address, mfa_verified)
+            VALUES ($1, $1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, user_id, family_id, parent_id, selector, verifier_hash, device_label, user_agent, ip_address,
+                      expires_at, last_used_at, rotated_at, revoked_at, mfa_verified, created_at
             "#,
+            id,
             user_id,
-            token,
-            expires_at
+            token.selector,
+            token.verifier_hash,
+            expires_at,
+            device_label,
+            client.user_agent,
+            client.ip_address,
+            mfa_verified
         )
         .fetch_one(pool)
         .await.expect("Failed to create refresh token");
@@ -31,33 +44,177 @@ pub async fn create_refresh_token(
         Ok(refresh_token)
     }
 
-    /// Find a refresh token by its token string
-    pub async fn find_refresh_token(pool: &DatabasePool, token: &str) -> Result<Option<RefreshToken>> {
+    /// Find an unexpired refresh token by its token string, including rotated and revoked ones.
+    /// Looks the row up by selector, then checks the verifier against the stored hash.
+    pub async fn find_refresh_token(
+        pool: &DatabasePool,
+        hasher: &TokenHasher,
+        token: &str,
+    ) -> Result<Option<RefreshToken>> {
+        let Some((selector, verifier)) = parse_split_token(token) else {
+            return Ok(None);
+        };
+
         let refresh_token = sqlx::query_as!(
             RefreshToken,
             r#"
-            SELECT id, user_id, token, expires_at, created_at
+            SELECT id, user_id, family_id, parent_id, selector, verifier_hash, device_label, user_agent, ip_address,
+                   expires_at, last_used_at, rotated_at, revoked_at, mfa_verified, created_at
             FROM refresh_tokens
-            WHERE token = $1 AND expires_at > NOW()
+            WHERE selector = $1 AND expires_at > NOW()
             "#,
-            token
+            selector
         )
         .fetch_optional(pool)
         .await?;
 
-        Ok(refresh_token)
+        Ok(refresh_token.filter(|t| hasher.verify_token_verifier(verifier, &t.verifier_hash)))
     }
 
-    /// Delete a refresh token by its token string
-    pub async fn delete_refresh_token(pool: &DatabasePool, token: &str) -> Result<()> {
-        sqlx::query!(
-            "DELETE FROM refresh_tokens WHERE token = $1",
-            token
+    /// Mark `parent` as rotated and issue its child in the same family.
+    /// Returns `None` if the parent was already rotated or revoked (e.g. a concurrent refresh).
+    pub async fn rotate_refresh_token(
+        pool: &DatabasePool,
+        parent: &RefreshToken,
+        new_token: &SplitToken,
+        expires_in_days: i64,
+        client: &ClientInfo,
+    ) -> Result<Option<RefreshToken>> {
+        let expires_at = OffsetDateTime::now_utc() + Duration::days(expires_in_days);
+
+        let mut tx = pool.begin().await?;
+
+        let rotated = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET rotated_at = NOW()
+            WHERE id = $1 AND rotated_at IS NULL AND revoked_at IS NULL
+            "#,
+            parent.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if rotated.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let refresh_token = sqlx::query_as!(
+            RefreshToken,
+            r#"
+            INSERT INTO refresh_tokens (family_id, parent_id, user_id, selector, verifier_hash, expires_at, device_label, user_agent, ip_address, mfa_verified)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id, user_id, family_id, parent_id, selector, verifier_hash, device_label, user_agent, ip_address,
+                      expires_at, last_used_at, rotated_at, revoked_at, mfa_verified, created_at
+            "#,
+            parent.family_id,
+            parent.id,
+            parent.user_id,
+            new_token.selector,
+            new_token.verifier_hash,
+            expires_at,
+            parent.device_label,
+            client.user_agent.as_ref().or(parent.user_agent.as_ref()),
+            client.ip_address.as_ref().or(parent.ip_address.as_ref()),
+            parent.mfa_verified
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(refresh_token))
+    }
+
+    /// Revoke every token in a family; returns how many were still live
+    pub async fn revoke_token_family(pool: &DatabasePool, family_id: Uuid) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE refresh_tokens SET revoked_at = NOW() WHERE family_id = $1 AND revoked_at IS NULL",
+            family_id
         )
         .execute(pool)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected())
+    }
+
+    /// Active sessions of a user (the live head of each family), most recently used first.
+    /// `current` is always false here; the caller knows which session made the request.
+    pub async fn find_user_sessions(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as!(
+            Session,
+            r#"
+            SELECT t.family_id as id, t.device_label, t.user_agent, t.ip_address, false as "current!",
+                   (SELECT MIN(f.created_at) FROM refresh_tokens f WHERE f.family_id = t.family_id) as "created_at!",
+                   t.last_used_at, t.expires_at
+            FROM refresh_tokens t
+            WHERE t.user_id = $1 AND t.rotated_at IS NULL AND t.revoked_at IS NULL AND t.expires_at > NOW()
+            ORDER BY t.last_used_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Revoke one of the user's sessions; returns false if it does not exist
+    pub async fn delete_user_session(pool: &DatabasePool, user_id: Uuid, session_id: Uuid) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM refresh_tokens WHERE user_id = $1 AND family_id = $2",
+            user_id,
+            session_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revoke every session of the user except `keep`; returns the ids of the sessions that were live
+    pub async fn delete_other_sessions(pool: &DatabasePool, user_id: Uuid, keep: Option<Uuid>) -> Result<Vec<Uuid>> {
+        let revoked = sqlx::query_scalar!(
+            r#"
+            WITH deleted AS (
+                DELETE FROM refresh_tokens
+                WHERE user_id = $1 AND family_id IS DISTINCT FROM $2
+                RETURNING family_id, rotated_at, revoked_at, expires_at
+            )
+            SELECT family_id FROM deleted
+            WHERE rotated_at IS NULL AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+            user_id,
+            keep
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(revoked)
+    }
+
+    /// Drop dead families (expired, revoked or fully rotated), then the oldest live ones beyond `max_sessions`
+    pub async fn evict_excess_sessions(pool: &DatabasePool, user_id: Uuid, max_sessions: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            WITH live AS (
+                SELECT t.family_id,
+                       (SELECT MIN(f.created_at) FROM refresh_tokens f WHERE f.family_id = t.family_id) as started_at
+                FROM refresh_tokens t
+                WHERE t.user_id = $1 AND t.rotated_at IS NULL AND t.revoked_at IS NULL AND t.expires_at > NOW()
+            )
+            DELETE FROM refresh_tokens
+            WHERE user_id = $1
+              AND (family_id NOT IN (SELECT family_id FROM live)
+                   OR family_id IN (SELECT family_id FROM live ORDER BY started_at DESC OFFSET $2))
+            "#,
+            user_id,
+            max_sessions
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
     }
 
     /// Delete all refresh tokens for a user
@@ -70,4 +227,252 @@ pub async fn create_refresh_token(
         .await?;
 
         Ok(())
-    }
\ No newline at end of file
+    }
+
+    /// Issue a password reset token, replacing any the user has not used yet
+    pub async fn create_password_reset_token(
+        pool: &DatabasePool,
+        user_id: Uuid,
+        token: &SplitToken,
+        expires_in_minutes: i64,
+    ) -> Result<()> {
+        let expires_at = OffsetDateTime::now_utc() + Duration::minutes(expires_in_minutes);
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO password_reset_tokens (user_id, selector, verifier_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user_id,
+            token.selector,
+            token.verifier_hash,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Drop reset links that have not been used yet (e.g. after the password changed)
+    pub async fn delete_password_reset_tokens(pool: &DatabasePool, user_id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL",
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Find an unused, unexpired password reset token and check its verifier
+    pub async fn find_password_reset_token(
+        pool: &DatabasePool,
+        hasher: &TokenHasher,
+        token: &str,
+    ) -> Result<Option<PasswordResetToken>> {
+        let Some((selector, verifier)) = parse_split_token(token) else {
+            return Ok(None);
+        };
+
+        let reset_token = sqlx::query_as!(
+            PasswordResetToken,
+            r#"
+            SELECT id, user_id, selector, verifier_hash, expires_at, used_at, created_at
+            FROM password_reset_tokens
+            WHERE selector = $1 AND used_at IS NULL AND expires_at > NOW()
+            "#,
+            selector
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(reset_token.filter(|t| hasher.verify_token_verifier(verifier, &t.verifier_hash)))
+    }
+
+    /// Use the token to set a new password and end every session of the user.
+    /// Returns false if the token was used concurrently.
+    pub async fn reset_password_with_token(
+        pool: &DatabasePool,
+        reset_token: &PasswordResetToken,
+        password_hash: &str,
+    ) -> Result<bool> {
+        let mut tx = pool.begin().await?;
+
+        let used = sqlx::query!(
+            r#"
+            UPDATE password_reset_tokens SET used_at = NOW()
+            WHERE id = $1 AND used_at IS NULL AND expires_at > NOW()
+            "#,
+            reset_token.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if used.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "UPDATE users SET password_hash = $2 WHERE id = $1",
+            reset_token.user_id,
+            password_hash
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM refresh_tokens WHERE user_id = $1", reset_token.user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL",
+            reset_token.user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Issue an email verification token for `email`, replacing the user's earlier ones
+    pub async fn create_email_verification_token(
+        pool: &DatabasePool,
+        user_id: Uuid,
+        email: &str,
+        token: &SplitToken,
+        expires_in_hours: i64,
+    ) -> Result<()> {
+        let expires_at = OffsetDateTime::now_utc() + Duration::hours(expires_in_hours);
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!("DELETE FROM email_verification_tokens WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO email_verification_tokens (user_id, email, selector, verifier_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            user_id,
+            email,
+            token.selector,
+            token.verifier_hash,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// When the user was last sent a verification link, for throttling resends
+    pub async fn find_last_verification_sent(pool: &DatabasePool, user_id: Uuid) -> Result<Option<OffsetDateTime>> {
+        let sent_at = sqlx::query_scalar!(
+            "SELECT MAX(created_at) FROM email_verification_tokens WHERE user_id = $1",
+            user_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(sent_at)
+    }
+
+    /// Find an unexpired email verification token and check its verifier
+    pub async fn find_email_verification_token(
+        pool: &DatabasePool,
+        hasher: &TokenHasher,
+        token: &str,
+    ) -> Result<Option<EmailVerificationToken>> {
+        let Some((selector, verifier)) = parse_split_token(token) else {
+            return Ok(None);
+        };
+
+        let verification_token = sqlx::query_as!(
+            EmailVerificationToken,
+            r#"
+            SELECT id, user_id, email, selector, verifier_hash, expires_at, created_at
+            FROM email_verification_tokens
+            WHERE selector = $1 AND expires_at > NOW()
+            "#,
+            selector
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(verification_token.filter(|t| hasher.verify_token_verifier(verifier, &t.verifier_hash)))
+    }
+
+    pub enum EmailVerificationOutcome {
+        Verified(User),
+        /// The token was already used or the address is no longer the user's
+        Invalid,
+        /// Another account took the address while it was pending
+        EmailTaken,
+    }
+
+    /// Consume the token and mark its address verified, promoting it from pending_email if needed.
+    /// Nothing changes unless the address is verified.
+    pub async fn confirm_email_verification(
+        pool: &DatabasePool,
+        verification_token: &EmailVerificationToken,
+    ) -> Result<EmailVerificationOutcome> {
+        let mut tx = pool.begin().await?;
+
+        let consumed = sqlx::query!(
+            "DELETE FROM email_verification_tokens WHERE id = $1",
+            verification_token.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if consumed.rows_affected() == 0 {
+            return Ok(EmailVerificationOutcome::Invalid);
+        }
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users
+            SET email = $2,
+                pending_email = CASE WHEN pending_email = $2 THEN NULL ELSE pending_email END,
+                email_verified_at = NOW()
+            WHERE id = $1 AND (email = $2 OR pending_email = $2)
+            RETURNING id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
+            "#,
+            verification_token.user_id,
+            verification_token.email
+        )
+        .fetch_optional(&mut *tx)
+        .await;
+
+        let user = match user {
+            Ok(Some(user)) => user,
+            Ok(None) => return Ok(EmailVerificationOutcome::Invalid),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return Ok(EmailVerificationOutcome::EmailTaken)
+            }
+            Err(e) => return Err(e),
+        };
+        tx.commit().await?;
+
+        Ok(EmailVerificationOutcome::Verified(user))
+    }
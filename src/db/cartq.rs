@@ -0,0 +1,254 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::cart::{Cart, CartLine};
+use anyhow::Result;
+use uuid::Uuid;
+
+pub async fn find_or_create_user_cart(pool: &DatabasePool, user_id: Uuid) -> Result<Cart> {
+    let cart = sqlx::query_as!(
+        Cart,
+        r#"
+        INSERT INTO carts (user_id)
+        VALUES ($1)
+        ON CONFLICT (user_id) DO UPDATE SET updated_at = carts.updated_at
+        RETURNING id, user_id, token, coupon_code, currency, created_at
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(cart)
+}
+
+pub async fn find_user_cart(pool: &DatabasePool, user_id: Uuid) -> Result<Option<Cart>> {
+    let cart = sqlx::query_as!(
+        Cart,
+        "SELECT id, user_id, token, coupon_code, currency, created_at FROM carts WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(cart)
+}
+
+pub async fn create_guest_cart(pool: &DatabasePool, token: &str) -> Result<Cart> {
+    let cart = sqlx::query_as!(
+        Cart,
+        r#"
+        INSERT INTO carts (token)
+        VALUES ($1)
+        RETURNING id, user_id, token, coupon_code, currency, created_at
+        "#,
+        token
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(cart)
+}
+
+pub async fn find_guest_cart(pool: &DatabasePool, token: &str) -> Result<Option<Cart>> {
+    let cart = sqlx::query_as!(
+        Cart,
+        "SELECT id, user_id, token, coupon_code, currency, created_at FROM carts WHERE token = $1 AND user_id IS NULL",
+        token
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(cart)
+}
+
+/// Cart lines joined with the current product/variant price, converted into the cart's currency, and stock
+pub async fn find_cart_lines(pool: &DatabasePool, cart_id: Uuid) -> Result<Vec<CartLine>> {
+    let lines = sqlx::query_as!(
+        CartLine,
+        r#"
+        SELECT ci.id, ci.product_id, ci.variant_id, p.category_id, p.name as product_name, v.sku as "sku?",
+               convert_price(COALESCE(v.price, p.price), p.currency, c.currency) as unit_price,
+               COALESCE(v.stock, p.stock) as "available_stock!",
+               ci.quantity
+        FROM cart_items ci
+        JOIN carts c ON c.id = ci.cart_id
+        JOIN products p ON p.id = ci.product_id
+        LEFT JOIN product_variants v ON v.id = ci.variant_id
+        WHERE ci.cart_id = $1
+        ORDER BY ci.created_at ASC
+        "#,
+        cart_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(lines)
+}
+
+pub async fn find_cart_line(pool: &DatabasePool, cart_id: Uuid, item_id: Uuid) -> Result<Option<CartLine>> {
+    let line = sqlx::query_as!(
+        CartLine,
+        r#"
+        SELECT ci.id, ci.product_id, ci.variant_id, p.category_id, p.name as product_name, v.sku as "sku?",
+               convert_price(COALESCE(v.price, p.price), p.currency, c.currency) as unit_price,
+               COALESCE(v.stock, p.stock) as "available_stock!",
+               ci.quantity
+        FROM cart_items ci
+        JOIN carts c ON c.id = ci.cart_id
+        JOIN products p ON p.id = ci.product_id
+        LEFT JOIN product_variants v ON v.id = ci.variant_id
+        WHERE ci.cart_id = $1 AND ci.id = $2
+        "#,
+        cart_id,
+        item_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(line)
+}
+
+/// Quantity of a product/variant already in the cart
+pub async fn find_cart_quantity(
+    pool: &DatabasePool,
+    cart_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+) -> Result<i32> {
+    let quantity = sqlx::query_scalar!(
+        r#"
+        SELECT quantity FROM cart_items
+        WHERE cart_id = $1 AND product_id = $2 AND variant_id IS NOT DISTINCT FROM $3
+        "#,
+        cart_id,
+        product_id,
+        variant_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(quantity.unwrap_or(0))
+}
+
+/// Add an item, or increase its quantity if it is already in the cart
+pub async fn add_cart_item(
+    pool: &DatabasePool,
+    cart_id: Uuid,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    quantity: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO cart_items (cart_id, product_id, variant_id, quantity)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (cart_id, product_id, COALESCE(variant_id, '00000000-0000-0000-0000-000000000000'::uuid))
+        DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity
+        "#,
+        cart_id,
+        product_id,
+        variant_id,
+        quantity
+    )
+    .execute(pool)
+    .await?;
+
+    touch_cart(pool, cart_id).await
+}
+
+pub async fn update_cart_item_quantity(pool: &DatabasePool, cart_id: Uuid, item_id: Uuid, quantity: i32) -> Result<()> {
+    sqlx::query!(
+        "UPDATE cart_items SET quantity = $3 WHERE cart_id = $1 AND id = $2",
+        cart_id,
+        item_id,
+        quantity
+    )
+    .execute(pool)
+    .await?;
+
+    touch_cart(pool, cart_id).await
+}
+
+pub async fn delete_cart_item(pool: &DatabasePool, cart_id: Uuid, item_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM cart_items WHERE cart_id = $1 AND id = $2",
+        cart_id,
+        item_id
+    )
+    .execute(pool)
+    .await?;
+
+    touch_cart(pool, cart_id).await
+}
+
+pub async fn clear_cart(pool: &DatabasePool, cart_id: Uuid) -> Result<()> {
+    sqlx::query!("DELETE FROM cart_items WHERE cart_id = $1", cart_id)
+        .execute(pool)
+        .await?;
+
+    touch_cart(pool, cart_id).await
+}
+
+/// Move every item of a guest cart into a user cart, then drop the guest cart
+pub async fn merge_guest_cart(pool: &DatabasePool, token: &str, user_id: Uuid) -> Result<()> {
+    let Some(guest_cart) = find_guest_cart(pool, token).await? else {
+        return Ok(());
+    };
+    let user_cart = find_or_create_user_cart(pool, user_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO cart_items (cart_id, product_id, variant_id, quantity)
+        SELECT $1, product_id, variant_id, quantity
+        FROM cart_items
+        WHERE cart_id = $2
+        ON CONFLICT (cart_id, product_id, COALESCE(variant_id, '00000000-0000-0000-0000-000000000000'::uuid))
+        DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity
+        "#,
+        user_cart.id,
+        guest_cart.id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!("DELETE FROM carts WHERE id = $1", guest_cart.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    touch_cart(pool, user_cart.id).await
+}
+
+pub async fn set_cart_coupon(pool: &DatabasePool, cart_id: Uuid, code: Option<&str>) -> Result<()> {
+    sqlx::query!(
+        "UPDATE carts SET coupon_code = $2, updated_at = NOW() WHERE id = $1",
+        cart_id,
+        code
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_cart_currency(pool: &DatabasePool, cart_id: Uuid, currency: &str) -> Result<()> {
+    sqlx::query!(
+        "UPDATE carts SET currency = $2, updated_at = NOW() WHERE id = $1",
+        cart_id,
+        currency
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+async fn touch_cart(pool: &DatabasePool, cart_id: Uuid) -> Result<()> {
+    sqlx::query!("UPDATE carts SET updated_at = NOW() WHERE id = $1", cart_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
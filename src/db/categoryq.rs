@@ -7,12 +7,13 @@ pub async fn create_category_db(pool: &DatabasePool, category_data: CreateCatego
         let category = sqlx::query_as!(
             Category,
             r#"
-            INSERT INTO categories (name, description)
-            VALUES ($1, $2)
-            RETURNING id, name, description
+            INSERT INTO categories (name, description, parent_id)
+            VALUES ($1, $2, $3)
+            RETURNING id, name, description, parent_id
             "#,
             category_data.name,
-            category_data.description
+            category_data.description,
+            category_data.parent_id
         )
         .fetch_one(pool)
         .await?;
@@ -23,7 +24,7 @@ pub async fn create_category_db(pool: &DatabasePool, category_data: CreateCatego
     pub async fn find_all_categories(pool: &DatabasePool) -> Result<Vec<Category>> {
         let categories = sqlx::query_as!(
             Category,
-            "SELECT id, name, description FROM categories ORDER BY name"
+            "SELECT id, name, description, parent_id FROM categories ORDER BY name"
         )
         .fetch_all(pool)
         .await?;
@@ -34,7 +35,7 @@ pub async fn create_category_db(pool: &DatabasePool, category_data: CreateCatego
     pub async fn find_category_by_id(pool: &DatabasePool, category_id: Uuid) -> Result<Option<Category>> {
         let category = sqlx::query_as!(
             Category,
-            "SELECT id, name, description FROM categories WHERE id = $1",
+            "SELECT id, name, description, parent_id FROM categories WHERE id = $1",
             category_id
         )
         .fetch_optional(pool)
@@ -43,24 +44,139 @@ pub async fn create_category_db(pool: &DatabasePool, category_data: CreateCatego
         Ok(category)
     }
 
-    pub async fn update_category_db(pool: &DatabasePool, category_id: Uuid, data: UpdateCategory) -> Result<Category> {
+    /// Ancestors of a category ordered from the root down to the direct parent
+    pub async fn find_category_ancestors(pool: &DatabasePool, category_id: Uuid) -> Result<Vec<Category>> {
+        let ancestors = sqlx::query_as!(
+            Category,
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT c.id, c.name, c.description, c.parent_id, 0 AS depth
+                FROM categories c
+                WHERE c.id = (SELECT parent_id FROM categories WHERE id = $1)
+                UNION ALL
+                SELECT c.id, c.name, c.description, c.parent_id, a.depth + 1
+                FROM categories c
+                JOIN ancestors a ON c.id = a.parent_id
+            )
+            SELECT id as "id!", name as "name!", description, parent_id
+            FROM ancestors
+            ORDER BY depth DESC
+            "#,
+            category_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(ancestors)
+    }
+
+    /// Ids of a category and every category nested below it
+    pub async fn find_descendant_ids(pool: &DatabasePool, category_id: Uuid) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            WITH RECURSIVE descendants AS (
+                SELECT id FROM categories WHERE id = $1
+                UNION
+                SELECT c.id
+                FROM categories c
+                JOIN descendants d ON c.parent_id = d.id
+            )
+            SELECT id as "id!" FROM descendants
+            "#,
+            category_id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    pub async fn count_child_categories(pool: &DatabasePool, category_id: Uuid) -> Result<i64> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM categories WHERE parent_id = $1"#,
+            category_id
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Update a category. Moving it checks for cycles in the same transaction, with the category
+    /// and the new parent's ancestor chain locked so two concurrent moves cannot form a loop.
+    /// Returns `None` if the new parent is the category itself or one of its descendants.
+    pub async fn update_category_db(pool: &DatabasePool, category_id: Uuid, data: UpdateCategory) -> Result<Option<Category>> {
+        let make_root = data.make_root.unwrap_or(false);
+
+        let mut tx = pool.begin().await?;
+
+        if let Some(parent_id) = data.parent_id
+            && !make_root
+        {
+            sqlx::query!(
+                r#"
+                WITH RECURSIVE chain AS (
+                    SELECT id, parent_id FROM categories WHERE id = $2
+                    UNION
+                    SELECT c.id, c.parent_id
+                    FROM categories c
+                    JOIN chain ch ON c.id = ch.parent_id
+                )
+                SELECT id FROM categories
+                WHERE id = $1 OR id IN (SELECT id FROM chain)
+                ORDER BY id
+                FOR UPDATE
+                "#,
+                category_id,
+                parent_id
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+
+            let creates_cycle = sqlx::query_scalar!(
+                r#"
+                WITH RECURSIVE chain AS (
+                    SELECT id, parent_id FROM categories WHERE id = $2
+                    UNION
+                    SELECT c.id, c.parent_id
+                    FROM categories c
+                    JOIN chain ch ON c.id = ch.parent_id
+                )
+                SELECT EXISTS (SELECT 1 FROM chain WHERE id = $1) as "exists!"
+                "#,
+                category_id,
+                parent_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if creates_cycle {
+                return Ok(None);
+            }
+        }
+
         let category = sqlx::query_as!(
             Category,
             r#"
             UPDATE categories
             SET name = COALESCE($2, name),
-                description = COALESCE($3, description)
+                description = COALESCE($3, description),
+                parent_id = CASE WHEN $5 THEN NULL ELSE COALESCE($4, parent_id) END
             WHERE id = $1
-            RETURNING id, name, description
+            RETURNING id, name, description, parent_id
             "#,
             category_id,
             data.name,
-            data.description
+            data.description,
+            data.parent_id,
+            make_root
         )
-        .fetch_one(pool)
+        .fetch_one(&mut *tx)
         .await?;
 
-        Ok(category)
+        tx.commit().await?;
+
+        Ok(Some(category))
     }
 
     pub async fn delete_category_db(pool: &DatabasePool, category_id: Uuid) -> Result<()> {
@@ -72,4 +188,31 @@ pub async fn create_category_db(pool: &DatabasePool, category_data: CreateCatego
         .await?;
 
         Ok(())
-    }
\ No newline at end of file
+    }
+
+    /// Move the children of a category up to its parent, then delete it
+    pub async fn reparent_children_and_delete(pool: &DatabasePool, category_id: Uuid) -> Result<()> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE categories
+            SET parent_id = (SELECT parent_id FROM categories WHERE id = $1)
+            WHERE parent_id = $1
+            "#,
+            category_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM categories WHERE id = $1",
+            category_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
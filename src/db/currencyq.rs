@@ -0,0 +1,93 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::currency::{Currency, ExchangeRate};
+use anyhow::Result;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+pub async fn find_all_currencies(pool: &DatabasePool) -> Result<Vec<Currency>> {
+    let currencies = sqlx::query_as!(
+        Currency,
+        "SELECT code, name, decimals FROM currencies ORDER BY code ASC"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(currencies)
+}
+
+pub async fn find_currency(pool: &DatabasePool, code: &str) -> Result<Option<Currency>> {
+    let currency = sqlx::query_as!(
+        Currency,
+        "SELECT code, name, decimals FROM currencies WHERE code = $1",
+        code
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(currency)
+}
+
+pub async fn find_exchange_rates(pool: &DatabasePool) -> Result<Vec<ExchangeRate>> {
+    let rates = sqlx::query_as!(
+        ExchangeRate,
+        r#"
+        SELECT base_currency, quote_currency, rate, updated_by, updated_at
+        FROM exchange_rates
+        ORDER BY base_currency ASC, quote_currency ASC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rates)
+}
+
+/// Rate to convert `from` into `to`, falling back to the inverse pair; `None` when unknown
+pub async fn find_conversion_rate(pool: &DatabasePool, from: &str, to: &str) -> Result<Option<Decimal>> {
+    let rate = sqlx::query_scalar!("SELECT exchange_rate($1, $2)", from, to)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(rate)
+}
+
+/// Create or replace the rate for a currency pair
+pub async fn upsert_exchange_rate(
+    pool: &DatabasePool,
+    base_currency: &str,
+    quote_currency: &str,
+    rate: Decimal,
+    user_id: Option<Uuid>,
+) -> Result<ExchangeRate> {
+    let rate = sqlx::query_as!(
+        ExchangeRate,
+        r#"
+        INSERT INTO exchange_rates (base_currency, quote_currency, rate, updated_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (base_currency, quote_currency)
+        DO UPDATE SET rate = EXCLUDED.rate, updated_by = EXCLUDED.updated_by, updated_at = NOW()
+        RETURNING base_currency, quote_currency, rate, updated_by, updated_at
+        "#,
+        base_currency,
+        quote_currency,
+        rate,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(rate)
+}
+
+/// Returns false if the pair had no rate
+pub async fn delete_exchange_rate(pool: &DatabasePool, base_currency: &str, quote_currency: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM exchange_rates WHERE base_currency = $1 AND quote_currency = $2",
+        base_currency,
+        quote_currency
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,184 @@
+use crate::db::db_con::DatabasePool;
+use crate::db::notificationq::queue_back_in_stock_notifications;
+use crate::models::inventory::{StockMovement, StockMovementKind};
+use crate::models::other::PaginatedResponse;
+use anyhow::Result;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Append a ledger entry; the caller is responsible for updating `products.stock`
+/// (or the variant's stock when `variant_id` is set) in the same transaction
+#[allow(clippy::too_many_arguments)]
+pub async fn record_stock_movement(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    kind: StockMovementKind,
+    quantity: i32,
+    balance_after: i32,
+    reason: Option<String>,
+    user_id: Option<Uuid>,
+) -> Result<StockMovement> {
+    let movement = sqlx::query_as!(
+        StockMovement,
+        r#"
+        INSERT INTO stock_movements (product_id, variant_id, kind, quantity, balance_after, reason, user_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, product_id, variant_id, kind as "kind: StockMovementKind", quantity, balance_after,
+                  reason, user_id, created_at
+        "#,
+        product_id,
+        variant_id,
+        kind as StockMovementKind,
+        quantity,
+        balance_after,
+        reason,
+        user_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok(movement)
+}
+
+/// Apply a stock delta inside an existing transaction.
+/// Returns `None` without touching anything if the result would go negative.
+/// Coming back from zero queues back-in-stock notifications for subscribers.
+pub async fn apply_stock_delta(
+    conn: &mut PgConnection,
+    product_id: Uuid,
+    delta: i32,
+    kind: StockMovementKind,
+    reason: Option<String>,
+    user_id: Option<Uuid>,
+) -> Result<Option<StockMovement>> {
+    let balance = sqlx::query_scalar!(
+        r#"
+        UPDATE products
+        SET stock = stock + $2
+        WHERE id = $1 AND stock + $2 >= 0
+        RETURNING stock
+        "#,
+        product_id,
+        delta
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(balance) = balance else {
+        return Ok(None);
+    };
+
+    let movement = record_stock_movement(&mut *conn, product_id, None, kind, delta, balance, reason, user_id).await?;
+
+    if delta > 0 && balance == delta {
+        queue_back_in_stock_notifications(conn, product_id).await?;
+    }
+
+    Ok(Some(movement))
+}
+
+/// Apply a stock delta to a variant inside an existing transaction, recording it in the ledger.
+/// Returns `None` without touching anything if the result would go negative.
+pub async fn apply_variant_stock_delta(
+    conn: &mut PgConnection,
+    variant_id: Uuid,
+    delta: i32,
+    kind: StockMovementKind,
+    reason: Option<String>,
+    user_id: Option<Uuid>,
+) -> Result<Option<StockMovement>> {
+    let variant = sqlx::query!(
+        r#"
+        UPDATE product_variants
+        SET stock = stock + $2
+        WHERE id = $1 AND stock + $2 >= 0
+        RETURNING product_id, stock
+        "#,
+        variant_id,
+        delta
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(variant) = variant else {
+        return Ok(None);
+    };
+
+    let movement = record_stock_movement(
+        conn,
+        variant.product_id,
+        Some(variant_id),
+        kind,
+        delta,
+        variant.stock,
+        reason,
+        user_id,
+    )
+    .await?;
+
+    Ok(Some(movement))
+}
+
+/// Atomically adjust stock and record the movement
+pub async fn adjust_stock(
+    pool: &DatabasePool,
+    product_id: Uuid,
+    delta: i32,
+    kind: StockMovementKind,
+    reason: Option<String>,
+    user_id: Option<Uuid>,
+) -> Result<Option<StockMovement>> {
+    let mut tx = pool.begin().await?;
+
+    let movement = apply_stock_delta(&mut tx, product_id, delta, kind, reason, user_id).await?;
+    if movement.is_some() {
+        tx.commit().await?;
+    }
+
+    Ok(movement)
+}
+
+pub async fn find_stock_movements(
+    pool: &DatabasePool,
+    product_id: Uuid,
+    page: Option<u32>,
+    per_page: Option<u32>,
+) -> Result<PaginatedResponse<StockMovement>> {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let total_items = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM stock_movements WHERE product_id = $1"#,
+        product_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let movements = sqlx::query_as!(
+        StockMovement,
+        r#"
+        SELECT id, product_id, variant_id, kind as "kind: StockMovementKind", quantity, balance_after,
+               reason, user_id, created_at
+        FROM stock_movements
+        WHERE product_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+        product_id,
+        per_page as i64,
+        offset as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(PaginatedResponse {
+        item_on_page: Some(movements.len() as u32),
+        data: movements,
+        current_page: page,
+        total_items: total_items as u32,
+        per_page,
+        total_pages: (total_items as u32).div_ceil(per_page),
+    })
+}
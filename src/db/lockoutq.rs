@@ -0,0 +1,129 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::lockout::{LoginThrottle, LockoutQuery, ThrottlePolicy, ThrottleScope};
+use crate::models::other::PaginatedResponse;
+use anyhow::Result;
+use time::OffsetDateTime;
+
+pub async fn find_login_throttle(pool: &DatabasePool, scope: ThrottleScope, key: &str) -> Result<Option<LoginThrottle>> {
+    let throttle = sqlx::query_as!(
+        LoginThrottle,
+        r#"
+        SELECT scope as "scope: ThrottleScope", key, failures, last_failure_at, locked_until
+        FROM login_throttles
+        WHERE scope = $1 AND key = $2
+        "#,
+        scope as ThrottleScope,
+        key
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(throttle)
+}
+
+/// Count a failed login and apply the policy's back-off. The counter restarts when the
+/// previous failure is older than the policy window.
+pub async fn record_login_failure(
+    pool: &DatabasePool,
+    scope: ThrottleScope,
+    key: &str,
+    policy: &ThrottlePolicy,
+) -> Result<LoginThrottle> {
+    let now = OffsetDateTime::now_utc();
+
+    let mut tx = pool.begin().await?;
+
+    let failures = sqlx::query_scalar!(
+        r#"
+        INSERT INTO login_throttles (scope, key, failures, last_failure_at)
+        VALUES ($1, $2, 1, NOW())
+        ON CONFLICT (scope, key) DO UPDATE
+        SET failures = CASE WHEN login_throttles.last_failure_at < $3 THEN 1 ELSE login_throttles.failures + 1 END,
+            last_failure_at = NOW()
+        RETURNING failures
+        "#,
+        scope as ThrottleScope,
+        key,
+        now - policy.window
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let locked_until = policy.delay_after(failures).map(|delay| now + delay);
+
+    let throttle = sqlx::query_as!(
+        LoginThrottle,
+        r#"
+        UPDATE login_throttles
+        SET locked_until = GREATEST(locked_until, $3)
+        WHERE scope = $1 AND key = $2
+        RETURNING scope as "scope: ThrottleScope", key, failures, last_failure_at, locked_until
+        "#,
+        scope as ThrottleScope,
+        key,
+        locked_until
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(throttle)
+}
+
+/// Forget the failures of an email or IP (successful login, or an admin clearing a lockout)
+pub async fn clear_login_throttle(pool: &DatabasePool, scope: ThrottleScope, key: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM login_throttles WHERE scope = $1 AND key = $2",
+        scope as ThrottleScope,
+        key
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn find_login_throttles(pool: &DatabasePool, query: &LockoutQuery) -> Result<PaginatedResponse<LoginThrottle>> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+    let locked_only = query.locked_only.unwrap_or(false);
+
+    let total_items = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!" FROM login_throttles
+        WHERE ($1::throttle_scope IS NULL OR scope = $1) AND (NOT $2 OR locked_until > NOW())
+        "#,
+        query.scope as Option<ThrottleScope>,
+        locked_only
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let throttles = sqlx::query_as!(
+        LoginThrottle,
+        r#"
+        SELECT scope as "scope: ThrottleScope", key, failures, last_failure_at, locked_until
+        FROM login_throttles
+        WHERE ($1::throttle_scope IS NULL OR scope = $1) AND (NOT $2 OR locked_until > NOW())
+        ORDER BY locked_until DESC NULLS LAST, last_failure_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        query.scope as Option<ThrottleScope>,
+        locked_only,
+        per_page as i64,
+        offset as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(PaginatedResponse {
+        item_on_page: Some(throttles.len() as u32),
+        data: throttles,
+        current_page: page,
+        total_items: total_items as u32,
+        per_page,
+        total_pages: (total_items as u32).div_ceil(per_page),
+    })
+}
@@ -0,0 +1,243 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::mfa::{MfaChallenge, UserTotp, MFA_MAX_ATTEMPTS};
+use crate::utils::token::{parse_split_token, SplitToken, TokenHasher};
+use anyhow::Result;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+pub async fn find_user_totp(pool: &DatabasePool, user_id: Uuid) -> Result<Option<UserTotp>> {
+    let totp = sqlx::query_as!(
+        UserTotp,
+        "SELECT user_id, secret, confirmed_at, last_used_step, created_at FROM user_totp WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(totp)
+}
+
+/// Start (or restart) an enrollment with a new secret. `None` if TOTP is already enabled.
+pub async fn upsert_pending_totp(pool: &DatabasePool, user_id: Uuid, secret: &str) -> Result<Option<UserTotp>> {
+    let totp = sqlx::query_as!(
+        UserTotp,
+        r#"
+        INSERT INTO user_totp (user_id, secret)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE
+        SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW()
+        WHERE user_totp.confirmed_at IS NULL
+        RETURNING user_id, secret, confirmed_at, last_used_step, created_at
+        "#,
+        user_id,
+        secret
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(totp)
+}
+
+/// Enable TOTP with the step of the confirming code and store the first recovery codes.
+/// Returns false if the enrollment was confirmed concurrently.
+pub async fn confirm_totp(pool: &DatabasePool, user_id: Uuid, step: i64, code_hashes: &[String]) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let confirmed = sqlx::query!(
+        r#"
+        UPDATE user_totp SET confirmed_at = NOW(), last_used_step = $2
+        WHERE user_id = $1 AND confirmed_at IS NULL
+        "#,
+        user_id,
+        step
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if confirmed.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::varchar[])",
+        user_id,
+        code_hashes
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Record an accepted time step; false if that step (or a later one) was already used
+pub async fn use_totp_step(pool: &DatabasePool, user_id: Uuid, step: i64) -> Result<bool> {
+    let used = sqlx::query!(
+        r#"
+        UPDATE user_totp SET last_used_step = $2
+        WHERE user_id = $1 AND confirmed_at IS NOT NULL
+          AND (last_used_step IS NULL OR last_used_step < $2)
+        "#,
+        user_id,
+        step
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(used.rows_affected() > 0)
+}
+
+/// Replace every recovery code of the user
+pub async fn replace_recovery_codes(pool: &DatabasePool, user_id: Uuid, code_hashes: &[String]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO mfa_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::varchar[])",
+        user_id,
+        code_hashes
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Spend a recovery code; false if it does not exist or was already used
+pub async fn use_recovery_code(pool: &DatabasePool, user_id: Uuid, code_hash: &str) -> Result<bool> {
+    let used = sqlx::query!(
+        r#"
+        UPDATE mfa_recovery_codes SET used_at = NOW()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        code_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(used.rows_affected() > 0)
+}
+
+pub async fn count_recovery_codes(pool: &DatabasePool, user_id: Uuid) -> Result<i64> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM mfa_recovery_codes WHERE user_id = $1 AND used_at IS NULL"#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Turn TOTP off, dropping the secret, recovery codes and pending challenges
+pub async fn delete_user_totp(pool: &DatabasePool, user_id: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM mfa_challenges WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Issue a login challenge, clearing the user's expired ones
+pub async fn create_mfa_challenge(
+    pool: &DatabasePool,
+    user_id: Uuid,
+    token: &SplitToken,
+    device_label: Option<&str>,
+    cart_token: Option<&str>,
+    expires_in_minutes: i64,
+) -> Result<MfaChallenge> {
+    let expires_at = OffsetDateTime::now_utc() + Duration::minutes(expires_in_minutes);
+
+    sqlx::query!(
+        "DELETE FROM mfa_challenges WHERE user_id = $1 AND expires_at <= NOW()",
+        user_id
+    )
+    .execute(pool)
+    .await?;
+
+    let challenge = sqlx::query_as!(
+        MfaChallenge,
+        r#"
+        INSERT INTO mfa_challenges (user_id, selector, verifier_hash, device_label, cart_token, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, user_id, selector, verifier_hash, device_label, cart_token, attempts, expires_at, created_at
+        "#,
+        user_id,
+        token.selector,
+        token.verifier_hash,
+        device_label,
+        cart_token,
+        expires_at
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(challenge)
+}
+
+/// Find an unexpired challenge with attempts left and check its verifier
+pub async fn find_mfa_challenge(pool: &DatabasePool, hasher: &TokenHasher, token: &str) -> Result<Option<MfaChallenge>> {
+    let Some((selector, verifier)) = parse_split_token(token) else {
+        return Ok(None);
+    };
+
+    let challenge = sqlx::query_as!(
+        MfaChallenge,
+        r#"
+        SELECT id, user_id, selector, verifier_hash, device_label, cart_token, attempts, expires_at, created_at
+        FROM mfa_challenges
+        WHERE selector = $1 AND expires_at > NOW() AND attempts < $2
+        "#,
+        selector,
+        MFA_MAX_ATTEMPTS
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(challenge.filter(|c| hasher.verify_token_verifier(verifier, &c.verifier_hash)))
+}
+
+/// Count a wrong code against the challenge; returns the attempts so far
+pub async fn record_mfa_attempt(pool: &DatabasePool, challenge_id: Uuid) -> Result<i32> {
+    let attempts = sqlx::query_scalar!(
+        "UPDATE mfa_challenges SET attempts = attempts + 1 WHERE id = $1 RETURNING attempts",
+        challenge_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(attempts.unwrap_or(MFA_MAX_ATTEMPTS))
+}
+
+/// Consume a challenge; false if it was already used
+pub async fn delete_mfa_challenge(pool: &DatabasePool, challenge_id: Uuid) -> Result<bool> {
+    let deleted = sqlx::query!("DELETE FROM mfa_challenges WHERE id = $1", challenge_id)
+        .execute(pool)
+        .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
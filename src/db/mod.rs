@@ -1,6 +1,22 @@
+pub mod apikeyq;
 pub mod authq;
+pub mod cartq;
 pub mod categoryq;
+pub mod currencyq;
+pub mod inventoryq;
+pub mod lockoutq;
+pub mod mfaq;
+pub mod notificationq;
+pub mod orderq;
+pub mod paymentq;
 pub mod productq;
+pub mod promotionq;
+pub mod reviewq;
+pub mod revocationq;
+pub mod roleq;
+pub mod securityq;
 pub mod userq;
+pub mod variantq;
+pub mod wishlistq;
 pub mod db_con;
 pub mod searech;
\ No newline at end of file
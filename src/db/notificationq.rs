@@ -0,0 +1,118 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::notification::{NotificationStatus, OutgoingNotification, BACK_IN_STOCK};
+use anyhow::Result;
+use sqlx::PgConnection;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// Queue a back-in-stock notification for every subscriber of the product and
+/// drop their subscriptions. Call inside the transaction that restocked the product.
+pub async fn queue_back_in_stock_notifications(conn: &mut PgConnection, product_id: Uuid) -> Result<u64> {
+    let result = sqlx::query!(
+        r#"
+        WITH subscribers AS (
+            DELETE FROM stock_subscriptions
+            WHERE product_id = $1
+            RETURNING user_id
+        )
+        INSERT INTO notifications (user_id, kind, subject, body)
+        SELECT s.user_id, $2,
+               p.name || ' is back in stock',
+               'Good news: ' || p.name || ' is available again (' || p.stock || ' in stock).'
+        FROM subscribers s
+        JOIN products p ON p.id = $1
+        "#,
+        product_id,
+        BACK_IN_STOCK
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Claim pending notifications (oldest first) that have not used up their attempts for
+/// `claim_minutes`. Rows claimed by another dispatcher are skipped until their claim lapses.
+pub async fn claim_pending_notifications(
+    pool: &DatabasePool,
+    max_attempts: i32,
+    limit: i64,
+    claim_minutes: i64,
+) -> Result<Vec<OutgoingNotification>> {
+    let claimed_until = OffsetDateTime::now_utc() + Duration::minutes(claim_minutes);
+
+    let notifications = sqlx::query_as!(
+        OutgoingNotification,
+        r#"
+        WITH claimable AS (
+            SELECT id FROM notifications
+            WHERE status = 'pending' AND attempts < $1
+              AND (claimed_until IS NULL OR claimed_until < NOW())
+            ORDER BY created_at ASC
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED
+        ), claimed AS (
+            UPDATE notifications n
+            SET claimed_until = $3
+            FROM claimable c
+            WHERE n.id = c.id
+            RETURNING n.id, n.user_id, n.kind, n.subject, n.body, n.attempts, n.created_at
+        )
+        SELECT n.id as "id!", n.user_id as "user_id!", u.email, u.username, n.kind as "kind!",
+               n.subject as "subject!", n.body as "body!", n.attempts as "attempts!", n.created_at as "created_at!"
+        FROM claimed n
+        JOIN users u ON u.id = n.user_id
+        ORDER BY n.created_at ASC
+        "#,
+        max_attempts,
+        limit,
+        claimed_until
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(notifications)
+}
+
+pub async fn mark_notification_sent(pool: &DatabasePool, notification_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE notifications
+        SET status = $2, attempts = attempts + 1, sent_at = NOW(), last_error = NULL, claimed_until = NULL
+        WHERE id = $1
+        "#,
+        notification_id,
+        NotificationStatus::Sent as NotificationStatus
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a failed attempt; the notification is given up on once it runs out of attempts
+pub async fn mark_notification_failed(
+    pool: &DatabasePool,
+    notification_id: Uuid,
+    error: &str,
+    max_attempts: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE notifications
+        SET attempts = attempts + 1,
+            last_error = $2,
+            claimed_until = NULL,
+            status = CASE WHEN attempts + 1 >= $3 THEN $4 ELSE status END
+        WHERE id = $1
+        "#,
+        notification_id,
+        error,
+        max_attempts,
+        NotificationStatus::Failed as NotificationStatus
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,317 @@
+use crate::db::db_con::DatabasePool;
+use crate::db::inventoryq::{apply_stock_delta, apply_variant_stock_delta};
+use crate::db::promotionq::{promotion_has_uses_left, record_redemption};
+use crate::models::cart::{Cart, CartLine};
+use crate::models::inventory::StockMovementKind;
+use crate::models::order::{Order, OrderFilter, OrderItem, OrderStatus, OrderWithItems};
+use crate::models::other::PaginatedResponse;
+use crate::models::promotion::AppliedPromotion;
+use anyhow::{anyhow, Result};
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+/// Move stock for every order line: negative delta for sales, positive to put it back
+async fn move_order_stock(
+    conn: &mut PgConnection,
+    order_id: Uuid,
+    items: &[(Option<Uuid>, Option<Uuid>, i32)],
+    kind: StockMovementKind,
+    user_id: Option<Uuid>,
+) -> Result<bool> {
+    let sign = if kind == StockMovementKind::Sale { -1 } else { 1 };
+
+    for (product_id, variant_id, quantity) in items {
+        let delta = sign * quantity;
+        let reason = Some(format!("Order {}", order_id));
+        let moved = match (product_id, variant_id) {
+            (_, Some(variant_id)) => apply_variant_stock_delta(conn, *variant_id, delta, kind, reason, user_id)
+                .await?
+                .is_some(),
+            (Some(product_id), None) => apply_stock_delta(conn, *product_id, delta, kind, reason, user_id)
+                .await?
+                .is_some(),
+            // Product or variant was deleted since the order was placed, nothing to move
+            (None, None) => true,
+        };
+
+        if !moved {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+pub enum CheckoutOutcome {
+    Placed(OrderWithItems),
+    OutOfStock,
+    /// Name of the promotion that ran out of uses since the cart was priced
+    PromotionUnavailable(String),
+}
+
+/// Turn cart lines into a pending order in the cart's currency, redeem the applied promotions,
+/// decrement stock and empty the cart in one transaction. Nothing changes unless the order is placed.
+pub async fn create_order_from_cart(
+    pool: &DatabasePool,
+    user_id: Uuid,
+    cart: &Cart,
+    lines: &[CartLine],
+    promotions: &[AppliedPromotion],
+) -> Result<CheckoutOutcome> {
+    let unit_prices = lines
+        .iter()
+        .map(|line| {
+            line.unit_price
+                .ok_or_else(|| anyhow!("No {} price for cart item {}", cart.currency, line.id))
+        })
+        .collect::<Result<Vec<Decimal>>>()?;
+    let subtotal: Decimal = lines
+        .iter()
+        .zip(&unit_prices)
+        .map(|(line, unit_price)| unit_price * Decimal::from(line.quantity))
+        .sum();
+    let discount: Decimal = promotions.iter().map(|p| p.discount).sum::<Decimal>().min(subtotal);
+
+    let mut tx = pool.begin().await?;
+
+    for promotion in promotions {
+        if !promotion_has_uses_left(&mut tx, promotion.promotion_id, user_id).await? {
+            return Ok(CheckoutOutcome::PromotionUnavailable(promotion.name.clone()));
+        }
+    }
+
+    let order = sqlx::query_as!(
+        Order,
+        r#"
+        INSERT INTO orders (user_id, status, subtotal, discount, total, currency)
+        VALUES ($1, 'pending', $2, $3, $4, $5)
+        RETURNING id, user_id, status as "status: OrderStatus", subtotal, discount, total, currency, created_at, updated_at
+        "#,
+        user_id,
+        subtotal,
+        discount,
+        subtotal - discount,
+        cart.currency
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let stock_lines: Vec<_> = lines
+        .iter()
+        .map(|line| (Some(line.product_id), line.variant_id, line.quantity))
+        .collect();
+    if !move_order_stock(&mut tx, order.id, &stock_lines, StockMovementKind::Sale, Some(user_id)).await? {
+        return Ok(CheckoutOutcome::OutOfStock);
+    }
+
+    for promotion in promotions {
+        record_redemption(&mut tx, promotion.promotion_id, order.id, user_id, promotion.discount).await?;
+    }
+
+    let mut items = Vec::with_capacity(lines.len());
+    for (line, unit_price) in lines.iter().zip(unit_prices) {
+        let item = sqlx::query_as!(
+            OrderItem,
+            r#"
+            INSERT INTO order_items (order_id, product_id, variant_id, product_name, sku, unit_price, quantity, line_total)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, order_id, product_id, variant_id, product_name, sku, unit_price, quantity, line_total
+            "#,
+            order.id,
+            line.product_id,
+            line.variant_id,
+            line.product_name,
+            line.sku,
+            unit_price,
+            line.quantity,
+            unit_price * Decimal::from(line.quantity)
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        items.push(item);
+    }
+
+    sqlx::query!("DELETE FROM cart_items WHERE cart_id = $1", cart.id)
+        .execute(&mut *tx)
+        .await?;
+
+    // A coupon is spent once the order is placed
+    sqlx::query!("UPDATE carts SET coupon_code = NULL WHERE id = $1", cart.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(CheckoutOutcome::Placed(OrderWithItems { order, items }))
+}
+
+pub async fn find_order_by_id(pool: &DatabasePool, order_id: Uuid) -> Result<Option<Order>> {
+    let order = sqlx::query_as!(
+        Order,
+        r#"
+        SELECT id, user_id, status as "status: OrderStatus", subtotal, discount, total, currency, created_at, updated_at
+        FROM orders
+        WHERE id = $1
+        "#,
+        order_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(order)
+}
+
+pub async fn find_order_items(pool: &DatabasePool, order_id: Uuid) -> Result<Vec<OrderItem>> {
+    let items = sqlx::query_as!(
+        OrderItem,
+        r#"
+        SELECT id, order_id, product_id, variant_id, product_name, sku, unit_price, quantity, line_total
+        FROM order_items
+        WHERE order_id = $1
+        ORDER BY product_name ASC
+        "#,
+        order_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+pub async fn find_order_with_items(pool: &DatabasePool, order_id: Uuid) -> Result<Option<OrderWithItems>> {
+    let Some(order) = find_order_by_id(pool, order_id).await? else {
+        return Ok(None);
+    };
+    let items = find_order_items(pool, order_id).await?;
+
+    Ok(Some(OrderWithItems { order, items }))
+}
+
+/// Every order of the user with its items, newest first
+pub async fn find_user_orders_with_items(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<OrderWithItems>> {
+    let orders = sqlx::query_as!(
+        Order,
+        r#"
+        SELECT id, user_id, status as "status: OrderStatus", subtotal, discount, total, currency, created_at, updated_at
+        FROM orders
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut with_items = Vec::with_capacity(orders.len());
+    for order in orders {
+        let items = find_order_items(pool, order.id).await?;
+        with_items.push(OrderWithItems { order, items });
+    }
+
+    Ok(with_items)
+}
+
+pub async fn find_orders(pool: &DatabasePool, filter: &OrderFilter) -> Result<PaginatedResponse<Order>> {
+    let mut count_builder: QueryBuilder<'_, Postgres> = QueryBuilder::new("SELECT COUNT(*) FROM orders WHERE 1=1");
+    let mut order_builder: QueryBuilder<'_, Postgres> = QueryBuilder::new(
+        "SELECT id, user_id, status, subtotal, discount, total, currency, created_at, updated_at FROM orders WHERE 1=1",
+    );
+
+    // Apply filters to both builders
+    for builder in [&mut count_builder, &mut order_builder] {
+        if let Some(status) = filter.status {
+            builder.push(" AND status = ").push_bind(status);
+        }
+        if let Some(user_id) = filter.user_id {
+            builder.push(" AND user_id = ").push_bind(user_id);
+        }
+        if let Some(from) = filter.from {
+            builder.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            builder.push(" AND created_at < ").push_bind(to);
+        }
+    }
+
+    let total_items: (i64,) = count_builder
+        .build_query_as()
+        .fetch_one(pool)
+        .await?;
+
+    // Pagination
+    let page = filter.page.unwrap_or(1).max(1);
+    let per_page = filter.per_page.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let orders = order_builder
+        .push(" ORDER BY created_at DESC")
+        .push(" LIMIT ").push_bind(per_page as i64)
+        .push(" OFFSET ").push_bind(offset as i64)
+        .build_query_as::<Order>()
+        .fetch_all(pool)
+        .await?;
+
+    Ok(PaginatedResponse {
+        item_on_page: Some(orders.len() as u32),
+        data: orders,
+        current_page: page,
+        total_items: total_items.0 as u32,
+        per_page,
+        total_pages: (total_items.0 as u32).div_ceil(per_page),
+    })
+}
+
+/// Compare-and-set the order status. Cancelling puts the stock back.
+/// Returns `None` if the order was no longer in the `from` state.
+pub async fn transition_order_status(
+    pool: &DatabasePool,
+    order_id: Uuid,
+    from: OrderStatus,
+    to: OrderStatus,
+    user_id: Option<Uuid>,
+) -> Result<Option<Order>> {
+    let mut tx = pool.begin().await?;
+
+    let order = sqlx::query_as!(
+        Order,
+        r#"
+        UPDATE orders
+        SET status = $3, updated_at = NOW()
+        WHERE id = $1 AND status = $2
+        RETURNING id, user_id, status as "status: OrderStatus", subtotal, discount, total, currency, created_at, updated_at
+        "#,
+        order_id,
+        from as OrderStatus,
+        to as OrderStatus
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(order) = order else {
+        return Ok(None);
+    };
+
+    if to == OrderStatus::Cancelled {
+        let items = sqlx::query!(
+            "SELECT product_id, variant_id, sku, quantity FROM order_items WHERE order_id = $1",
+            order_id
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| match (row.variant_id, row.sku) {
+            // Only variant lines have a SKU. If the variant was deleted, its units never came
+            // out of the product's stock, so there is nothing to put back.
+            (None, Some(_)) => (None, None, row.quantity),
+            (variant_id, _) => (row.product_id, variant_id, row.quantity),
+        })
+        .collect::<Vec<_>>();
+
+        move_order_stock(&mut tx, order_id, &items, StockMovementKind::Return, user_id).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(order))
+}
@@ -0,0 +1,120 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::payment::{Payment, PaymentKind, PaymentStatus};
+use anyhow::Result;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+pub async fn create_payment(
+    pool: &DatabasePool,
+    order_id: Uuid,
+    provider: &str,
+    provider_ref: &str,
+    kind: PaymentKind,
+    status: PaymentStatus,
+    amount: Decimal,
+) -> Result<Payment> {
+    let payment = sqlx::query_as!(
+        Payment,
+        r#"
+        INSERT INTO payments (order_id, provider, provider_ref, kind, status, amount)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (provider, provider_ref, kind) DO UPDATE SET updated_at = NOW()
+        RETURNING id, order_id, provider, provider_ref, kind as "kind: PaymentKind",
+                  status as "status: PaymentStatus", amount, created_at, updated_at
+        "#,
+        order_id,
+        provider,
+        provider_ref,
+        kind as PaymentKind,
+        status as PaymentStatus,
+        amount
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(payment)
+}
+
+pub async fn find_payment_by_ref(
+    pool: &DatabasePool,
+    provider: &str,
+    provider_ref: &str,
+    kind: PaymentKind,
+) -> Result<Option<Payment>> {
+    let payment = sqlx::query_as!(
+        Payment,
+        r#"
+        SELECT id, order_id, provider, provider_ref, kind as "kind: PaymentKind",
+               status as "status: PaymentStatus", amount, created_at, updated_at
+        FROM payments
+        WHERE provider = $1 AND provider_ref = $2 AND kind = $3
+        "#,
+        provider,
+        provider_ref,
+        kind as PaymentKind
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(payment)
+}
+
+pub async fn find_payments_by_order(pool: &DatabasePool, order_id: Uuid) -> Result<Vec<Payment>> {
+    let payments = sqlx::query_as!(
+        Payment,
+        r#"
+        SELECT id, order_id, provider, provider_ref, kind as "kind: PaymentKind",
+               status as "status: PaymentStatus", amount, created_at, updated_at
+        FROM payments
+        WHERE order_id = $1
+        ORDER BY created_at ASC
+        "#,
+        order_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(payments)
+}
+
+pub async fn update_payment_status(pool: &DatabasePool, payment_id: Uuid, status: PaymentStatus) -> Result<()> {
+    sqlx::query!(
+        "UPDATE payments SET status = $2, updated_at = NOW() WHERE id = $1",
+        payment_id,
+        status as PaymentStatus
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remember a webhook event; returns false if it was already handled
+pub async fn record_webhook_event(pool: &DatabasePool, provider: &str, event_id: &str) -> Result<bool> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO payment_webhook_events (provider, event_id)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+        provider,
+        event_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Forget a webhook event so a redelivery is processed again (used when handling failed)
+pub async fn forget_webhook_event(pool: &DatabasePool, provider: &str, event_id: &str) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM payment_webhook_events WHERE provider = $1 AND event_id = $2",
+        provider,
+        event_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -1,40 +1,75 @@
+use crate::db::categoryq::find_descendant_ids;
 use crate::db::db_con::DatabasePool;
+use crate::db::inventoryq::{apply_stock_delta, record_stock_movement};
+use crate::models::currency::DEFAULT_CURRENCY;
+use crate::models::inventory::StockMovementKind;
+use crate::db::variantq::find_variants_by_product;
 use crate::models::other::PaginatedResponse;
-use crate::models::product::{CreateProduct, Product, ProductWithCategory, UpdateProduct,ProductFilter};
+use crate::models::product::{CreateProduct, Product, ProductWithCategory, UpdateProduct, ProductFilter, ProductSort};
 use anyhow::Result;
 use uuid::Uuid;
 use rust_decimal::Decimal;
 use sqlx::{Postgres, QueryBuilder};
 
-pub async fn create_product_db(pool: &DatabasePool, product_data: CreateProduct) -> Result<Product> {
+pub async fn create_product_db(
+    pool: &DatabasePool,
+    product_data: CreateProduct,
+    user_id: Option<Uuid>,
+) -> Result<Product> {
     // Parse price string to Decimal
     let price = Decimal::from_str_exact(&product_data.price).map_err(|_| {
         anyhow::anyhow!("Invalid price format")
     })?;
 
+    let mut tx = pool.begin().await?;
+
     let product = sqlx::query_as!(
         Product,
         r#"
-        INSERT INTO products (name, description, price, category_id, stock)
-        VALUES ($1, $2, $3, $4, $5)
-        RETURNING id, name, description, price, category_id, image_url, stock, created_at
+        INSERT INTO products (name, description, price, currency, category_id, stock)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, description, price, currency, category_id, image_url, stock,
+                  average_rating, review_count, created_at
         "#,
         product_data.name,
         product_data.description,
         price,
+        product_data.currency.as_deref().unwrap_or(DEFAULT_CURRENCY),
         product_data.category_id,
         product_data.stock
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    // Initial stock enters the ledger as a receipt
+    if product.stock > 0 {
+        record_stock_movement(
+            &mut tx,
+            product.id,
+            None,
+            StockMovementKind::Receipt,
+            product.stock,
+            product.stock,
+            Some("Initial stock".to_string()),
+            user_id,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
     Ok(product)
 }
 
 pub async fn find_product_by_id(pool: &DatabasePool, product_id: Uuid) -> Result<Option<Product>> {
     let product = sqlx::query_as!(
         Product,
-        "SELECT id, name, description, price, category_id, image_url, stock, created_at FROM products WHERE id = $1",
+        r#"
+        SELECT id, name, description, price, currency, category_id, image_url, stock,
+               average_rating, review_count, created_at
+        FROM products
+        WHERE id = $1
+        "#,
         product_id
     )
     .fetch_optional(pool)
@@ -50,7 +85,8 @@ pub async fn find_product_with_category_by_id(
     let product = sqlx::query!(
         r#"
         SELECT 
-            p.id, p.name, p.description, p.price, p.category_id, p.image_url, p.stock, p.created_at,
+            p.id, p.name, p.description, p.price, p.currency, p.category_id, p.image_url, p.stock,
+            p.average_rating, p.review_count, p.created_at,
             c.name as category_name
         FROM products p
         JOIN categories c ON p.category_id = c.id
@@ -61,16 +97,25 @@ pub async fn find_product_with_category_by_id(
     .fetch_optional(pool)
     .await.map_err(|_| anyhow::anyhow!("Failed to fetch product"))?;
 
-    Ok(product.map(|row| ProductWithCategory {
+    let Some(row) = product else {
+        return Ok(None);
+    };
+    let variants = find_variants_by_product(pool, row.id).await?;
+
+    Ok(Some(ProductWithCategory {
         id: row.id,
         name: row.name,
         description: row.description,
         price: row.price,
+        currency: row.currency,
         category_id: row.category_id,
         category_name: row.category_name,
         image_url: row.image_url,
         stock: row.stock,
+        average_rating: row.average_rating,
+        review_count: row.review_count,
         created_at: row.created_at,
+        variants,
     }))
 }
 
@@ -78,6 +123,7 @@ pub async fn update_product_db(
     pool: &DatabasePool,
     product_id: Uuid,
     update_data: UpdateProduct,
+    user_id: Option<Uuid>,
 ) -> Result<Product> {
     // Parse price if provided
     let price = if let Some(price_str) = &update_data.price {
@@ -86,6 +132,32 @@ pub async fn update_product_db(
         None
     };
 
+    let mut tx = pool.begin().await?;
+
+    // Setting stock directly is recorded as an adjustment for the difference
+    if let Some(stock) = update_data.stock {
+        let current = sqlx::query_scalar!(
+            "SELECT stock FROM products WHERE id = $1 FOR UPDATE",
+            product_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let delta = stock - current;
+        if delta != 0 {
+            apply_stock_delta(
+                &mut tx,
+                product_id,
+                delta,
+                StockMovementKind::Adjustment,
+                Some("Stock set via product update".to_string()),
+                user_id,
+            )
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Stock cannot be negative"))?;
+        }
+    }
+
     let product = sqlx::query_as!(
         Product,
         r#"
@@ -93,21 +165,24 @@ pub async fn update_product_db(
         SET name = COALESCE($2, name),
             description = COALESCE($3, description),
             price = COALESCE($4, price),
-            category_id = COALESCE($5, category_id),
-            stock = COALESCE($6, stock)
+            currency = COALESCE($5, currency),
+            category_id = COALESCE($6, category_id)
         WHERE id = $1
-        RETURNING id, name, description, price, category_id, image_url, stock, created_at
+        RETURNING id, name, description, price, currency, category_id, image_url, stock,
+                  average_rating, review_count, created_at
         "#,
         product_id,
         update_data.name,
         update_data.description,
         price,
-        update_data.category_id,
-        update_data.stock
+        update_data.currency,
+        update_data.category_id
     )
-    .fetch_one(pool)
+    .fetch_one(&mut *tx)
     .await?;
 
+    tx.commit().await?;
+
     Ok(product)
 }
 
@@ -122,7 +197,8 @@ pub async fn update_product_image(
         UPDATE products
         SET image_url = $2
         WHERE id = $1
-        RETURNING id, name, description, price, category_id, image_url, stock, created_at
+        RETURNING id, name, description, price, currency, category_id, image_url, stock,
+                  average_rating, review_count, created_at
         "#,
         product_id,
         image_url
@@ -161,28 +237,69 @@ pub async fn search_products(pool: &DatabasePool, filter: &ProductFilter) -> Res
         product_builder.push(" AND category = ")
                        .push_bind(category);
     }
-    if let Some(min_price) = filter.min_price {
-        count_builder.push(" AND price >= ")
-                     .push_bind(min_price);
-        product_builder.push(" AND price >= ")
-                       .push_bind(min_price);
+    if let Some(category_id) = filter.category_id {
+        let category_ids = if filter.include_subcategories.unwrap_or(false) {
+            find_descendant_ids(pool, category_id).await?
+        } else {
+            vec![category_id]
+        };
+        count_builder.push(" AND category_id = ANY(")
+                     .push_bind(category_ids.clone())
+                     .push(")");
+        product_builder.push(" AND category_id = ANY(")
+                       .push_bind(category_ids)
+                       .push(")");
     }
-    if let Some(max_price) = filter.max_price {
-        count_builder.push(" AND price <= ")
-                     .push_bind(max_price);
-        product_builder.push(" AND price <= ")
+    if let Some(currency) = &filter.currency {
+        // Products that cannot be priced in the requested currency are left out
+        for builder in [&mut count_builder, &mut product_builder] {
+            builder.push(" AND exchange_rate(currency, ").push_bind(currency.clone()).push(") IS NOT NULL");
+            if let Some(min_price) = filter.min_price {
+                builder.push(" AND convert_price(price, currency, ")
+                       .push_bind(currency.clone())
+                       .push(") >= ")
+                       .push_bind(min_price);
+            }
+            if let Some(max_price) = filter.max_price {
+                builder.push(" AND convert_price(price, currency, ")
+                       .push_bind(currency.clone())
+                       .push(") <= ")
                        .push_bind(max_price);
+            }
+        }
+    } else {
+        if let Some(min_price) = filter.min_price {
+            count_builder.push(" AND price >= ")
+                         .push_bind(min_price);
+            product_builder.push(" AND price >= ")
+                           .push_bind(min_price);
+        }
+        if let Some(max_price) = filter.max_price {
+            count_builder.push(" AND price <= ")
+                         .push_bind(max_price);
+            product_builder.push(" AND price <= ")
+                           .push_bind(max_price);
+        }
     }
     if let Some(in_stock) = filter.in_stock {
+        // A product counts as in stock if it or any of its variants has stock
+        let stocked = "(stock > 0 OR EXISTS (SELECT 1 FROM product_variants v WHERE v.product_id = products.id AND v.stock > 0))";
         if in_stock {
-            count_builder.push(" AND stock > 0");
-            product_builder.push(" AND stock > 0");
+            count_builder.push(" AND ").push(stocked);
+            product_builder.push(" AND ").push(stocked);
         } else {
-            count_builder.push(" AND stock = 0");
-            product_builder.push(" AND stock = 0");
+            count_builder.push(" AND NOT ").push(stocked);
+            product_builder.push(" AND NOT ").push(stocked);
         }
     }
 
+    if let Some(min_rating) = filter.min_rating {
+        count_builder.push(" AND average_rating >= ")
+                     .push_bind(min_rating);
+        product_builder.push(" AND average_rating >= ")
+                       .push_bind(min_rating);
+    }
+
     // Count total items
     let total_items: (i64,) = count_builder
         .build_query_as()
@@ -196,8 +313,14 @@ pub async fn search_products(pool: &DatabasePool, filter: &ProductFilter) -> Res
     let total_pages = (total_items.0 as u32 + per_page - 1) / per_page;
 
     // Fetch paginated items
+    let order_by = match filter.sort {
+        Some(ProductSort::Newest) => " ORDER BY created_at DESC, id ASC",
+        Some(ProductSort::Rating) => " ORDER BY average_rating DESC NULLS LAST, review_count DESC, id ASC",
+        Some(ProductSort::Reviews) => " ORDER BY review_count DESC, id ASC",
+        None => " ORDER BY id ASC",
+    };
     let products = product_builder
-        .push(" ORDER BY id ASC")
+        .push(order_by)
         .push(" LIMIT ").push_bind(per_page as i64)
         .push(" OFFSET ").push_bind(offset as i64)
         .build_query_as::<Product>()
@@ -0,0 +1,264 @@
+use crate::db::categoryq::find_descendant_ids;
+use crate::db::currencyq::find_conversion_rate;
+use crate::db::db_con::DatabasePool;
+use crate::models::currency::DEFAULT_CURRENCY;
+use crate::models::promotion::*;
+use anyhow::Result;
+use rust_decimal::Decimal;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+fn parse_optional_decimal(value: &Option<String>) -> Result<Option<Decimal>> {
+    match value {
+        Some(value) => Ok(Some(Decimal::from_str_exact(value)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn create_promotion_db(pool: &DatabasePool, data: CreatePromotion) -> Result<Promotion> {
+    let discount_value = Decimal::from_str_exact(&data.discount_value)?;
+    let min_spend = parse_optional_decimal(&data.min_spend)?;
+
+    let promotion = sqlx::query_as!(
+        Promotion,
+        r#"
+        INSERT INTO promotions (name, code, discount_type, discount_value, scope, product_id, category_id,
+                                min_spend, currency, starts_at, ends_at, usage_limit, per_user_limit, active)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING id, name, code, discount_type as "discount_type: DiscountType", discount_value,
+                  scope as "scope: PromotionScope", product_id, category_id, min_spend, currency, starts_at, ends_at,
+                  usage_limit, per_user_limit, active, created_at
+        "#,
+        data.name,
+        data.code,
+        data.discount_type as DiscountType,
+        discount_value,
+        data.scope as PromotionScope,
+        data.product_id,
+        data.category_id,
+        min_spend,
+        data.currency.as_deref().unwrap_or(DEFAULT_CURRENCY),
+        data.starts_at,
+        data.ends_at,
+        data.usage_limit,
+        data.per_user_limit,
+        data.active.unwrap_or(true)
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(promotion)
+}
+
+pub async fn find_all_promotions(pool: &DatabasePool) -> Result<Vec<Promotion>> {
+    let promotions = sqlx::query_as!(
+        Promotion,
+        r#"
+        SELECT id, name, code, discount_type as "discount_type: DiscountType", discount_value,
+               scope as "scope: PromotionScope", product_id, category_id, min_spend, currency, starts_at, ends_at,
+               usage_limit, per_user_limit, active, created_at
+        FROM promotions
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(promotions)
+}
+
+pub async fn find_promotion_by_id(pool: &DatabasePool, promotion_id: Uuid) -> Result<Option<Promotion>> {
+    let promotion = sqlx::query_as!(
+        Promotion,
+        r#"
+        SELECT id, name, code, discount_type as "discount_type: DiscountType", discount_value,
+               scope as "scope: PromotionScope", product_id, category_id, min_spend, currency, starts_at, ends_at,
+               usage_limit, per_user_limit, active, created_at
+        FROM promotions
+        WHERE id = $1
+        "#,
+        promotion_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(promotion)
+}
+
+pub async fn find_promotion_by_code(pool: &DatabasePool, code: &str) -> Result<Option<Promotion>> {
+    let promotion = sqlx::query_as!(
+        Promotion,
+        r#"
+        SELECT id, name, code, discount_type as "discount_type: DiscountType", discount_value,
+               scope as "scope: PromotionScope", product_id, category_id, min_spend, currency, starts_at, ends_at,
+               usage_limit, per_user_limit, active, created_at
+        FROM promotions
+        WHERE code = $1
+        "#,
+        code
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(promotion)
+}
+
+pub async fn update_promotion_db(pool: &DatabasePool, promotion_id: Uuid, data: UpdatePromotion) -> Result<Promotion> {
+    let discount_value = parse_optional_decimal(&data.discount_value)?;
+    let min_spend = parse_optional_decimal(&data.min_spend)?;
+
+    let promotion = sqlx::query_as!(
+        Promotion,
+        r#"
+        UPDATE promotions
+        SET name = COALESCE($2, name),
+            discount_value = COALESCE($3, discount_value),
+            min_spend = COALESCE($4, min_spend),
+            starts_at = COALESCE($5, starts_at),
+            ends_at = COALESCE($6, ends_at),
+            usage_limit = COALESCE($7, usage_limit),
+            per_user_limit = COALESCE($8, per_user_limit),
+            active = COALESCE($9, active),
+            currency = COALESCE($10, currency)
+        WHERE id = $1
+        RETURNING id, name, code, discount_type as "discount_type: DiscountType", discount_value,
+                  scope as "scope: PromotionScope", product_id, category_id, min_spend, currency, starts_at, ends_at,
+                  usage_limit, per_user_limit, active, created_at
+        "#,
+        promotion_id,
+        data.name,
+        discount_value,
+        min_spend,
+        data.starts_at,
+        data.ends_at,
+        data.usage_limit,
+        data.per_user_limit,
+        data.active,
+        data.currency
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(promotion)
+}
+
+pub async fn delete_promotion_db(pool: &DatabasePool, promotion_id: Uuid) -> Result<()> {
+    sqlx::query!("DELETE FROM promotions WHERE id = $1", promotion_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Redemptions of a promotion overall and by one user.
+/// Cancelled orders give their use back; their redemption rows stay for the record.
+pub async fn count_promotion_uses(
+    conn: &mut PgConnection,
+    promotion_id: Uuid,
+    user_id: Option<Uuid>,
+) -> Result<(i64, i64)> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "total!",
+               COUNT(*) FILTER (WHERE r.user_id = $2) as "by_user!"
+        FROM promotion_redemptions r
+        JOIN orders o ON o.id = r.order_id
+        WHERE r.promotion_id = $1 AND o.status <> 'cancelled'
+        "#,
+        promotion_id,
+        user_id
+    )
+    .fetch_one(conn)
+    .await?;
+
+    Ok((row.total, row.by_user))
+}
+
+/// Automatic promotions plus the coupon (if any), with their usage counts and their rate into
+/// the cart's currency. Inactive automatic promotions are left out; an inactive coupon is kept
+/// so it can be explained.
+pub async fn find_promotion_candidates(
+    pool: &DatabasePool,
+    coupon_code: Option<&str>,
+    user_id: Option<Uuid>,
+    currency: &str,
+) -> Result<Vec<PromotionCandidate>> {
+    let promotions = sqlx::query_as!(
+        Promotion,
+        r#"
+        SELECT id, name, code, discount_type as "discount_type: DiscountType", discount_value,
+               scope as "scope: PromotionScope", product_id, category_id, min_spend, currency, starts_at, ends_at,
+               usage_limit, per_user_limit, active, created_at
+        FROM promotions
+        WHERE (code IS NULL AND active) OR code = $1
+        ORDER BY code NULLS FIRST, created_at ASC
+        "#,
+        coupon_code
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut conn = pool.acquire().await?;
+    let mut candidates = Vec::with_capacity(promotions.len());
+    for promotion in promotions {
+        let category_ids = match promotion.category_id {
+            Some(category_id) if promotion.scope == PromotionScope::Category => {
+                find_descendant_ids(pool, category_id).await?
+            }
+            _ => Vec::new(),
+        };
+        let (total_uses, user_uses) = count_promotion_uses(&mut conn, promotion.id, user_id).await?;
+        let rate = find_conversion_rate(pool, &promotion.currency, currency).await?;
+
+        candidates.push(PromotionCandidate {
+            promotion,
+            category_ids,
+            total_uses,
+            user_uses,
+            rate,
+        });
+    }
+
+    Ok(candidates)
+}
+
+/// Lock the promotion and check it still has uses left; call inside the checkout transaction
+pub async fn promotion_has_uses_left(conn: &mut PgConnection, promotion_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let limits = sqlx::query!(
+        "SELECT usage_limit, per_user_limit FROM promotions WHERE id = $1 FOR UPDATE",
+        promotion_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let Some(limits) = limits else {
+        return Ok(false);
+    };
+
+    let (total_uses, user_uses) = count_promotion_uses(conn, promotion_id, Some(user_id)).await?;
+    Ok(limits.usage_limit.is_none_or(|limit| total_uses < limit as i64)
+        && limits.per_user_limit.is_none_or(|limit| user_uses < limit as i64))
+}
+
+pub async fn record_redemption(
+    conn: &mut PgConnection,
+    promotion_id: Uuid,
+    order_id: Uuid,
+    user_id: Uuid,
+    discount: Decimal,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO promotion_redemptions (promotion_id, order_id, user_id, discount)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        promotion_id,
+        order_id,
+        user_id,
+        discount
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::other::PaginatedResponse;
+use crate::models::review::*;
+use anyhow::Result;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Recompute the product's average rating and review count from approved reviews
+async fn refresh_product_rating(conn: &mut PgConnection, product_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE products
+        SET average_rating = stats.average, review_count = stats.count
+        FROM (
+            SELECT ROUND(AVG(rating), 2) as average, COUNT(*)::INTEGER as count
+            FROM reviews
+            WHERE product_id = $1 AND status = 'approved'
+        ) stats
+        WHERE id = $1
+        "#,
+        product_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether the user has a paid (or later) order containing the product
+pub async fn has_purchased_product(pool: &DatabasePool, user_id: Uuid, product_id: Uuid) -> Result<bool> {
+    let purchased = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM order_items oi
+            JOIN orders o ON o.id = oi.order_id
+            WHERE o.user_id = $1 AND oi.product_id = $2
+              AND o.status IN ('paid', 'shipped', 'delivered')
+        ) as "exists!"
+        "#,
+        user_id,
+        product_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(purchased)
+}
+
+/// Every review the user wrote, whatever its moderation state
+pub async fn find_reviews_by_user(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<Review>> {
+    let reviews = sqlx::query_as!(
+        Review,
+        r#"
+        SELECT r.id, r.product_id, r.user_id, u.username, r.rating, r.comment,
+               r.status as "status: ReviewStatus", r.verified_purchase, r.created_at, r.updated_at
+        FROM reviews r
+        JOIN users u ON u.id = r.user_id
+        WHERE r.user_id = $1
+        ORDER BY r.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(reviews)
+}
+
+pub async fn find_review_by_id(pool: &DatabasePool, review_id: Uuid) -> Result<Option<Review>> {
+    let review = sqlx::query_as!(
+        Review,
+        r#"
+        SELECT r.id, r.product_id, r.user_id, u.username, r.rating, r.comment,
+               r.status as "status: ReviewStatus", r.verified_purchase, r.created_at, r.updated_at
+        FROM reviews r
+        JOIN users u ON u.id = r.user_id
+        WHERE r.id = $1
+        "#,
+        review_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(review)
+}
+
+/// Insert the review; `None` if the user already reviewed the product
+pub async fn create_review_db(
+    pool: &DatabasePool,
+    product_id: Uuid,
+    user_id: Uuid,
+    data: CreateReview,
+    verified_purchase: bool,
+) -> Result<Option<Review>> {
+    let review_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO reviews (product_id, user_id, rating, comment, verified_purchase)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (product_id, user_id) DO NOTHING
+        RETURNING id
+        "#,
+        product_id,
+        user_id,
+        data.rating,
+        data.comment.trim(),
+        verified_purchase
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(review_id) = review_id else {
+        return Ok(None);
+    };
+
+    let review = find_review_by_id(pool, review_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Review disappeared after insert"))?;
+
+    Ok(Some(review))
+}
+
+/// Change a review's moderation status and keep the product rating in sync
+pub async fn set_review_status(pool: &DatabasePool, review_id: Uuid, status: ReviewStatus) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let product_id = sqlx::query_scalar!(
+        "UPDATE reviews SET status = $2, updated_at = NOW() WHERE id = $1 RETURNING product_id",
+        review_id,
+        status as ReviewStatus
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    refresh_product_rating(&mut tx, product_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+pub async fn delete_review_db(pool: &DatabasePool, review_id: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    let product_id = sqlx::query_scalar!("DELETE FROM reviews WHERE id = $1 RETURNING product_id", review_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    refresh_product_rating(&mut tx, product_id).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Reviews newest first, optionally limited to one product and/or status
+pub async fn find_reviews(
+    pool: &DatabasePool,
+    product_id: Option<Uuid>,
+    status: Option<ReviewStatus>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+) -> Result<PaginatedResponse<Review>> {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(20).clamp(1, 100);
+    let offset = (page - 1) * per_page;
+
+    let total_items = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!" FROM reviews
+        WHERE ($1::uuid IS NULL OR product_id = $1) AND ($2::review_status IS NULL OR status = $2)
+        "#,
+        product_id,
+        status as Option<ReviewStatus>
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let reviews = sqlx::query_as!(
+        Review,
+        r#"
+        SELECT r.id, r.product_id, r.user_id, u.username, r.rating, r.comment,
+               r.status as "status: ReviewStatus", r.verified_purchase, r.created_at, r.updated_at
+        FROM reviews r
+        JOIN users u ON u.id = r.user_id
+        WHERE ($1::uuid IS NULL OR r.product_id = $1) AND ($2::review_status IS NULL OR r.status = $2)
+        ORDER BY r.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        product_id,
+        status as Option<ReviewStatus>,
+        per_page as i64,
+        offset as i64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(PaginatedResponse {
+        item_on_page: Some(reviews.len() as u32),
+        data: reviews,
+        current_page: page,
+        total_items: total_items as u32,
+        per_page,
+        total_pages: (total_items as u32).div_ceil(per_page),
+    })
+}
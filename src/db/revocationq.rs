@@ -0,0 +1,81 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::auth::{RevokedToken, TokenWatermark};
+use anyhow::Result;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// Revoke a jti or session id until `expires_at`, after which no token it covers can still be valid
+pub async fn insert_revoked_token(
+    pool: &DatabasePool,
+    id: Uuid,
+    user_id: Uuid,
+    expires_at: OffsetDateTime,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO revoked_tokens (id, user_id, expires_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (id) DO UPDATE SET expires_at = GREATEST(revoked_tokens.expires_at, EXCLUDED.expires_at)
+        "#,
+        id,
+        user_id,
+        expires_at
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn find_revoked_tokens(pool: &DatabasePool) -> Result<Vec<RevokedToken>> {
+    let tokens = sqlx::query_as!(
+        RevokedToken,
+        "SELECT id, expires_at FROM revoked_tokens WHERE expires_at > NOW()"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tokens)
+}
+
+pub async fn delete_expired_revoked_tokens(pool: &DatabasePool) -> Result<u64> {
+    let result = sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at <= NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Invalidate every access token issued to the user so far.
+/// Truncated to whole seconds to compare with `iat`; tokens issued in that second are invalid too.
+pub async fn set_tokens_valid_after(pool: &DatabasePool, user_id: Uuid) -> Result<OffsetDateTime> {
+    let valid_after = sqlx::query_scalar!(
+        r#"
+        UPDATE users SET tokens_valid_after = date_trunc('second', NOW())
+        WHERE id = $1
+        RETURNING tokens_valid_after as "tokens_valid_after!"
+        "#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(valid_after)
+}
+
+/// Watermarks set after `since`; older ones can no longer reject an unexpired token
+pub async fn find_token_watermarks(pool: &DatabasePool, since: OffsetDateTime) -> Result<Vec<TokenWatermark>> {
+    let watermarks = sqlx::query_as!(
+        TokenWatermark,
+        r#"
+        SELECT id as user_id, tokens_valid_after as "tokens_valid_after!"
+        FROM users
+        WHERE tokens_valid_after > $1
+        "#,
+        since
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(watermarks)
+}
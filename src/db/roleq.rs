@@ -0,0 +1,183 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::permission::Role;
+use anyhow::Result;
+use uuid::Uuid;
+
+pub async fn find_roles(pool: &DatabasePool) -> Result<Vec<Role>> {
+    let roles = sqlx::query_as!(
+        Role,
+        r#"
+        SELECT r.name, r.description, r.builtin,
+               COALESCE(ARRAY_AGG(rp.permission ORDER BY rp.permission) FILTER (WHERE rp.permission IS NOT NULL), '{}') as "permissions!",
+               r.created_at
+        FROM roles r
+        LEFT JOIN role_permissions rp ON rp.role = r.name
+        GROUP BY r.name
+        ORDER BY r.builtin DESC, r.name ASC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(roles)
+}
+
+pub async fn find_role(pool: &DatabasePool, name: &str) -> Result<Option<Role>> {
+    let role = sqlx::query_as!(
+        Role,
+        r#"
+        SELECT r.name, r.description, r.builtin,
+               COALESCE(ARRAY_AGG(rp.permission ORDER BY rp.permission) FILTER (WHERE rp.permission IS NOT NULL), '{}') as "permissions!",
+               r.created_at
+        FROM roles r
+        LEFT JOIN role_permissions rp ON rp.role = r.name
+        WHERE r.name = $1
+        GROUP BY r.name
+        "#,
+        name
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(role)
+}
+
+/// Create a custom role; returns false if the name is taken
+pub async fn create_role_db(pool: &DatabasePool, name: &str, description: Option<&str>, permissions: &[String]) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let created = sqlx::query!(
+        "INSERT INTO roles (name, description) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING",
+        name,
+        description
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if created.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        "INSERT INTO role_permissions (role, permission) SELECT $1, UNNEST($2::varchar[])",
+        name,
+        permissions
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Update a role's description and/or replace its permissions
+pub async fn update_role_db(
+    pool: &DatabasePool,
+    name: &str,
+    description: Option<&str>,
+    permissions: Option<&[String]>,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE roles SET description = COALESCE($2, description) WHERE name = $1",
+        name,
+        description
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    if let Some(permissions) = permissions {
+        sqlx::query!("DELETE FROM role_permissions WHERE role = $1", name)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "INSERT INTO role_permissions (role, permission) SELECT $1, UNNEST($2::varchar[])",
+            name,
+            permissions
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Delete a custom role; built-in roles are never deleted
+pub async fn delete_role_db(pool: &DatabasePool, name: &str) -> Result<bool> {
+    let deleted = sqlx::query!("DELETE FROM roles WHERE name = $1 AND NOT builtin", name)
+        .execute(pool)
+        .await?;
+
+    Ok(deleted.rows_affected() > 0)
+}
+
+/// Users holding the role, either as their `role` or as an extra role
+pub async fn find_role_user_ids(pool: &DatabasePool, name: &str) -> Result<Vec<Uuid>> {
+    let user_ids = sqlx::query_scalar!(
+        r#"
+        SELECT id as "id!" FROM users WHERE role::text = $1
+        UNION
+        SELECT user_id FROM user_roles WHERE role = $1
+        "#,
+        name
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(user_ids)
+}
+
+pub async fn find_user_extra_roles(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<String>> {
+    let roles = sqlx::query_scalar!(
+        "SELECT role FROM user_roles WHERE user_id = $1 ORDER BY role",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(roles)
+}
+
+/// Replace the extra roles of a user
+pub async fn set_user_extra_roles(pool: &DatabasePool, user_id: Uuid, roles: &[String]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM user_roles WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO user_roles (user_id, role) SELECT $1, UNNEST($2::varchar[])",
+        user_id,
+        roles
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Union of the permissions of the user's role and extra roles
+pub async fn find_user_permissions(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<String>> {
+    let permissions = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT rp.permission
+        FROM role_permissions rp
+        WHERE rp.role = (SELECT role::text FROM users WHERE id = $1)
+           OR rp.role IN (SELECT role FROM user_roles WHERE user_id = $1)
+        ORDER BY rp.permission
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(permissions)
+}
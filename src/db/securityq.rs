@@ -0,0 +1,51 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::auth::ClientInfo;
+use crate::models::security::SecurityEvent;
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+pub async fn record_security_event(
+    pool: &DatabasePool,
+    user_id: Option<Uuid>,
+    kind: &str,
+    client: &ClientInfo,
+    details: Value,
+) -> Result<SecurityEvent> {
+    let event = sqlx::query_as!(
+        SecurityEvent,
+        r#"
+        INSERT INTO security_events (user_id, kind, ip_address, user_agent, details)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, user_id, kind, ip_address, user_agent, details as "details: Json<Value>", created_at
+        "#,
+        user_id,
+        kind,
+        client.ip_address,
+        client.user_agent,
+        Json(details) as _
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(event)
+}
+
+/// The user's security events, newest first
+pub async fn find_user_security_events(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<SecurityEvent>> {
+    let events = sqlx::query_as!(
+        SecurityEvent,
+        r#"
+        SELECT id, user_id, kind, ip_address, user_agent, details as "details: Json<Value>", created_at
+        FROM security_events
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
@@ -1,6 +1,7 @@
-use sqlx::Result;
+use sqlx::{Postgres, QueryBuilder, Result};
 use crate::db::db_con::DatabasePool;
-use crate::models::user::{User, CreateUser, UserRole};
+use crate::models::user::{AccountDeletion, User, CreateUser, RoleChange, UserQuery, UserRole, UserStatus};
+use crate::models::other::PaginatedResponse;
 use uuid::Uuid;
 
 pub async fn create_user(pool: &DatabasePool, user_data: CreateUser, password_hash: String) -> Result<User> {
@@ -9,7 +10,7 @@ pub async fn create_user(pool: &DatabasePool, user_data: CreateUser, password_ha
             r#"
             INSERT INTO users (username, email, password_hash, role)
             VALUES ($1, $2, $3, $4)
-            RETURNING id, username, email, password_hash, role as "role: UserRole", created_at
+            RETURNING id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
             "#,
             user_data.username,
             user_data.email,
@@ -26,7 +27,7 @@ pub async fn find_by_email(pool: &DatabasePool, email: &str) -> Result<Option<Us
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, username, email, password_hash, role as "role: UserRole", created_at
+            SELECT id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
             FROM users
             WHERE email = $1
             "#,
@@ -42,7 +43,7 @@ pub async fn find_by_id(pool: &DatabasePool, user_id: Uuid) -> Result<Option<Use
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT id, username, email, password_hash, role as "role: UserRole", created_at
+            SELECT id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
             FROM users
             WHERE id = $1
             "#,
@@ -62,7 +63,7 @@ pub async fn find_by_id(pool: &DatabasePool, user_id: Uuid) -> Result<Option<Use
             SET username = COALESCE($2, username),
                 email = COALESCE($3, email)
             WHERE id = $1
-            RETURNING id, username, email, password_hash, role as "role: UserRole", created_at
+            RETURNING id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
             "#,
             user_id,
             username,
@@ -74,10 +75,11 @@ pub async fn find_by_id(pool: &DatabasePool, user_id: Uuid) -> Result<Option<Use
         Ok(user)
 }
 
-pub async fn delete_user(pool: &DatabasePool, user_id: Uuid) -> Result<()> {
+pub async fn update_password(pool: &DatabasePool, user_id: Uuid, password_hash: &str) -> Result<()> {
         sqlx::query!(
-            "DELETE FROM users WHERE id = $1",
-            user_id
+            "UPDATE users SET password_hash = $2 WHERE id = $1",
+            user_id,
+            password_hash
         )
         .execute(pool)
         .await?;
@@ -85,3 +87,255 @@ pub async fn delete_user(pool: &DatabasePool, user_id: Uuid) -> Result<()> {
         Ok(())
 }
 
+/// Hold a new address until it is confirmed through the verification link
+pub async fn set_pending_email(pool: &DatabasePool, user_id: Uuid, pending_email: Option<&str>) -> Result<User> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users SET pending_email = $2
+            WHERE id = $1
+            RETURNING id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
+            "#,
+            user_id,
+            pending_email
+        )
+        .fetch_one(pool)
+        .await?;
+
+        Ok(user)
+}
+
+/// Delete an account by anonymising it. The row is kept so orders and reviews still point at
+/// a user; everything else personal is dropped. Refused for the last active superadmin.
+pub async fn anonymize_account(pool: &DatabasePool, user_id: Uuid) -> Result<Option<AccountDeletion>> {
+        let mut tx = pool.begin().await?;
+
+        let superadmins = sqlx::query_scalar!(
+            "SELECT id FROM users WHERE role = 'superadmin' AND status <> 'deleted' FOR UPDATE"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if superadmins.len() == 1 && superadmins[0] == user_id {
+            return Ok(Some(AccountDeletion::LastSuperAdmin));
+        }
+
+        // The old address keys the login throttle, so drop it along with the user's data
+        let email = sqlx::query_scalar!(
+            "SELECT email FROM users WHERE id = $1 AND status <> 'deleted' FOR UPDATE",
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(email) = email else {
+            return Ok(None);
+        };
+
+        // '!' is never a valid password hash, so nobody can log in as the account again
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET username = 'deleted-' || REPLACE(id::text, '-', ''),
+                email = 'deleted-' || id::text || '@deleted.invalid',
+                password_hash = '!',
+                role = 'user',
+                status = 'deleted',
+                email_verified_at = NULL,
+                pending_email = NULL
+            WHERE id = $1
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM refresh_tokens WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM password_reset_tokens WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM email_verification_tokens WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM user_totp WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM mfa_recovery_codes WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM mfa_challenges WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM user_roles WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM carts WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM wishlist_items WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM stock_subscriptions WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM notifications WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!("DELETE FROM api_keys WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // Events stay for the audit trail, without where the requests came from
+        sqlx::query!(
+            "UPDATE security_events SET ip_address = NULL, user_agent = NULL, details = '{}' WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM login_throttles WHERE scope = 'email' AND key = LOWER(TRIM($1))",
+            email
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(AccountDeletion::Deleted))
+}
+
+/// Status of the user, checked on every authenticated request
+pub async fn find_user_status(pool: &DatabasePool, user_id: Uuid) -> Result<Option<UserStatus>> {
+        let status = sqlx::query_scalar!(
+            r#"SELECT status as "status: UserStatus" FROM users WHERE id = $1"#,
+            user_id
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(status)
+}
+
+
+/// Search users by username or email, newest first
+pub async fn find_users(pool: &DatabasePool, query: &UserQuery) -> Result<PaginatedResponse<User>> {
+        let mut count_builder: QueryBuilder<'_, Postgres> = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE 1=1");
+        let mut user_builder: QueryBuilder<'_, Postgres> = QueryBuilder::new(
+            "SELECT id, username, email, password_hash, role, status, email_verified_at, pending_email, created_at FROM users WHERE 1=1",
+        );
+
+        let search = query
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("%{}%", s));
+
+        for builder in [&mut count_builder, &mut user_builder] {
+            if let Some(ref pattern) = search {
+                builder
+                    .push(" AND (username ILIKE ")
+                    .push_bind(pattern.clone())
+                    .push(" OR email ILIKE ")
+                    .push_bind(pattern.clone())
+                    .push(")");
+            }
+            if let Some(ref role) = query.role {
+                builder.push(" AND role = ").push_bind(role.clone());
+            }
+            if let Some(status) = query.status {
+                builder.push(" AND status = ").push_bind(status);
+            }
+        }
+
+        let total_items: (i64,) = count_builder
+            .build_query_as()
+            .fetch_one(pool)
+            .await?;
+
+        let page = query.page.unwrap_or(1).max(1);
+        let per_page = query.per_page.unwrap_or(20).clamp(1, 100);
+        let offset = (page - 1) * per_page;
+
+        let users = user_builder
+            .push(" ORDER BY created_at DESC")
+            .push(" LIMIT ").push_bind(per_page as i64)
+            .push(" OFFSET ").push_bind(offset as i64)
+            .build_query_as::<User>()
+            .fetch_all(pool)
+            .await?;
+
+        Ok(PaginatedResponse {
+            item_on_page: Some(users.len() as u32),
+            data: users,
+            current_page: page,
+            total_items: total_items.0 as u32,
+            per_page,
+            total_pages: (total_items.0 as u32).div_ceil(per_page),
+        })
+}
+
+/// Change a user's role. Superadmins are locked while counting so two concurrent demotions
+/// cannot remove the last one.
+pub async fn change_user_role(pool: &DatabasePool, user_id: Uuid, role: UserRole) -> Result<Option<RoleChange>> {
+        let mut tx = pool.begin().await?;
+
+        let superadmins = sqlx::query_scalar!(
+            "SELECT id FROM users WHERE role = 'superadmin' FOR UPDATE"
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if superadmins.len() == 1 && superadmins[0] == user_id && !matches!(role, UserRole::SuperAdmin) {
+            return Ok(Some(RoleChange::LastSuperAdmin));
+        }
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users SET role = $2
+            WHERE id = $1
+            RETURNING id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
+            "#,
+            user_id,
+            role as UserRole
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user.map(RoleChange::Updated))
+}
+
+/// Suspend or reactivate a user; deleted accounts stay deleted
+pub async fn set_user_status(pool: &DatabasePool, user_id: Uuid, status: UserStatus) -> Result<Option<User>> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            UPDATE users SET status = $2
+            WHERE id = $1 AND status <> 'deleted'
+            RETURNING id, username, email, password_hash, role as "role: UserRole", status as "status: UserStatus", email_verified_at, pending_email, created_at
+            "#,
+            user_id,
+            status as UserStatus
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(user)
+}
@@ -0,0 +1,187 @@
+use crate::db::db_con::DatabasePool;
+use crate::db::inventoryq::{apply_variant_stock_delta, record_stock_movement};
+use crate::models::inventory::StockMovementKind;
+use crate::models::product::{CreateVariant, ProductVariant, UpdateVariant};
+use anyhow::Result;
+use rust_decimal::Decimal;
+use sqlx::types::Json;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub async fn create_variant_db(
+    pool: &DatabasePool,
+    product_id: Uuid,
+    variant_data: CreateVariant,
+    user_id: Option<Uuid>,
+) -> Result<ProductVariant> {
+    // Parse price override if provided
+    let price = match &variant_data.price {
+        Some(price_str) => Some(Decimal::from_str_exact(price_str)?),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let variant = sqlx::query_as!(
+        ProductVariant,
+        r#"
+        INSERT INTO product_variants (product_id, sku, options, price, stock)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, product_id, sku, options as "options: Json<BTreeMap<String, String>>",
+                  price, stock, created_at
+        "#,
+        product_id,
+        variant_data.sku,
+        Json(variant_data.options) as _,
+        price,
+        variant_data.stock
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Initial stock enters the ledger as a receipt
+    if variant.stock > 0 {
+        record_stock_movement(
+            &mut tx,
+            product_id,
+            Some(variant.id),
+            StockMovementKind::Receipt,
+            variant.stock,
+            variant.stock,
+            Some("Initial stock".to_string()),
+            user_id,
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(variant)
+}
+
+pub async fn find_variants_by_product(pool: &DatabasePool, product_id: Uuid) -> Result<Vec<ProductVariant>> {
+    let variants = sqlx::query_as!(
+        ProductVariant,
+        r#"
+        SELECT id, product_id, sku, options as "options: Json<BTreeMap<String, String>>",
+               price, stock, created_at
+        FROM product_variants
+        WHERE product_id = $1
+        ORDER BY created_at ASC
+        "#,
+        product_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(variants)
+}
+
+pub async fn find_variant_by_id(
+    pool: &DatabasePool,
+    product_id: Uuid,
+    variant_id: Uuid,
+) -> Result<Option<ProductVariant>> {
+    let variant = sqlx::query_as!(
+        ProductVariant,
+        r#"
+        SELECT id, product_id, sku, options as "options: Json<BTreeMap<String, String>>",
+               price, stock, created_at
+        FROM product_variants
+        WHERE id = $1 AND product_id = $2
+        "#,
+        variant_id,
+        product_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(variant)
+}
+
+pub async fn find_variant_by_sku(pool: &DatabasePool, sku: &str) -> Result<Option<ProductVariant>> {
+    let variant = sqlx::query_as!(
+        ProductVariant,
+        r#"
+        SELECT id, product_id, sku, options as "options: Json<BTreeMap<String, String>>",
+               price, stock, created_at
+        FROM product_variants
+        WHERE sku = $1
+        "#,
+        sku
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(variant)
+}
+
+pub async fn update_variant_db(
+    pool: &DatabasePool,
+    variant_id: Uuid,
+    update_data: UpdateVariant,
+    user_id: Option<Uuid>,
+) -> Result<ProductVariant> {
+    // Parse price if provided
+    let price = match &update_data.price {
+        Some(price_str) => Some(Decimal::from_str_exact(price_str)?),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    // Setting stock directly is recorded as an adjustment for the difference
+    if let Some(stock) = update_data.stock {
+        let current = sqlx::query_scalar!(
+            "SELECT stock FROM product_variants WHERE id = $1 FOR UPDATE",
+            variant_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let delta = stock - current;
+        if delta != 0 {
+            apply_variant_stock_delta(
+                &mut tx,
+                variant_id,
+                delta,
+                StockMovementKind::Adjustment,
+                Some("Stock set via variant update".to_string()),
+                user_id,
+            )
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Stock cannot be negative"))?;
+        }
+    }
+
+    let variant = sqlx::query_as!(
+        ProductVariant,
+        r#"
+        UPDATE product_variants
+        SET sku = COALESCE($2, sku),
+            options = COALESCE($3, options),
+            price = COALESCE($4, price)
+        WHERE id = $1
+        RETURNING id, product_id, sku, options as "options: Json<BTreeMap<String, String>>",
+                  price, stock, created_at
+        "#,
+        variant_id,
+        update_data.sku,
+        update_data.options.map(Json) as _,
+        price
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(variant)
+}
+
+pub async fn delete_variant_db(pool: &DatabasePool, variant_id: Uuid) -> Result<()> {
+    sqlx::query!("DELETE FROM product_variants WHERE id = $1", variant_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::db::db_con::DatabasePool;
+use crate::models::wishlist::WishlistItem;
+use anyhow::Result;
+use uuid::Uuid;
+
+pub async fn find_wishlist(pool: &DatabasePool, user_id: Uuid) -> Result<Vec<WishlistItem>> {
+    let items = sqlx::query_as!(
+        WishlistItem,
+        r#"
+        SELECT w.id, w.product_id, p.name as product_name, p.price, p.currency, p.image_url, p.stock,
+               EXISTS (
+                   SELECT 1 FROM stock_subscriptions s
+                   WHERE s.user_id = w.user_id AND s.product_id = w.product_id
+               ) as "notify_when_in_stock!",
+               w.created_at
+        FROM wishlist_items w
+        JOIN products p ON p.id = w.product_id
+        WHERE w.user_id = $1
+        ORDER BY w.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(items)
+}
+
+/// Add a product to the wishlist; adding it twice is a no-op
+pub async fn add_wishlist_item(pool: &DatabasePool, user_id: Uuid, product_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO wishlist_items (user_id, product_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, product_id) DO NOTHING
+        "#,
+        user_id,
+        product_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns false if the product was not on the wishlist
+pub async fn remove_wishlist_item(pool: &DatabasePool, user_id: Uuid, product_id: Uuid) -> Result<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM wishlist_items WHERE user_id = $1 AND product_id = $2",
+        user_id,
+        product_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn subscribe_to_stock(pool: &DatabasePool, user_id: Uuid, product_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO stock_subscriptions (user_id, product_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, product_id) DO NOTHING
+        "#,
+        user_id,
+        product_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn unsubscribe_from_stock(pool: &DatabasePool, user_id: Uuid, product_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM stock_subscriptions WHERE user_id = $1 AND product_id = $2",
+        user_id,
+        product_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
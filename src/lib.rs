@@ -3,9 +3,16 @@ pub mod db;
 pub mod services;
 pub mod utils;
 pub mod middleware;
+pub mod payments;
+pub mod notifications;
+pub mod mail;
 
 use crate::db::db_con::DatabasePool;
+use crate::mail::Mailer;
+use crate::payments::PaymentProvider;
 use crate::utils::jwt::JwtKeys;
+use crate::utils::revocation::RevocationList;
+use crate::utils::token::TokenHasher;
 use std::sync::Arc;
 
 
@@ -13,4 +20,8 @@ use std::sync::Arc;
 pub struct AppState {
     pub db_pool: DatabasePool,
     pub jwt_keys: Arc<JwtKeys>,
+    pub token_hasher: Arc<TokenHasher>,
+    pub revocations: Arc<RevocationList>,
+    pub payments: Arc<dyn PaymentProvider>,
+    pub mailer: Arc<dyn Mailer>,
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use super::{Email, Mailer};
+use anyhow::Result;
+use axum::async_trait;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// Development outbox that appends one JSON line per email to a file
+pub struct FileMailer {
+    path: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn send(&self, email: &Email) -> Result<()> {
+        let mut line = serde_json::to_string(email)?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+
+        Ok(())
+    }
+}
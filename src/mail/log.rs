@@ -0,0 +1,18 @@
+use super::{Email, Mailer};
+use anyhow::Result;
+use axum::async_trait;
+
+/// Development transport that only logs the email
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    async fn send(&self, email: &Email) -> Result<()> {
+        tracing::info!("Email to {}: {}\n{}", email.to, email.subject, email.body);
+        Ok(())
+    }
+}
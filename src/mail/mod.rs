@@ -0,0 +1,60 @@
+pub mod file;
+pub mod log;
+pub mod smtp;
+pub mod templates;
+
+use anyhow::Result;
+use axum::async_trait;
+use dotenvy::dotenv;
+use serde::Serialize;
+use std::sync::Arc;
+
+// Plain-text email handed to a transport
+#[derive(Debug, Clone, Serialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Transport for account emails (password resets, verification links, ...)
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    /// Short identifier used in logs (e.g. "smtp", "file")
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, email: &Email) -> Result<()>;
+}
+
+/// Pick the transport from MAILER ("log", "file" or "smtp"); the file outbox writes to MAIL_FILE
+pub fn mailer_from_env() -> Result<Arc<dyn Mailer>> {
+    dotenv().ok();
+    let mailer: Arc<dyn Mailer> = match std::env::var("MAILER").as_deref() {
+        Ok("smtp") => Arc::new(smtp::SmtpMailer::from_env()?),
+        Ok("file") => {
+            let path = std::env::var("MAIL_FILE").unwrap_or_else(|_| "outbox.log".to_string());
+            Arc::new(file::FileMailer::new(path))
+        }
+        _ => Arc::new(log::LogMailer),
+    };
+
+    Ok(mailer)
+}
+
+/// Send without making the caller wait, so response time does not reveal whether an email went out
+pub fn send_in_background(mailer: Arc<dyn Mailer>, email: Email) {
+    tokio::spawn(async move {
+        if let Err(e) = mailer.send(&email).await {
+            tracing::error!("{} mailer failed to send '{}': {:?}", mailer.name(), email.subject, e);
+        }
+    });
+}
+
+/// Base URL of the storefront, used to build links in emails
+pub fn app_url() -> String {
+    dotenv().ok();
+    std::env::var("APP_URL")
+        .unwrap_or_else(|_| "http://localhost:3000".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
@@ -0,0 +1,60 @@
+use super::{Email, Mailer};
+use anyhow::{anyhow, Context, Result};
+use axum::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// SMTP relay transport
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// SMTP_HOST, SMTP_PORT (587), SMTP_TLS ("starttls", "tls" or "none"),
+    /// optional SMTP_USERNAME/SMTP_PASSWORD, and MAIL_FROM as the sender
+    pub fn from_env() -> Result<Self> {
+        let host = std::env::var("SMTP_HOST").context("SMTP_HOST must be set when MAILER=smtp")?;
+        let port = std::env::var("SMTP_PORT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(587);
+
+        let builder = match std::env::var("SMTP_TLS").as_deref() {
+            Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            Ok("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+        };
+        let builder = match (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => builder.credentials(Credentials::new(username, password)),
+            _ => builder,
+        };
+
+        let from = std::env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@localhost".to_string());
+        let from = from.parse().map_err(|e| anyhow!("Invalid MAIL_FROM '{}': {}", from, e))?;
+
+        Ok(Self {
+            transport: builder.port(port).build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    fn name(&self) -> &'static str {
+        "smtp"
+    }
+
+    async fn send(&self, email: &Email) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(&email.subject)
+            .body(email.body.clone())?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use super::{app_url, Email};
+
+pub fn password_reset(to: &str, token: &str, valid_minutes: i64) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Someone asked to reset the password for this account.\n\n\
+             Use this link within {} minutes to choose a new password:\n{}/reset-password?token={}\n\n\
+             If this wasn't you, you can ignore this email.",
+            valid_minutes,
+            app_url(),
+            token
+        ),
+    }
+}
+
+pub fn email_verification(to: &str, token: &str, valid_hours: i64) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Confirm your email address".to_string(),
+        body: format!(
+            "Please confirm that this is your email address.\n\n             Open this link within {} hours:\n{}/verify-email?token={}\n\n             If you didn't create an account or change your email, you can ignore this email.",
+            valid_hours,
+            app_url(),
+            token
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_reset_email_links_token() {
+        let email = password_reset("user@example.com", "abc.def", 30);
+
+        assert_eq!(email.to, "user@example.com");
+        assert!(email.body.contains("/reset-password?token=abc.def"));
+        assert!(email.body.contains("30 minutes"));
+    }
+}
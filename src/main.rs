@@ -1,15 +1,22 @@
 use tests3::db::db_con::{create_pool};
-use tests3::services::{auth,profile,categories,products};
-use tests3::middleware::auth::{auth_required, admin_required};
+use tests3::services::{api_keys,auth,cart,profile,categories,currencies,lockouts,mfa,orders,payments,products,promotions,reviews,roles,users,variants,verification,wishlist};
+use tests3::middleware::auth::{auth_required, require_permission};
+use tests3::notifications::{notifier_from_env, spawn_dispatcher};
+use tests3::mail::mailer_from_env;
+use tests3::payments::payment_provider_from_env;
 use tests3::utils::jwt::JwtKeys;
+use tests3::utils::revocation::{spawn_revocation_refresher, RevocationList};
+use tests3::utils::token::TokenHasher;
 use axum::{
     extract::DefaultBodyLimit,
     http::Method,
     middleware,
-    routing::{get, post, put, delete},
+    routing::{get, post, put, patch, delete},
     Router,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
@@ -38,11 +45,40 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("Database connected successfully");
 
     // Create JWT keys
-    let jwt_keys = Arc::new(JwtKeys::from_env());
+    let jwt_keys = Arc::new(JwtKeys::from_env()?);
+
+    // Key for hashing stored tokens and recovery codes
+    let token_hasher = Arc::new(TokenHasher::from_env()?);
+
+    // Revoked access tokens, reloaded periodically so other instances' revocations apply here too
+    let revocations = Arc::new(RevocationList::new());
+    revocations.refresh(&db_pool).await?;
+    let revocation_seconds = std::env::var("REVOCATION_REFRESH_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+    spawn_revocation_refresher(db_pool.clone(), revocations.clone(), Duration::from_secs(revocation_seconds));
+
+    // Payment provider (PAYMENT_PROVIDER=mock until a vendor integration is configured)
+    let payments = payment_provider_from_env()?;
+
+    // Account emails (log/file outbox in development, SMTP in production)
+    let mailer = mailer_from_env()?;
+
+    // Deliver queued notifications (log/file sink in development)
+    let poll_seconds = std::env::var("NOTIFICATION_POLL_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10);
+    spawn_dispatcher(db_pool.clone(), notifier_from_env(), Duration::from_secs(poll_seconds));
 
     let state = AppState {
         db_pool,
         jwt_keys,
+        token_hasher,
+        revocations,
+        payments,
+        mailer,
     };
     
     // Create uploads directory if it doesn't exist
@@ -55,38 +91,195 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/auth/register", post(auth::register))
         .route("/api/auth/login", post(auth::login))
         .route("/api/auth/refresh", post(auth::refresh_token))
-        .route("/api/auth/logout", post(auth::logout));
+        .route("/api/auth/logout", post(auth::logout))
+        .route("/api/auth/password/forgot", post(auth::forgot_password))
+        .route("/api/auth/password/reset", post(auth::reset_password))
+        .route("/api/auth/email/verify", post(verification::verify_email))
+        .route("/api/auth/mfa/verify", post(auth::verify_mfa))
+        .route("/.well-known/jwks.json", get(auth::jwks));
 
     // Create protected user routes (with auth middleware)
     let protected_user_routes = Router::new()
         .route("/api/profile", get(profile::get_profile))
-        .route("/api/profile", put(profile::update_profile))
+        .route("/api/profile", put(profile::update_profile).delete(profile::delete_account))
+        .route("/api/profile/export", get(profile::export_account))
+        .route("/api/profile/password", put(profile::change_password))
+        .route("/api/profile/email/resend", post(verification::resend_verification))
+        .route("/api/profile/sessions", get(profile::list_sessions).delete(profile::revoke_other_sessions))
+        .route("/api/profile/sessions/:id", delete(profile::revoke_session))
+        .route("/api/profile/mfa", get(mfa::get_mfa_status).delete(mfa::disable))
+        .route("/api/profile/mfa/enroll", post(mfa::enroll))
+        .route("/api/profile/mfa/confirm", post(mfa::confirm))
+        .route("/api/profile/mfa/recovery-codes", post(mfa::regenerate_recovery_codes))
+        .route("/api/profile/api-keys", get(api_keys::list_api_keys).post(api_keys::create_api_key))
+        .route("/api/profile/api-keys/:id", delete(api_keys::delete_api_key))
+        .route("/api/profile/impersonation", delete(users::end_impersonation))
+        .route("/api/cart/items", get(cart::get_cart).post(cart::add_item).delete(cart::clear_items))
+        .route("/api/cart/items/:id", patch(cart::update_item).delete(cart::remove_item))
+        .route("/api/cart/coupon", put(cart::apply_coupon).delete(cart::remove_coupon))
+        .route("/api/cart/currency", put(cart::set_currency))
+        .route("/api/checkout", post(orders::checkout))
+        .route("/api/products/:id/reviews", post(reviews::create_review))
+        .route("/api/wishlist", get(wishlist::get_wishlist).post(wishlist::add_item))
+        .route("/api/wishlist/:id", delete(wishlist::remove_item))
+        .route("/api/wishlist/:id/notify", post(wishlist::subscribe).delete(wishlist::unsubscribe))
+        .route("/api/profile/orders", get(orders::list_my_orders))
+        .route("/api/profile/orders/:id", get(orders::get_my_order))
+        .route("/api/profile/orders/:id/cancel", post(orders::cancel_my_order))
+        .route("/api/profile/orders/:id/pay", post(payments::pay_order))
         .route_layer(middleware::from_fn_with_state(state.clone(), auth_required));
 
     // Create public routes (no middleware)
     let public_routes = Router::new()
         .route("/api/products", get(products::list_products))
         .route("/api/products/:id", get(products::get_product))
+        .route("/api/products/:id/variants", get(variants::list_variants))
+        .route("/api/products/:id/reviews", get(reviews::list_product_reviews))
         .route("/api/categories", get(categories::list_categories))
-        .route("/api/categories/:id", get(categories::get_category));
+        .route("/api/currencies", get(currencies::list_currencies))
+        .route("/api/categories/tree", get(categories::get_category_tree))
+        .route("/api/categories/:id", get(categories::get_category))
+        .route("/api/payments/webhook", post(payments::webhook))
+        .route("/api/guest-cart/items", get(cart::get_guest_cart).post(cart::add_guest_item))
+        .route("/api/guest-cart/items/:id", patch(cart::update_guest_item).delete(cart::remove_guest_item))
+        .route("/api/guest-cart/currency", put(cart::set_guest_currency));
 
-    // Create admin routes (with admin middleware)
+    // Create admin routes: each requires a permission from the user's roles
     let admin_routes = Router::new()
-        .route("/api/products", post(products::create_product))
-        .route("/api/products/:id", put(products::update_product))
-        .route("/api/products/:id", delete(products::delete_product))
-        .route("/api/products/:id/upload-image", post(products::upload_image))
-        .route("/api/categories", post(categories::create_category))
-        .route("/api/categories/:id", put(categories::update_category))
-        .route("/api/categories/:id", delete(categories::delete_category))
-        .route_layer(middleware::from_fn_with_state(state.clone(), admin_required));
+        .route("/api/products", post(products::create_product).route_layer(require_permission("products:write")))
+        .route("/api/products/:id", put(products::update_product).route_layer(require_permission("products:write")))
+        .route("/api/products/:id", delete(products::delete_product).route_layer(require_permission("products:delete")))
+        .route(
+            "/api/products/:id/upload-image",
+            post(products::upload_image).route_layer(require_permission("products:images")),
+        )
+        .route(
+            "/api/products/:id/stock/adjust",
+            post(products::adjust_product_stock).route_layer(require_permission("inventory:write")),
+        )
+        .route(
+            "/api/products/:id/stock/history",
+            get(products::stock_history).route_layer(require_permission("inventory:read")),
+        )
+        .route(
+            "/api/products/:id/variants",
+            post(variants::create_variant).route_layer(require_permission("products:write")),
+        )
+        .route(
+            "/api/products/:id/variants/:variant_id",
+            put(variants::update_variant).route_layer(require_permission("products:write")),
+        )
+        .route(
+            "/api/products/:id/variants/:variant_id",
+            delete(variants::delete_variant).route_layer(require_permission("products:delete")),
+        )
+        .route("/api/categories", post(categories::create_category).route_layer(require_permission("categories:write")))
+        .route("/api/categories/:id", put(categories::update_category).route_layer(require_permission("categories:write")))
+        .route(
+            "/api/categories/:id",
+            delete(categories::delete_category).route_layer(require_permission("categories:delete")),
+        )
+        .route("/api/admin/orders", get(orders::list_orders).route_layer(require_permission("orders:read")))
+        .route("/api/admin/orders/:id", get(orders::get_order).route_layer(require_permission("orders:read")))
+        .route(
+            "/api/admin/orders/:id/status",
+            put(orders::update_order_status).route_layer(require_permission("orders:manage")),
+        )
+        .route(
+            "/api/admin/orders/:id/payments",
+            get(payments::list_order_payments).route_layer(require_permission("orders:read")),
+        )
+        .route(
+            "/api/admin/orders/:id/capture",
+            post(payments::capture_order_payment).route_layer(require_permission("orders:manage")),
+        )
+        .route("/api/admin/orders/:id/refund", post(payments::refund_order).route_layer(require_permission("orders:refund")))
+        .route(
+            "/api/admin/exchange-rates",
+            get(currencies::list_exchange_rates).route_layer(require_permission("currencies:manage")),
+        )
+        .route(
+            "/api/admin/exchange-rates/:base/:quote",
+            put(currencies::set_exchange_rate)
+                .delete(currencies::delete_exchange_rate_pair)
+                .route_layer(require_permission("currencies:manage")),
+        )
+        .route("/api/admin/users", get(users::list_users).route_layer(require_permission("users:read")))
+        .route("/api/admin/users/:id", get(users::get_user).route_layer(require_permission("users:read")))
+        .route("/api/admin/users/:id/role", put(users::update_user_role).route_layer(require_permission("roles:manage")))
+        .route(
+            "/api/admin/users/:id/roles",
+            get(roles::get_user_roles)
+                .put(roles::set_user_roles)
+                .route_layer(require_permission("roles:manage")),
+        )
+        .route("/api/admin/users/:id/suspend", post(users::suspend_user).route_layer(require_permission("users:manage")))
+        .route(
+            "/api/admin/users/:id/reactivate",
+            post(users::reactivate_user).route_layer(require_permission("users:manage")),
+        )
+        .route("/api/admin/users/:id/logout", post(users::force_logout).route_layer(require_permission("users:manage")))
+        .route(
+            "/api/admin/users/:id/impersonate",
+            post(users::impersonate_user).route_layer(require_permission("users:impersonate")),
+        )
+        .route(
+            "/api/admin/users/:id/api-keys",
+            get(api_keys::list_user_api_keys).route_layer(require_permission("users:read")),
+        )
+        .route(
+            "/api/admin/users/:id/api-keys",
+            post(api_keys::create_user_api_key).route_layer(require_permission("users:manage")),
+        )
+        .route(
+            "/api/admin/users/:id/api-keys/:key_id",
+            delete(api_keys::delete_user_api_key).route_layer(require_permission("users:manage")),
+        )
+        .route("/api/admin/permissions", get(roles::list_permissions).route_layer(require_permission("roles:manage")))
+        .route(
+            "/api/admin/roles",
+            get(roles::list_roles)
+                .post(roles::create_role)
+                .route_layer(require_permission("roles:manage")),
+        )
+        .route(
+            "/api/admin/roles/:name",
+            put(roles::update_role)
+                .delete(roles::delete_role)
+                .route_layer(require_permission("roles:manage")),
+        )
+        .route("/api/admin/lockouts", get(lockouts::list_lockouts).route_layer(require_permission("lockouts:manage")))
+        .route(
+            "/api/admin/lockouts/:scope/:key",
+            delete(lockouts::clear_lockout).route_layer(require_permission("lockouts:manage")),
+        )
+        .route("/api/admin/reviews", get(reviews::list_reviews).route_layer(require_permission("reviews:moderate")))
+        .route("/api/admin/reviews/:id", delete(reviews::delete_review).route_layer(require_permission("reviews:moderate")))
+        .route(
+            "/api/admin/reviews/:id/status",
+            put(reviews::moderate_review).route_layer(require_permission("reviews:moderate")),
+        )
+        .route(
+            "/api/admin/promotions",
+            get(promotions::list_promotions)
+                .post(promotions::create_promotion)
+                .route_layer(require_permission("promotions:manage")),
+        )
+        .route(
+            "/api/admin/promotions/:id",
+            get(promotions::get_promotion)
+                .put(promotions::update_promotion)
+                .delete(promotions::delete_promotion)
+                .route_layer(require_permission("promotions:manage")),
+        )
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth_required));
 
     // Combine all routes
     let app = Router::new()
         .merge(auth_routes)           // No middleware
         .merge(protected_user_routes) // Auth middleware
         .merge(public_routes)         // No middleware  
-        .merge(admin_routes)          // Admin middleware
+        .merge(admin_routes)          // Auth middleware + permissions
         
         // Static file serving for uploaded images
         .nest_service("/uploads", ServeDir::new("uploads"))
@@ -104,7 +297,7 @@ async fn main() -> anyhow::Result<()> {
                 .layer(
                     CorsLayer::new()
                         .allow_origin(Any)
-                        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+                        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
                         .allow_headers(Any),
                 )
                 .layer(DefaultBodyLimit::max(10 * 1024 * 1024)) // 10MB max file size
@@ -114,7 +307,7 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
     tracing::info!("Server starting on http://0.0.0.0:3000");
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -1,13 +1,22 @@
-use crate::models::user::UserRole;
-use crate::utils::jwt::{extract_token_from_header, verify_access_token};
+use crate::db::apikeyq::{find_api_key, touch_api_key};
+use crate::db::userq::{find_by_id, find_user_status};
+use crate::models::api_key::strip_api_key_prefix;
+use crate::models::auth::Actor;
+use crate::models::permission::is_known_permission;
+use crate::models::user::{UserRole, UserStatus};
+use crate::services::mfa::mfa_required_for;
+use crate::services::roles::resolve_permissions;
+use crate::utils::jwt::{extract_token_from_header, verify_access_token, AuthToken};
 use crate::utils::error::{AppError, AppResult};
 use crate::AppState;
 use axum::{
     extract::{Request, State},
     http::{header::AUTHORIZATION, HeaderMap},
-    middleware::Next,
+    middleware::{self, FromFnLayer, Next},
     response::Response,
 };
+use std::future::Future;
+use std::pin::Pin;
 
 
 // Authentication state that gets injected into handlers
@@ -16,81 +25,160 @@ pub struct AuthUser {
     pub user_id: uuid::Uuid,
     pub username: String,
     pub role: UserRole,
+    pub session_id: Option<uuid::Uuid>,
+    pub mfa: bool,                // The session passed a second factor
+    pub permissions: Vec<String>, // From the token; see `require_permission`
+    pub api_key_id: Option<uuid::Uuid>, // Set when authenticated with an API key instead of a session
+    pub token_id: Option<uuid::Uuid>,   // `jti` of the access token
+    pub act: Option<Actor>,             // The admin impersonating the user, if any
+}
+
+impl AuthUser {
+    pub fn is_impersonated(&self) -> bool {
+        self.act.is_some()
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
 }
 
 
-// Middleware for required authentication (fails if no valid token)
+// Header for API keys; `Authorization: ApiKey <key>` works as well
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+// Middleware for required authentication (fails if no valid token or API key)
 pub async fn auth_required(
     State(state): State<AppState>,
     headers: HeaderMap,
     mut request: Request,
     next: Next,
 ) -> AppResult<Response> {
-    let keys = state.jwt_keys;
-    let auth_header = headers
-        .get(AUTHORIZATION)
-        .ok_or_else(|| AppError::Authentication("Missing authorization header".to_string()))?;
+    let token = match headers.get(API_KEY_HEADER) {
+        Some(api_key) => AuthToken::ApiKey(
+            api_key
+                .to_str()
+                .map_err(|_| AppError::Authentication("Invalid API key header".to_string()))?,
+        ),
+        None => {
+            let auth_header = headers
+                .get(AUTHORIZATION)
+                .ok_or_else(|| AppError::Authentication("Missing authorization header".to_string()))?;
+
+            let auth_str = auth_header
+                .to_str()
+                .map_err(|_| AppError::Authentication("Invalid authorization header".to_string()))?;
+
+            extract_token_from_header(auth_str)
+                .ok_or_else(|| AppError::Authentication("Invalid authorization format".to_string()))?
+        }
+    };
 
-    let auth_str = auth_header
-        .to_str()
-        .map_err(|_| AppError::Authentication("Invalid authorization header".to_string()))?;
+    let auth_user = match token {
+        AuthToken::Bearer(token) => access_token_user(&state, token).await?,
+        AuthToken::ApiKey(key) => api_key_user(&state, key).await?,
+    };
 
-    let token = extract_token_from_header(auth_str)
-        .ok_or_else(|| AppError::Authentication("Invalid authorization format".to_string()))?;
+    request.extensions_mut().insert(auth_user);
+    Ok(next.run(request).await)
+}
 
-    let claims = verify_access_token(token, &keys)?;
+async fn access_token_user(state: &AppState, token: &str) -> AppResult<AuthUser> {
+    let claims = verify_access_token(token, &state.jwt_keys)?;
 
     let user_id = uuid::Uuid::parse_str(&claims.sub)
         .map_err(|_| AppError::Authentication("Invalid user ID in token".to_string()))?;
 
-    let auth_user = AuthUser {
+    if state.revocations.is_revoked(user_id, &claims) {
+        return Err(AppError::token_revoked());
+    }
+
+    // Suspending or deleting an account takes effect before its access tokens expire
+    let status = find_user_status(&state.db_pool, user_id)
+        .await?
+        .ok_or_else(AppError::invalid_token)?;
+    if status != UserStatus::Active {
+        return Err(AppError::account_inactive(status));
+    }
+
+    Ok(AuthUser {
         user_id,
         username: claims.username,
         role: claims.role,
-    };
+        session_id: claims.sid,
+        mfa: claims.mfa,
+        permissions: claims.perms,
+        api_key_id: None,
+        token_id: Some(claims.jti),
+        act: claims.act,
+    })
+}
 
-    request.extensions_mut().insert(auth_user);
-    Ok(next.run(request).await)
+/// Keys act as their user, with the key's permissions that the user still holds
+async fn api_key_user(state: &AppState, key: &str) -> AppResult<AuthUser> {
+    let pool = &state.db_pool;
+    let invalid_key = || AppError::Authentication("Invalid API key".to_string());
+
+    let now = time::OffsetDateTime::now_utc();
+    let api_key = match strip_api_key_prefix(key.trim()) {
+        Some(token) => find_api_key(pool, &state.token_hasher, token).await?,
+        None => None,
+    }
+    .filter(|api_key| api_key.is_active(now))
+    .ok_or_else(invalid_key)?;
+
+    let user = find_by_id(pool, api_key.user_id).await?.ok_or_else(invalid_key)?;
+    if user.status != UserStatus::Active {
+        return Err(AppError::account_inactive(user.status));
+    }
+
+    let held = resolve_permissions(pool, &user).await?;
+    if api_key.is_due_for_touch(now) {
+        touch_api_key(pool, api_key.id).await?;
+    }
+    let permissions = api_key.permissions.into_iter().filter(|p| held.contains(p)).collect();
+
+    Ok(AuthUser {
+        user_id: user.id,
+        username: user.username,
+        role: user.role,
+        session_id: None,
+        // As recorded when the key was created, so a later promotion still needs a new key
+        mfa: api_key.mfa_verified,
+        permissions,
+        api_key_id: Some(api_key.id),
+        token_id: None,
+        act: None,
+    })
 }
 
-// Middleware for admin-only access
-pub async fn admin_required(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    mut request: Request,
-    next: Next,
-) -> AppResult<Response> {
-    let keys = state.jwt_keys;
-    let auth_header = headers
-        .get(AUTHORIZATION)
-        .ok_or_else(|| AppError::Authentication("Missing authorization header".to_string()))?;
-
-    let auth_str = auth_header
-        .to_str()
-        .map_err(|_| AppError::Authentication("Invalid authorization header".to_string()))?;
-
-    let token = extract_token_from_header(auth_str)
-        .ok_or_else(|| AppError::Authentication("Invalid authorization format".to_string()))?;
-
-    let claims = verify_access_token(token, &keys)?;
-
-    // Check if user is admin
-    match claims.role {
-        UserRole::Admin => {
-            let user_id = uuid::Uuid::parse_str(&claims.sub)
-                .map_err(|_| AppError::Authentication("Invalid user ID in token".to_string()))?;
-
-            let auth_user = AuthUser {
-                user_id,
-                username: claims.username,
-                role: claims.role,
-            };
-
-            request.extensions_mut().insert(auth_user);
-            Ok(next.run(request).await)
-        }
-        UserRole::User => Err(AppError::insufficient_permissions()),
+type PermissionCheck = Pin<Box<dyn Future<Output = AppResult<Response>> + Send>>;
+
+/// Route layer letting a request through only if the user has `permission`.
+/// Goes on routes behind `auth_required`, which puts the `AuthUser` in the request.
+pub fn require_permission(
+    permission: &'static str,
+) -> FromFnLayer<impl Fn(Request, Next) -> PermissionCheck + Clone + Send + Sync + 'static, (), (Request,)> {
+    debug_assert!(is_known_permission(permission), "unknown permission {}", permission);
+    middleware::from_fn(move |request: Request, next: Next| -> PermissionCheck {
+        Box::pin(check_permission(permission, request, next))
+    })
+}
+
+async fn check_permission(permission: &'static str, request: Request, next: Next) -> AppResult<Response> {
+    let auth_user = request.require_auth_user()?;
+    if !auth_user.has_permission(permission) {
+        return Err(AppError::insufficient_permissions());
     }
+
+    // Roles that must use two-factor authentication need a session that passed it
+    if mfa_required_for(&auth_user.role) && !auth_user.mfa {
+        return Err(AppError::Authorization(
+            "Two-factor authentication is required: enable it under /api/profile/mfa and log in again".to_string(),
+        ));
+    }
+
+    Ok(next.run(request).await)
 }
 
 // Extension trait for easy access to authenticated user
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+use validator::Validate;
+
+// Marks a string as one of our API keys (and keeps it recognisable in logs and secret scanners)
+pub const API_KEY_PREFIX: &str = "sk_";
+
+// How often `last_used_at` is written for a key in use; matches the guard in `touch_api_key`
+pub const API_KEY_TOUCH_INTERVAL: Duration = Duration::minutes(1);
+
+/// Mark a split token as an API key: `sk_<selector>.<verifier>` for the client, `sk_<selector>` as the stored prefix
+pub fn format_api_key(token: &str) -> String {
+    format!("{}{}", API_KEY_PREFIX, token)
+}
+
+/// The split token inside a presented key; `None` if it is not one of our keys
+pub fn strip_api_key_prefix(key: &str) -> Option<&str> {
+    key.strip_prefix(API_KEY_PREFIX)
+}
+
+// API key (stored in database)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub prefix: String, // `sk_<selector>`, enough to tell keys apart
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub permissions: Vec<String>,
+    pub created_by: Option<Uuid>, // Differs from user_id for keys an admin created
+    pub mfa_verified: bool,       // The session that created the key had passed a second factor
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl ApiKey {
+    pub fn is_active(&self, now: OffsetDateTime) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > now)
+    }
+
+    pub fn is_due_for_touch(&self, now: OffsetDateTime) -> bool {
+        self.last_used_at.is_none_or(|last_used_at| now - last_used_at >= API_KEY_TOUCH_INTERVAL)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiKey {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    pub permissions: Vec<String>, // Must be a subset of the owner's permissions
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+}
+
+// Returned once on creation; the key cannot be shown again
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_key(expires_at: Option<OffsetDateTime>, revoked_at: Option<OffsetDateTime>) -> ApiKey {
+        ApiKey {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "inventory sync".to_string(),
+            prefix: "sk_0123".to_string(),
+            key_hash: String::new(),
+            permissions: vec!["inventory:write".to_string()],
+            created_by: None,
+            mfa_verified: false,
+            expires_at,
+            last_used_at: None,
+            revoked_at,
+            created_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    #[test]
+    fn test_api_key_active() {
+        let now = OffsetDateTime::now_utc();
+        assert!(api_key(None, None).is_active(now));
+        assert!(api_key(Some(now + Duration::days(1)), None).is_active(now));
+        assert!(!api_key(Some(now - Duration::seconds(1)), None).is_active(now));
+        assert!(!api_key(None, Some(now)).is_active(now));
+    }
+
+    #[test]
+    fn test_api_key_touch_interval() {
+        let now = OffsetDateTime::now_utc();
+        let mut key = api_key(None, None);
+        assert!(key.is_due_for_touch(now));
+        key.last_used_at = Some(now - Duration::seconds(30));
+        assert!(!key.is_due_for_touch(now));
+        key.last_used_at = Some(now - API_KEY_TOUCH_INTERVAL);
+        assert!(key.is_due_for_touch(now));
+    }
+
+    #[test]
+    fn test_api_key_prefix() {
+        let key = format_api_key("abc.def");
+        assert_eq!(key, "sk_abc.def");
+        assert_eq!(strip_api_key_prefix(&key), Some("abc.def"));
+        assert_eq!(strip_api_key_prefix("abc.def"), None);
+    }
+}
@@ -2,6 +2,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use time::OffsetDateTime;
+use crate::models::mfa::MfaChallengeResponse;
 use crate::models::user::{User, UserRole};
 use validator::Validate;
 
@@ -11,6 +12,11 @@ pub struct LoginRequest {
      #[validate(email(message = "Invalid email format"))]
     pub email: String,
     pub password: String,
+    // Anonymous cart to merge into the user's cart
+    pub cart_token: Option<String>,
+    // Shown in the session list, e.g. "Work laptop"
+    #[validate(length(max = 100, message = "Device label cannot exceed 100 characters"))]
+    pub device_label: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -22,6 +28,14 @@ pub struct AuthResponse {
     pub user: User,
 }
 
+// Login either completes or asks for the second factor
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    MfaRequired(MfaChallengeResponse),
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshTokenRequest {
     pub refresh_token: String,
@@ -33,16 +47,120 @@ pub struct Claims {
     pub sub: String, // User ID
     pub username: String,
     pub role: UserRole,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>, // Session (refresh token) the access token was issued for
+    pub jti: Uuid,         // Unique token id, used to revoke this token alone
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mfa: bool, // Issued for a session that passed a second factor
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub perms: Vec<String>, // Permissions of the user's roles when the token was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>, // Set when an admin is acting as the user (impersonation)
     pub exp: usize,
     pub iat: usize,
 }
 
+// The admin behind an impersonation token (the `act` claim of RFC 8693)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: Uuid,
+    pub username: String,
+}
+
 // Refresh token model (stored in database)
 #[derive(Debug, Clone, FromRow)]
 pub struct RefreshToken {
     pub id: Uuid,
     pub user_id: Uuid,
+    pub family_id: Uuid,         // Shared by every rotation of one login; the session id
+    pub parent_id: Option<Uuid>, // Token this one was rotated from
+    pub selector: String,      // Public half of the token, used for the lookup
+    pub verifier_hash: String, // Keyed hash of the secret half; the token itself is never stored
+    pub device_label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: OffsetDateTime,
+    pub last_used_at: OffsetDateTime,
+    pub rotated_at: Option<OffsetDateTime>, // Set once exchanged; presenting it again is reuse
+    pub revoked_at: Option<OffsetDateTime>,
+    pub mfa_verified: bool, // The login passed a second factor
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+// Password reset token model (stored in database); the token itself is only ever emailed
+#[derive(Debug, Clone, FromRow)]
+pub struct PasswordResetToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub selector: String,
+    pub verifier_hash: String,
+    pub expires_at: OffsetDateTime,
+    pub used_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
     pub token: String,
+}
+
+// Email verification token model (stored in database)
+#[derive(Debug, Clone, FromRow)]
+pub struct EmailVerificationToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub email: String, // Address the link confirms
+    pub selector: String,
+    pub verifier_hash: String,
+    pub expires_at: OffsetDateTime,
+    pub created_at: OffsetDateTime,
+}
+
+// Entry of the access token revocation list (a jti or a session id)
+#[derive(Debug, Clone, FromRow)]
+pub struct RevokedToken {
+    pub id: Uuid,
     pub expires_at: OffsetDateTime,
+}
+
+// Access tokens of the user issued up to `tokens_valid_after` (whole seconds) are rejected
+#[derive(Debug, Clone, FromRow)]
+pub struct TokenWatermark {
+    pub user_id: Uuid,
+    pub tokens_valid_after: OffsetDateTime,
+}
+
+// Client details recorded on a session
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+// Active session (for API responses)
+#[derive(Debug, Serialize, FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub device_label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub current: bool, // The session the request was made from
+    #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
-}
\ No newline at end of file
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_used_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
@@ -0,0 +1,213 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use crate::models::promotion::{AppliedPromotion, PromotionLine, PromotionOutcome, RejectedPromotion};
+
+// Cart model (owned by a user or by an anonymous cart token)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Cart {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub token: Option<String>,
+    pub coupon_code: Option<String>,
+    pub currency: String, // Every line is priced in this currency
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// Cart line joined with current product data
+#[derive(Debug, Clone, FromRow)]
+pub struct CartLine {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub category_id: Uuid,
+    pub product_name: String,
+    pub sku: Option<String>,
+    pub unit_price: Option<Decimal>, // In the cart's currency; `None` when there is no exchange rate
+    pub available_stock: i32,
+    pub quantity: i32,
+}
+
+// Add item request
+#[derive(Debug, Deserialize)]
+pub struct AddCartItem {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub quantity: i32,
+}
+
+// Change cart currency request
+#[derive(Debug, Deserialize)]
+pub struct SetCartCurrency {
+    pub currency: String,
+}
+
+// Change quantity request
+#[derive(Debug, Deserialize)]
+pub struct UpdateCartItem {
+    pub quantity: i32,
+}
+
+// Cart item (for API responses)
+#[derive(Debug, Serialize)]
+pub struct CartItemView {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub category_id: Uuid,
+    pub product_name: String,
+    pub sku: Option<String>,
+    pub unit_price: Option<Decimal>,
+    pub quantity: i32,
+    pub line_total: Decimal,
+    pub available_stock: i32,
+    pub issue: Option<String>, // Set when the item cannot be bought as-is
+}
+
+// Cart with totals (for API responses)
+#[derive(Debug, Serialize)]
+pub struct CartView {
+    pub cart_id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cart_token: Option<String>,
+    pub currency: String,
+    pub items: Vec<CartItemView>,
+    pub item_count: i32,
+    pub subtotal: Decimal,
+    pub discount: Decimal,
+    pub total: Decimal,
+    pub coupon_code: Option<String>,
+    pub applied_promotions: Vec<AppliedPromotion>,
+    pub rejected_promotions: Vec<RejectedPromotion>,
+    pub has_issues: bool,
+}
+
+impl CartView {
+    /// Price the cart lines. Items that are out of stock, exceed the available stock
+    /// or cannot be priced in the cart's currency are flagged and left out of the subtotal.
+    pub fn from_lines(cart: &Cart, lines: Vec<CartLine>) -> Self {
+        let mut subtotal = Decimal::ZERO;
+        let mut item_count = 0;
+
+        let items: Vec<CartItemView> = lines
+            .into_iter()
+            .map(|line| {
+                let issue = if line.unit_price.is_none() {
+                    Some(format!("Price not available in {}", cart.currency))
+                } else if line.available_stock <= 0 {
+                    Some("Out of stock".to_string())
+                } else if line.quantity > line.available_stock {
+                    Some(format!("Only {} left in stock", line.available_stock))
+                } else {
+                    None
+                };
+
+                let line_total = line.unit_price.unwrap_or_default() * Decimal::from(line.quantity);
+                if issue.is_none() {
+                    subtotal += line_total;
+                    item_count += line.quantity;
+                }
+
+                CartItemView {
+                    id: line.id,
+                    product_id: line.product_id,
+                    variant_id: line.variant_id,
+                    category_id: line.category_id,
+                    product_name: line.product_name,
+                    sku: line.sku,
+                    unit_price: line.unit_price,
+                    quantity: line.quantity,
+                    line_total,
+                    available_stock: line.available_stock,
+                    issue,
+                }
+            })
+            .collect();
+
+        CartView {
+            cart_id: cart.id,
+            cart_token: cart.token.clone(),
+            currency: cart.currency.clone(),
+            has_issues: items.iter().any(|i| i.issue.is_some()),
+            items,
+            item_count,
+            subtotal,
+            discount: Decimal::ZERO,
+            total: subtotal,
+            coupon_code: cart.coupon_code.clone(),
+            applied_promotions: Vec::new(),
+            rejected_promotions: Vec::new(),
+        }
+    }
+
+    /// Purchasable lines, as seen by the promotion engine
+    pub fn promotion_lines(&self) -> Vec<PromotionLine> {
+        self.items
+            .iter()
+            .filter(|item| item.issue.is_none())
+            .map(|item| PromotionLine {
+                product_id: item.product_id,
+                category_id: item.category_id,
+                line_total: item.line_total,
+            })
+            .collect()
+    }
+
+    pub fn apply_promotions(&mut self, outcome: PromotionOutcome) {
+        self.discount = outcome.discount;
+        self.total = self.subtotal - outcome.discount;
+        self.applied_promotions = outcome.applied;
+        self.rejected_promotions = outcome.rejected;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn line(price: &str, stock: i32, quantity: i32) -> CartLine {
+        CartLine {
+            id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            variant_id: None,
+            category_id: Uuid::new_v4(),
+            product_name: "Widget".to_string(),
+            sku: None,
+            unit_price: Some(Decimal::from_str(price).unwrap()),
+            available_stock: stock,
+            quantity,
+        }
+    }
+
+    #[test]
+    fn test_cart_totals_skip_flagged_items() {
+        let cart = Cart {
+            id: Uuid::new_v4(),
+            user_id: Some(Uuid::new_v4()),
+            token: None,
+            coupon_code: None,
+            currency: "USD".to_string(),
+            created_at: OffsetDateTime::now_utc(),
+        };
+
+        let mut unpriced = line("3.00", 5, 1);
+        unpriced.unit_price = None;
+
+        let view = CartView::from_lines(
+            &cart,
+            vec![line("19.99", 10, 2), line("5.00", 0, 1), line("1.10", 2, 3), unpriced],
+        );
+
+        assert_eq!(view.subtotal, Decimal::from_str("39.98").unwrap());
+        assert_eq!(view.item_count, 2);
+        assert!(view.has_issues);
+        assert_eq!(view.items[1].issue.as_deref(), Some("Out of stock"));
+        assert_eq!(view.items[2].issue.as_deref(), Some("Only 2 left in stock"));
+        assert_eq!(view.items[3].issue.as_deref(), Some("Price not available in USD"));
+    }
+}
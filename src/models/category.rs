@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 // Category model
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -7,6 +8,7 @@ pub struct Category {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
 }
 
 // Category creation request
@@ -14,10 +16,109 @@ pub struct Category {
 pub struct CreateCategory {
     pub name: String,
     pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateCategory {
     pub name: Option<String>,
     pub description: Option<String>,
-}
\ No newline at end of file
+    pub parent_id: Option<Uuid>,
+    // Set to true to move the category back to the top level
+    pub make_root: Option<bool>,
+}
+
+// Category with its breadcrumb trail, ordered from the root down to the direct parent
+#[derive(Debug, Serialize)]
+pub struct CategoryWithAncestors {
+    #[serde(flatten)]
+    pub category: Category,
+    pub ancestors: Vec<Category>,
+}
+
+// Node of the nested category tree
+#[derive(Debug, Serialize)]
+pub struct CategoryNode {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub children: Vec<CategoryNode>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteCategoryQuery {
+    // Move children up to the deleted category's parent instead of refusing
+    pub reparent: Option<bool>,
+}
+
+impl CategoryNode {
+    /// Build the nested tree from a flat list of categories.
+    /// Categories whose parent is missing from the list are treated as roots.
+    pub fn build_tree(categories: Vec<Category>) -> Vec<CategoryNode> {
+        let ids: HashSet<Uuid> = categories.iter().map(|c| c.id).collect();
+        let mut children: HashMap<Option<Uuid>, Vec<Category>> = HashMap::new();
+
+        for category in categories {
+            let key = category.parent_id.filter(|p| ids.contains(p));
+            children.entry(key).or_default().push(category);
+        }
+
+        fn attach(
+            parent: Option<Uuid>,
+            children: &mut HashMap<Option<Uuid>, Vec<Category>>,
+        ) -> Vec<CategoryNode> {
+            let Some(level) = children.remove(&parent) else {
+                return Vec::new();
+            };
+
+            level
+                .into_iter()
+                .map(|c| CategoryNode {
+                    children: attach(Some(c.id), children),
+                    id: c.id,
+                    name: c.name,
+                    description: c.description,
+                    parent_id: c.parent_id,
+                })
+                .collect()
+        }
+
+        attach(None, &mut children)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(name: &str, parent_id: Option<Uuid>) -> Category {
+        Category {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            parent_id,
+        }
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let electronics = category("Electronics", None);
+        let laptops = category("Laptops", Some(electronics.id));
+        let gaming = category("Gaming", Some(laptops.id));
+        let books = category("Books", None);
+
+        let tree = CategoryNode::build_tree(vec![
+            books.clone(),
+            electronics.clone(),
+            gaming.clone(),
+            laptops.clone(),
+        ]);
+
+        assert_eq!(tree.len(), 2);
+        let electronics_node = tree.iter().find(|n| n.id == electronics.id).unwrap();
+        assert_eq!(electronics_node.children.len(), 1);
+        assert_eq!(electronics_node.children[0].id, laptops.id);
+        assert_eq!(electronics_node.children[0].children[0].id, gaming.id);
+    }
+}
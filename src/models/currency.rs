@@ -0,0 +1,89 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Prices are stored in this currency unless another is given
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+// Currency model
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Currency {
+    pub code: String,
+    pub name: String,
+    pub decimals: i16, // Minor-unit digits, e.g. 2 for USD and 0 for JPY
+}
+
+// Exchange rate model: 1 base_currency = rate quote_currency
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ExchangeRate {
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub rate: Decimal,
+    pub updated_by: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+// Exchange rate create/replace request
+#[derive(Debug, Deserialize)]
+pub struct SetExchangeRate {
+    pub rate: String, // We'll parse this to Decimal
+}
+
+// `?currency=EUR` on product endpoints
+#[derive(Debug, Deserialize)]
+pub struct CurrencyQuery {
+    pub currency: Option<String>,
+}
+
+/// Round an amount to the currency's minor unit, half away from zero (like Postgres ROUND)
+pub fn round_to_currency(amount: Decimal, decimals: i16) -> Decimal {
+    amount.round_dp_with_strategy(decimals.max(0) as u32, RoundingStrategy::MidpointAwayFromZero)
+}
+
+// Rate from one stored currency into the requested one
+#[derive(Debug, Clone)]
+pub struct PriceConversion {
+    pub currency: Currency,
+    pub rate: Decimal,
+}
+
+impl PriceConversion {
+    pub fn apply(&self, amount: Decimal) -> Decimal {
+        round_to_currency(amount * self.rate, self.currency.decimals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn conversion(code: &str, decimals: i16, rate: &str) -> PriceConversion {
+        PriceConversion {
+            currency: Currency {
+                code: code.to_string(),
+                name: code.to_string(),
+                decimals,
+            },
+            rate: Decimal::from_str(rate).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_conversion_rounds_to_currency_decimals() {
+        let price = Decimal::from_str("19.99").unwrap();
+
+        assert_eq!(conversion("JPY", 0, "151.37").apply(price), Decimal::from(3026));
+        assert_eq!(conversion("EUR", 2, "0.9215").apply(price), Decimal::from_str("18.42").unwrap());
+        assert_eq!(conversion("KWD", 3, "0.30745").apply(price), Decimal::from_str("6.146").unwrap());
+    }
+
+    #[test]
+    fn test_rounding_is_half_away_from_zero() {
+        assert_eq!(round_to_currency(Decimal::from_str("2.5").unwrap(), 0), Decimal::from(3));
+        assert_eq!(round_to_currency(Decimal::from_str("1.005").unwrap(), 2), Decimal::from_str("1.01").unwrap());
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Why stock changed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "stock_movement_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum StockMovementKind {
+    Receipt,
+    Sale,
+    Adjustment,
+    Return,
+}
+
+impl StockMovementKind {
+    /// Receipts and returns add stock, sales remove it, adjustments go either way
+    pub fn allows_delta(&self, delta: i32) -> bool {
+        match self {
+            StockMovementKind::Receipt | StockMovementKind::Return => delta > 0,
+            StockMovementKind::Sale => delta < 0,
+            StockMovementKind::Adjustment => delta != 0,
+        }
+    }
+}
+
+// Stock movement (ledger entry)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StockMovement {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>, // Set for variant stock; balance_after is then the variant's
+    pub kind: StockMovementKind,
+    pub quantity: i32,
+    pub balance_after: i32,
+    pub reason: Option<String>,
+    pub user_id: Option<Uuid>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// Stock adjustment request (delta semantics)
+#[derive(Debug, Deserialize)]
+pub struct AdjustStockRequest {
+    pub delta: i32,
+    pub kind: StockMovementKind,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StockHistoryQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_movement_kind_delta_direction() {
+        assert!(StockMovementKind::Receipt.allows_delta(5));
+        assert!(!StockMovementKind::Receipt.allows_delta(-5));
+        assert!(StockMovementKind::Sale.allows_delta(-1));
+        assert!(!StockMovementKind::Sale.allows_delta(1));
+        assert!(StockMovementKind::Adjustment.allows_delta(-3));
+        assert!(!StockMovementKind::Adjustment.allows_delta(0));
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::{Duration, OffsetDateTime};
+
+// What a failure counter is keyed by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "throttle_scope", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ThrottleScope {
+    Email,
+    Ip,
+}
+
+// Failed login counter for one email or IP
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct LoginThrottle {
+    pub scope: ThrottleScope,
+    pub key: String,
+    pub failures: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub last_failure_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub locked_until: Option<OffsetDateTime>,
+}
+
+impl LoginThrottle {
+    /// Seconds until login is allowed again, if currently locked
+    pub fn retry_after(&self, now: OffsetDateTime) -> Option<u64> {
+        self.locked_until
+            .filter(|until| *until > now)
+            .map(|until| (until - now).whole_seconds().max(1) as u64)
+    }
+}
+
+// Admin listing filter
+#[derive(Debug, Deserialize)]
+pub struct LockoutQuery {
+    pub scope: Option<ThrottleScope>,
+    pub locked_only: Option<bool>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// How failures translate into waiting time: a few free attempts, then exponential back-off
+/// doubling from `base_delay` up to `max_delay`, and a full lockout from `lockout_after` failures.
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottlePolicy {
+    pub free_attempts: i32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub lockout_after: i32,
+    pub lockout: Duration,
+    pub window: Duration, // Counters restart after this long without failures
+}
+
+impl ThrottlePolicy {
+    pub const EMAIL: ThrottlePolicy = ThrottlePolicy {
+        free_attempts: 3,
+        base_delay: Duration::seconds(1),
+        max_delay: Duration::minutes(5),
+        lockout_after: 10,
+        lockout: Duration::minutes(15),
+        window: Duration::hours(1),
+    };
+
+    // One address may legitimately serve many users (NAT, offices), so IPs get more headroom
+    pub const IP: ThrottlePolicy = ThrottlePolicy {
+        free_attempts: 20,
+        base_delay: Duration::seconds(1),
+        max_delay: Duration::minutes(5),
+        lockout_after: 100,
+        lockout: Duration::minutes(30),
+        window: Duration::hours(1),
+    };
+
+    pub fn for_scope(scope: ThrottleScope) -> Self {
+        match scope {
+            ThrottleScope::Email => Self::EMAIL,
+            ThrottleScope::Ip => Self::IP,
+        }
+    }
+
+    /// Wait imposed after the given number of consecutive failures
+    pub fn delay_after(&self, failures: i32) -> Option<Duration> {
+        if failures >= self.lockout_after {
+            return Some(self.lockout);
+        }
+        if failures <= self.free_attempts {
+            return None;
+        }
+
+        let exponent = (failures - self.free_attempts - 1).min(30) as u32;
+        let delay = self.base_delay * 2_i32.saturating_pow(exponent);
+        Some(delay.min(self.max_delay))
+    }
+
+    pub fn is_lockout(&self, failures: i32) -> bool {
+        failures >= self.lockout_after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_backs_off_then_locks() {
+        let policy = ThrottlePolicy::EMAIL;
+
+        assert_eq!(policy.delay_after(3), None);
+        assert_eq!(policy.delay_after(4), Some(Duration::seconds(1)));
+        assert_eq!(policy.delay_after(5), Some(Duration::seconds(2)));
+        assert_eq!(policy.delay_after(7), Some(Duration::seconds(8)));
+        assert_eq!(policy.delay_after(10), Some(Duration::minutes(15)));
+        assert!(policy.is_lockout(10));
+
+        let slow = ThrottlePolicy { lockout_after: i32::MAX, ..policy };
+        assert_eq!(slow.delay_after(40), Some(Duration::minutes(5)));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = OffsetDateTime::now_utc();
+        let mut throttle = LoginThrottle {
+            scope: ThrottleScope::Email,
+            key: "user@example.com".to_string(),
+            failures: 5,
+            last_failure_at: now,
+            locked_until: Some(now + Duration::seconds(30)),
+        };
+
+        assert_eq!(throttle.retry_after(now), Some(30));
+        throttle.locked_until = Some(now - Duration::seconds(1));
+        assert_eq!(throttle.retry_after(now), None);
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Wrong codes allowed on one login challenge before it is discarded
+pub const MFA_MAX_ATTEMPTS: i32 = 5;
+// Recovery codes issued on confirmation (and on regeneration)
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+// TOTP enrollment (stored in database); unconfirmed until the first code is checked
+#[derive(Debug, Clone, FromRow)]
+pub struct UserTotp {
+    pub user_id: Uuid,
+    pub secret: String, // Hex encoded shared secret
+    pub confirmed_at: Option<OffsetDateTime>,
+    pub last_used_step: Option<i64>, // Last accepted time step; older or equal steps are replays
+    pub created_at: OffsetDateTime,
+}
+
+impl UserTotp {
+    pub fn is_enabled(&self) -> bool {
+        self.confirmed_at.is_some()
+    }
+
+    pub fn secret_bytes(&self) -> Option<Vec<u8>> {
+        hex::decode(&self.secret).ok()
+    }
+}
+
+// Pending second login step (stored in database)
+#[derive(Debug, Clone, FromRow)]
+pub struct MfaChallenge {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub selector: String,
+    pub verifier_hash: String,
+    pub device_label: Option<String>, // Login details replayed once the challenge is passed
+    pub cart_token: Option<String>,
+    pub attempts: i32,
+    pub expires_at: OffsetDateTime,
+    pub created_at: OffsetDateTime,
+}
+
+// Returned by login instead of AuthResponse while a second factor is outstanding
+#[derive(Debug, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    pub expires_in: i64, // Seconds
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMfaRequest {
+    pub mfa_token: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>, // Used instead of a code when the authenticator is lost
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaEnrollment {
+    pub secret: String, // Base32, for manual entry
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DisableMfaRequest {
+    pub current_password: String,
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+// Shown once; only hashes are kept
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaStatus {
+    pub enabled: bool,
+    pub required: bool, // Enforced for the user's role
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub enabled_at: Option<OffsetDateTime>,
+    pub recovery_codes_remaining: i64,
+}
@@ -1,5 +1,19 @@
+pub mod api_key;
 pub mod auth;
+pub mod cart;
 pub mod category;
+pub mod currency;
+pub mod inventory;
+pub mod lockout;
+pub mod mfa;
+pub mod notification;
+pub mod order;
+pub mod payment;
 pub mod product;
+pub mod promotion;
+pub mod review;
+pub mod security;
 pub mod user;
+pub mod wishlist;
+pub mod permission;
 pub mod other;
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+pub const BACK_IN_STOCK: &str = "back_in_stock";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "notification_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+// Queued notification with its recipient, as handed to a notifier
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OutgoingNotification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+    pub username: String,
+    pub kind: String,
+    pub subject: String,
+    pub body: String,
+    pub attempts: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
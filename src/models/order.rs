@@ -0,0 +1,132 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Order status (state machine)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "order_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OrderStatus {
+    Pending,
+    Paid,
+    Shipped,
+    Delivered,
+    Cancelled,
+    Refunded,
+}
+
+impl OrderStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Shipped => "shipped",
+            OrderStatus::Delivered => "delivered",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Refunded => "refunded",
+        }
+    }
+
+    /// pending -> paid -> shipped -> delivered, with cancellation before payment
+    /// and refunds once paid. Cancelled and refunded orders are final.
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        matches!(
+            (self, next),
+            (OrderStatus::Pending, OrderStatus::Paid)
+                | (OrderStatus::Pending, OrderStatus::Cancelled)
+                | (OrderStatus::Paid, OrderStatus::Shipped)
+                | (OrderStatus::Paid, OrderStatus::Refunded)
+                | (OrderStatus::Shipped, OrderStatus::Delivered)
+                | (OrderStatus::Delivered, OrderStatus::Refunded)
+        )
+    }
+
+    /// Statuses only the payment flow may set: a refund has to move money, so it goes through
+    /// the refund endpoint or the provider's webhook, never the admin status endpoint
+    pub fn is_set_by_payments(&self) -> bool {
+        matches!(self, OrderStatus::Refunded)
+    }
+}
+
+// Order model
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Order {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub status: OrderStatus,
+    pub subtotal: Decimal,
+    pub discount: Decimal,
+    pub total: Decimal,
+    pub currency: String, // Of every amount on the order, its items, payments and refunds
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+// Order line, snapshotting the product at purchase time
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct OrderItem {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub product_id: Option<Uuid>,
+    pub variant_id: Option<Uuid>,
+    pub product_name: String,
+    pub sku: Option<String>,
+    pub unit_price: Decimal,
+    pub quantity: i32,
+    pub line_total: Decimal,
+}
+
+// Order with its items (for API responses)
+#[derive(Debug, Serialize)]
+pub struct OrderWithItems {
+    #[serde(flatten)]
+    pub order: Order,
+    pub items: Vec<OrderItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateOrderStatus {
+    pub status: OrderStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderFilter {
+    pub status: Option<OrderStatus>,
+    pub user_id: Option<Uuid>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub to: Option<OffsetDateTime>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_transitions() {
+        assert!(OrderStatus::Pending.can_transition_to(OrderStatus::Paid));
+        assert!(OrderStatus::Paid.can_transition_to(OrderStatus::Shipped));
+        assert!(OrderStatus::Shipped.can_transition_to(OrderStatus::Delivered));
+        assert!(OrderStatus::Pending.can_transition_to(OrderStatus::Cancelled));
+        assert!(OrderStatus::Paid.can_transition_to(OrderStatus::Refunded));
+
+        assert!(!OrderStatus::Pending.can_transition_to(OrderStatus::Shipped));
+        assert!(!OrderStatus::Shipped.can_transition_to(OrderStatus::Cancelled));
+        assert!(!OrderStatus::Cancelled.can_transition_to(OrderStatus::Paid));
+        assert!(!OrderStatus::Refunded.can_transition_to(OrderStatus::Refunded));
+    }
+
+    #[test]
+    fn test_refunds_are_left_to_payments() {
+        assert!(OrderStatus::Refunded.is_set_by_payments());
+        assert!(!OrderStatus::Shipped.is_set_by_payments());
+        assert!(!OrderStatus::Cancelled.is_set_by_payments());
+    }
+}
@@ -0,0 +1,49 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "payment_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentKind {
+    Payment,
+    Refund,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "payment_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+// Payment or refund recorded against an order
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Payment {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub provider: String,
+    pub provider_ref: String,
+    pub kind: PaymentKind,
+    pub status: PaymentStatus,
+    pub amount: Decimal,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+// Returned to the client so it can complete the payment with the provider
+#[derive(Debug, Serialize)]
+pub struct PaymentIntentResponse {
+    pub order_id: Uuid,
+    pub provider: String,
+    pub intent_id: String,
+    pub client_secret: String,
+    pub amount: Decimal,
+    pub currency: String,
+}
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use validator::Validate;
+
+// Every permission a route can require, as `<resource>:<action>`
+pub const PERMISSIONS: &[&str] = &[
+    "products:write",
+    "products:images",
+    "products:delete",
+    "inventory:read",
+    "inventory:write",
+    "categories:write",
+    "categories:delete",
+    "orders:read",
+    "orders:manage",
+    "orders:refund",
+    "currencies:manage",
+    "promotions:manage",
+    "reviews:moderate",
+    "lockouts:manage",
+    "users:read",
+    "users:manage",
+    "users:impersonate",
+    "roles:manage",
+];
+
+// Roles every database has; their names match the `user_role` values
+pub const BUILTIN_ROLES: &[&str] = &["user", "admin", "superadmin"];
+// Always holds every permission and cannot be edited
+pub const SUPERADMIN_ROLE: &str = "superadmin";
+
+pub fn is_known_permission(permission: &str) -> bool {
+    PERMISSIONS.contains(&permission)
+}
+
+/// Role names are lowercase words joined by `-` or `_`, e.g. "content-editor"
+pub fn is_valid_role_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 50
+        && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+/// Sorted, de-duplicated permissions; `Err` names the first unknown one
+pub fn normalize_permissions(permissions: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized = Vec::with_capacity(permissions.len());
+    for permission in permissions {
+        let permission = permission.trim();
+        if !is_known_permission(permission) {
+            return Err(permission.to_string());
+        }
+        normalized.push(permission.to_string());
+    }
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+// A named permission set (for API responses)
+#[derive(Debug, Serialize)]
+pub struct Role {
+    pub name: String,
+    pub description: Option<String>,
+    pub builtin: bool,
+    pub permissions: Vec<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateRole {
+    pub name: String,
+    #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateRole {
+    #[validate(length(max = 255, message = "Description cannot exceed 255 characters"))]
+    pub description: Option<String>,
+    pub permissions: Option<Vec<String>>,
+}
+
+// Extra roles of a user, on top of the one named by their `role`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssignRoles {
+    pub roles: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_permissions() {
+        let permissions = vec![
+            "orders:refund".to_string(),
+            " products:write".to_string(),
+            "orders:refund".to_string(),
+        ];
+        assert_eq!(
+            normalize_permissions(&permissions).unwrap(),
+            vec!["orders:refund".to_string(), "products:write".to_string()]
+        );
+        assert_eq!(normalize_permissions(&["products:*".to_string()]).unwrap_err(), "products:*");
+    }
+
+    #[test]
+    fn test_role_names() {
+        assert!(is_valid_role_name("content-editor"));
+        assert!(!is_valid_role_name("Content Editor"));
+        assert!(!is_valid_role_name(""));
+    }
+}
@@ -2,7 +2,10 @@ use time::{OffsetDateTime};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use rust_decimal::Decimal;
+use sqlx::types::Json;
+use std::collections::BTreeMap;
 use uuid::Uuid;
+use crate::models::currency::PriceConversion;
 
 
 // Product model
@@ -12,19 +15,30 @@ pub struct Product {
     pub name: String,
     pub description: Option<String>,
     pub price: Decimal,
+    pub currency: String,
     pub category_id: Uuid,
     pub image_url: Option<String>,
     pub stock: i32,
+    pub average_rating: Option<Decimal>, // Over approved reviews
+    pub review_count: i32,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
 }
 
+impl Product {
+    pub fn convert_price(&mut self, conversion: &PriceConversion) {
+        self.price = conversion.apply(self.price);
+        self.currency = conversion.currency.code.clone();
+    }
+}
+
 // Product creation request
 #[derive(Debug, Deserialize)]
 pub struct CreateProduct {
     pub name: String,
     pub description: Option<String>,
     pub price: String, // We'll parse this to Decimal
+    pub currency: Option<String>, // Defaults to USD
     pub category_id: Uuid,
     pub stock: i32,
 }
@@ -35,6 +49,7 @@ pub struct UpdateProduct {
     pub name: Option<String>,
     pub description: Option<String>,
     pub price: Option<String>,
+    pub currency: Option<String>,
     pub category_id: Option<Uuid>,
     pub stock: Option<i32>,
 }
@@ -46,12 +61,27 @@ pub struct ProductWithCategory {
     pub name: String,
     pub description: Option<String>,
     pub price: Decimal,
+    pub currency: String,
     pub category_id: Uuid,
     pub category_name: String,
     pub image_url: Option<String>,
     pub stock: i32,
+    pub average_rating: Option<Decimal>, // Over approved reviews
+    pub review_count: i32,
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
+    pub variants: Vec<ProductVariant>,
+}
+
+impl ProductWithCategory {
+    /// Convert the product price and variant price overrides
+    pub fn convert_price(&mut self, conversion: &PriceConversion) {
+        self.price = conversion.apply(self.price);
+        self.currency = conversion.currency.code.clone();
+        for variant in &mut self.variants {
+            variant.price = variant.price.map(|price| conversion.apply(price));
+        }
+    }
 }
 
 
@@ -59,9 +89,56 @@ pub struct ProductWithCategory {
 pub struct ProductFilter {
     pub search: Option<String>,
     pub category_name: Option<String>,
+    pub category_id: Option<Uuid>,
+    // When filtering by category_id, also match products in nested subcategories
+    pub include_subcategories: Option<bool>,
+    // Currency to show prices in; min_price/max_price are read in this currency too
+    pub currency: Option<String>,
     pub min_price: Option<f64>,
     pub max_price: Option<f64>,
     pub in_stock: Option<bool>,
+    pub min_rating: Option<f64>,
+    pub sort: Option<ProductSort>,
     pub page: Option<u32>,
     pub per_page: Option<u32>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductSort {
+    Newest,
+    Rating,  // Highest rated first, unrated last
+    Reviews, // Most reviewed first
+}
+
+// Product variant (size, colour, ...) with its own SKU and stock
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProductVariant {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub sku: String,
+    pub options: Json<BTreeMap<String, String>>,
+    pub price: Option<Decimal>, // Overrides the product price when set
+    pub stock: i32,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// Variant creation request
+#[derive(Debug, Deserialize)]
+pub struct CreateVariant {
+    pub sku: String,
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    pub price: Option<String>, // We'll parse this to Decimal
+    pub stock: i32,
+}
+
+// Variant update request
+#[derive(Debug, Deserialize)]
+pub struct UpdateVariant {
+    pub sku: Option<String>,
+    pub options: Option<BTreeMap<String, String>>,
+    pub price: Option<String>,
+    pub stock: Option<i32>,
+}
@@ -0,0 +1,375 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use crate::models::currency::{round_to_currency, Currency};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "promotion_discount_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DiscountType {
+    Percentage,
+    Fixed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "promotion_scope", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PromotionScope {
+    Cart,
+    Product,
+    Category,
+}
+
+// Promotion model
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Promotion {
+    pub id: Uuid,
+    pub name: String,
+    pub code: Option<String>, // Coupon code; automatic promotion when empty
+    pub discount_type: DiscountType,
+    pub discount_value: Decimal,
+    pub scope: PromotionScope,
+    pub product_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub min_spend: Option<Decimal>,
+    pub currency: String, // Of the fixed discount and min_spend
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub active: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// Promotion creation request
+#[derive(Debug, Deserialize)]
+pub struct CreatePromotion {
+    pub name: String,
+    pub code: Option<String>,
+    pub discount_type: DiscountType,
+    pub discount_value: String, // We'll parse this to Decimal
+    pub scope: PromotionScope,
+    pub product_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub min_spend: Option<String>,
+    pub currency: Option<String>, // Defaults to USD
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub active: Option<bool>,
+}
+
+// Promotion update request
+#[derive(Debug, Deserialize)]
+pub struct UpdatePromotion {
+    pub name: Option<String>,
+    pub discount_value: Option<String>,
+    pub min_spend: Option<String>,
+    pub currency: Option<String>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub starts_at: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub ends_at: Option<OffsetDateTime>,
+    pub usage_limit: Option<i32>,
+    pub per_user_limit: Option<i32>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApplyCoupon {
+    pub code: String,
+}
+
+// Promotion loaded for evaluation, with the usage counts it is checked against
+#[derive(Debug, Clone)]
+pub struct PromotionCandidate {
+    pub promotion: Promotion,
+    // For category scope: the category and all of its subcategories
+    pub category_ids: Vec<Uuid>,
+    pub total_uses: i64,
+    pub user_uses: i64,
+    // Promotion currency to cart currency; `None` when there is no exchange rate
+    pub rate: Option<Decimal>,
+}
+
+// Cart line as seen by the promotion engine
+#[derive(Debug, Clone)]
+pub struct PromotionLine {
+    pub product_id: Uuid,
+    pub category_id: Uuid,
+    pub line_total: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedPromotion {
+    pub promotion_id: Uuid,
+    pub name: String,
+    pub code: Option<String>,
+    pub discount: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedPromotion {
+    pub promotion_id: Uuid,
+    pub name: String,
+    pub code: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PromotionOutcome {
+    pub applied: Vec<AppliedPromotion>,
+    pub rejected: Vec<RejectedPromotion>,
+    pub discount: Decimal,
+}
+
+impl PromotionCandidate {
+    /// A fixed amount of the promotion (discount or min spend) in the cart's currency
+    fn in_cart_currency(&self, amount: Decimal, currency: &Currency) -> Option<Decimal> {
+        self.rate.map(|rate| round_to_currency(amount * rate, currency.decimals))
+    }
+
+    fn rejection(
+        &self,
+        lines: &[PromotionLine],
+        subtotal: Decimal,
+        currency: &Currency,
+        now: OffsetDateTime,
+    ) -> Option<String> {
+        let p = &self.promotion;
+
+        if !p.active {
+            return Some("Promotion is not active".to_string());
+        }
+        if p.starts_at.is_some_and(|starts_at| now < starts_at) {
+            return Some("Promotion has not started yet".to_string());
+        }
+        if p.ends_at.is_some_and(|ends_at| now >= ends_at) {
+            return Some("Promotion has ended".to_string());
+        }
+        if p.usage_limit.is_some_and(|limit| self.total_uses >= limit as i64) {
+            return Some("Promotion usage limit reached".to_string());
+        }
+        if p.per_user_limit.is_some_and(|limit| self.user_uses >= limit as i64) {
+            return Some("You have already used this promotion the maximum number of times".to_string());
+        }
+        let needs_rate = p.discount_type == DiscountType::Fixed || p.min_spend.is_some();
+        if needs_rate && self.rate.is_none() {
+            return Some(format!("Promotion is not available in {}", currency.code));
+        }
+        if let Some(min_spend) = p.min_spend.and_then(|amount| self.in_cart_currency(amount, currency))
+            && subtotal < min_spend
+        {
+            return Some(format!("Minimum spend of {} not met", min_spend));
+        }
+        if self.eligible_total(lines).is_zero() {
+            return Some("No eligible items in cart".to_string());
+        }
+
+        None
+    }
+
+    /// Sum of the lines this promotion applies to
+    fn eligible_total(&self, lines: &[PromotionLine]) -> Decimal {
+        let p = &self.promotion;
+        lines
+            .iter()
+            .filter(|line| match p.scope {
+                PromotionScope::Cart => true,
+                PromotionScope::Product => p.product_id == Some(line.product_id),
+                PromotionScope::Category => self.category_ids.contains(&line.category_id),
+            })
+            .map(|line| line.line_total)
+            .sum()
+    }
+}
+
+/// Evaluate promotions against the cart lines, priced in `currency`. Applicable promotions
+/// stack in the order given, and the total discount never exceeds the subtotal.
+pub fn evaluate_promotions(
+    candidates: &[PromotionCandidate],
+    lines: &[PromotionLine],
+    currency: &Currency,
+    now: OffsetDateTime,
+) -> PromotionOutcome {
+    let subtotal: Decimal = lines.iter().map(|line| line.line_total).sum();
+    let mut outcome = PromotionOutcome::default();
+
+    for candidate in candidates {
+        let p = &candidate.promotion;
+
+        if let Some(reason) = candidate.rejection(lines, subtotal, currency, now) {
+            outcome.rejected.push(RejectedPromotion {
+                promotion_id: p.id,
+                name: p.name.clone(),
+                code: p.code.clone(),
+                reason,
+            });
+            continue;
+        }
+
+        let base = candidate.eligible_total(lines);
+        let discount = match p.discount_type {
+            DiscountType::Percentage => round_to_currency(base * p.discount_value / Decimal::ONE_HUNDRED, currency.decimals),
+            DiscountType::Fixed => candidate
+                .in_cart_currency(p.discount_value, currency)
+                .unwrap_or_default()
+                .min(base),
+        }
+        .min(subtotal - outcome.discount);
+
+        outcome.discount += discount;
+        outcome.applied.push(AppliedPromotion {
+            promotion_id: p.id,
+            name: p.name.clone(),
+            code: p.code.clone(),
+            discount,
+        });
+    }
+
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use time::Duration;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn candidate(discount_type: DiscountType, value: &str, scope: PromotionScope) -> PromotionCandidate {
+        PromotionCandidate {
+            promotion: Promotion {
+                id: Uuid::new_v4(),
+                name: "Promo".to_string(),
+                code: None,
+                discount_type,
+                discount_value: dec(value),
+                scope,
+                product_id: None,
+                category_id: None,
+                min_spend: None,
+                currency: "USD".to_string(),
+                starts_at: None,
+                ends_at: None,
+                usage_limit: None,
+                per_user_limit: None,
+                active: true,
+                created_at: OffsetDateTime::now_utc(),
+            },
+            category_ids: Vec::new(),
+            total_uses: 0,
+            user_uses: 0,
+            rate: Some(Decimal::ONE),
+        }
+    }
+
+    fn currency(code: &str, decimals: i16) -> Currency {
+        Currency {
+            code: code.to_string(),
+            name: code.to_string(),
+            decimals,
+        }
+    }
+
+    #[test]
+    fn test_percentage_and_category_scope() {
+        let laptops = Uuid::new_v4();
+        let books = Uuid::new_v4();
+        let lines = vec![
+            PromotionLine { product_id: Uuid::new_v4(), category_id: laptops, line_total: dec("100.00") },
+            PromotionLine { product_id: Uuid::new_v4(), category_id: books, line_total: dec("20.00") },
+        ];
+
+        let mut category_promo = candidate(DiscountType::Percentage, "12.5", PromotionScope::Category);
+        category_promo.category_ids = vec![laptops];
+        let cart_promo = candidate(DiscountType::Fixed, "5", PromotionScope::Cart);
+
+        let outcome = evaluate_promotions(&[category_promo, cart_promo], &lines, &currency("USD", 2), OffsetDateTime::now_utc());
+        assert_eq!(outcome.applied.len(), 2);
+        assert_eq!(outcome.applied[0].discount, dec("12.50"));
+        assert_eq!(outcome.discount, dec("17.50"));
+    }
+
+    #[test]
+    fn test_rejection_reasons() {
+        let now = OffsetDateTime::now_utc();
+        let lines = vec![PromotionLine { product_id: Uuid::new_v4(), category_id: Uuid::new_v4(), line_total: dec("30") }];
+
+        let mut min_spend = candidate(DiscountType::Fixed, "5", PromotionScope::Cart);
+        min_spend.promotion.min_spend = Some(dec("50"));
+        let mut expired = candidate(DiscountType::Fixed, "5", PromotionScope::Cart);
+        expired.promotion.ends_at = Some(now - Duration::days(1));
+        let mut used_up = candidate(DiscountType::Fixed, "5", PromotionScope::Cart);
+        used_up.promotion.per_user_limit = Some(1);
+        used_up.user_uses = 1;
+        let mut other_product = candidate(DiscountType::Fixed, "5", PromotionScope::Product);
+        other_product.promotion.product_id = Some(Uuid::new_v4());
+
+        let outcome = evaluate_promotions(&[min_spend, expired, used_up, other_product], &lines, &currency("USD", 2), now);
+        assert!(outcome.applied.is_empty());
+        assert_eq!(outcome.discount, Decimal::ZERO);
+        let reasons: Vec<_> = outcome.rejected.iter().map(|r| r.reason.as_str()).collect();
+        assert_eq!(reasons[0], "Minimum spend of 50 not met");
+        assert_eq!(reasons[1], "Promotion has ended");
+        assert_eq!(reasons[3], "No eligible items in cart");
+    }
+
+    #[test]
+    fn test_discount_capped_at_subtotal() {
+        let lines = vec![PromotionLine { product_id: Uuid::new_v4(), category_id: Uuid::new_v4(), line_total: dec("8") }];
+        let outcome = evaluate_promotions(
+            &[
+                candidate(DiscountType::Fixed, "5", PromotionScope::Cart),
+                candidate(DiscountType::Fixed, "5", PromotionScope::Cart),
+            ],
+            &lines,
+            &currency("USD", 2),
+            OffsetDateTime::now_utc(),
+        );
+        assert_eq!(outcome.discount, dec("8"));
+        assert_eq!(outcome.applied[1].discount, dec("3"));
+    }
+
+    #[test]
+    fn test_fixed_amounts_are_converted_into_the_cart_currency() {
+        let yen = currency("JPY", 0);
+        let lines = vec![PromotionLine { product_id: Uuid::new_v4(), category_id: Uuid::new_v4(), line_total: dec("15000") }];
+
+        let mut fixed = candidate(DiscountType::Fixed, "5", PromotionScope::Cart);
+        fixed.rate = Some(dec("151.37"));
+        let mut min_spend = candidate(DiscountType::Percentage, "10", PromotionScope::Cart);
+        min_spend.promotion.min_spend = Some(dec("200"));
+        min_spend.rate = Some(dec("151.37"));
+        let mut no_rate = candidate(DiscountType::Fixed, "5", PromotionScope::Cart);
+        no_rate.rate = None;
+
+        let outcome = evaluate_promotions(&[fixed, min_spend, no_rate], &lines, &yen, OffsetDateTime::now_utc());
+        assert_eq!(outcome.applied.len(), 1);
+        assert_eq!(outcome.applied[0].discount, dec("757"));
+        assert_eq!(outcome.rejected[0].reason, "Minimum spend of 30274 not met");
+        assert_eq!(outcome.rejected[1].reason, "Promotion is not available in JPY");
+    }
+
+    #[test]
+    fn test_percentage_rounds_to_the_cart_currency() {
+        let lines = vec![PromotionLine { product_id: Uuid::new_v4(), category_id: Uuid::new_v4(), line_total: dec("999") }];
+        let mut percent = candidate(DiscountType::Percentage, "12.5", PromotionScope::Cart);
+        // Percentages need no exchange rate
+        percent.rate = None;
+
+        let outcome = evaluate_promotions(&[percent], &lines, &currency("JPY", 0), OffsetDateTime::now_utc());
+        assert_eq!(outcome.discount, dec("125"));
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Moderation state: only approved reviews are shown and counted in the rating
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "review_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewStatus {
+    Pending,
+    Approved,
+    Hidden,
+}
+
+// Review model
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Review {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub rating: i16,
+    pub comment: String,
+    pub status: ReviewStatus,
+    pub verified_purchase: bool, // The author has a paid order for the product
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+// Review creation request
+#[derive(Debug, Deserialize)]
+pub struct CreateReview {
+    pub rating: i16,
+    pub comment: String,
+}
+
+// Moderation request (admin only)
+#[derive(Debug, Deserialize)]
+pub struct ModerateReview {
+    pub status: ReviewStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReviewQuery {
+    pub status: Option<ReviewStatus>, // Ignored on the public listing
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
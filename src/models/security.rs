@@ -0,0 +1,37 @@
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::FromRow;
+use sqlx::types::Json;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Security event kinds
+pub const REFRESH_TOKEN_REUSE: &str = "refresh_token_reuse";
+pub const PASSWORD_RESET: &str = "password_reset";
+pub const PASSWORD_CHANGED: &str = "password_changed";
+pub const LOGIN_LOCKOUT: &str = "login_lockout";
+pub const MFA_ENABLED: &str = "mfa_enabled";
+pub const MFA_DISABLED: &str = "mfa_disabled";
+pub const MFA_RECOVERY_CODE_USED: &str = "mfa_recovery_code_used";
+pub const ROLE_CHANGED: &str = "role_changed";
+pub const ACCOUNT_SUSPENDED: &str = "account_suspended";
+pub const ACCOUNT_REACTIVATED: &str = "account_reactivated";
+pub const FORCED_LOGOUT: &str = "forced_logout";
+pub const ACCOUNT_DELETED: &str = "account_deleted";
+pub const API_KEY_CREATED: &str = "api_key_created";
+pub const API_KEY_REVOKED: &str = "api_key_revoked";
+pub const IMPERSONATION_STARTED: &str = "impersonation_started";
+pub const IMPERSONATION_ENDED: &str = "impersonation_ended";
+
+// Security event model
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub kind: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Json<Value>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
@@ -3,12 +3,20 @@ use sqlx::FromRow;
 use uuid::Uuid;
 use time::{OffsetDateTime};
 use validator::Validate;
+use crate::models::api_key::ApiKey;
+use crate::models::auth::Session;
+use crate::models::cart::CartView;
+use crate::models::order::OrderWithItems;
+use crate::models::review::Review;
+use crate::models::security::SecurityEvent;
+use crate::models::wishlist::WishlistItem;
 // User role enum
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "user_role", rename_all = "lowercase")]
 pub enum UserRole {
     Admin,
     User,
+    SuperAdmin, // Admin who may also grant and revoke roles
 }
 
 impl Default for UserRole {
@@ -17,6 +25,32 @@ impl Default for UserRole {
     }
 }
 
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Admin => "admin",
+            UserRole::User => "user",
+            UserRole::SuperAdmin => "superadmin",
+        }
+    }
+
+    /// Admins and superadmins can use the admin routes
+    pub fn is_admin(&self) -> bool {
+        matches!(self, UserRole::Admin | UserRole::SuperAdmin)
+    }
+}
+
+// Account status; suspended users are locked out until reactivated, deleted ones for good
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "user_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    #[default]
+    Active,
+    Suspended,
+    Deleted, // Self-service deletion; the row stays with its PII anonymised
+}
+
 // User model
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
@@ -26,6 +60,11 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub role: UserRole,
+    #[serde(default)]
+    pub status: UserStatus,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub email_verified_at: Option<OffsetDateTime>,
+    pub pending_email: Option<String>, // New address awaiting confirmation
     #[serde(with = "time::serde::rfc3339")]
     pub created_at: OffsetDateTime,
 }
@@ -36,7 +75,6 @@ pub struct CreateUser {
     pub username: String,
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
-    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
     pub password: String,
 }
 
@@ -45,4 +83,79 @@ pub struct UpdateUserRequest {
     pub username: Option<String>,
     #[validate(email(message = "Invalid email format"))]
     pub email: Option<String>,
+    // Required when changing the email
+    pub current_password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub current_password: String,
+}
+
+// Outcome of deleting an account
+pub enum AccountDeletion {
+    Deleted,
+    LastSuperAdmin, // Refused: nobody would be left to manage roles
+}
+
+// Everything held about a user, for privacy requests
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_at: OffsetDateTime,
+    pub user: User,
+    pub extra_roles: Vec<String>,
+    pub mfa_enabled: bool,
+    pub sessions: Vec<Session>,
+    pub api_keys: Vec<ApiKey>,
+    pub orders: Vec<OrderWithItems>,
+    pub reviews: Vec<Review>,
+    pub wishlist: Vec<WishlistItem>,
+    pub cart: Option<CartView>,
+    pub security_events: Vec<SecurityEvent>,
+}
+
+// Admin user search
+#[derive(Debug, Deserialize)]
+pub struct UserQuery {
+    pub search: Option<String>, // Matches username or email
+    pub role: Option<UserRole>,
+    pub status: Option<UserStatus>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRole {
+    pub role: UserRole,
+}
+
+// A user as seen by admins, with their active sessions
+#[derive(Debug, Serialize)]
+pub struct AdminUserView {
+    #[serde(flatten)]
+    pub user: User,
+    pub mfa_enabled: bool,
+    pub sessions: Vec<Session>,
+}
+
+// Short-lived access token for acting as a user (admin impersonation)
+#[derive(Debug, Serialize)]
+pub struct ImpersonationResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64, // Seconds; the token cannot be refreshed
+    pub user: User,
+}
+
+// Outcome of a role change
+pub enum RoleChange {
+    Updated(User),
+    LastSuperAdmin, // Refused: nobody would be left to manage roles
 }
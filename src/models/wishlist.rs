@@ -0,0 +1,28 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Wishlist entry joined with current product data (for API responses)
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WishlistItem {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub product_name: String,
+    pub price: Decimal,
+    pub currency: String,
+    pub image_url: Option<String>,
+    pub stock: i32,
+    pub notify_when_in_stock: bool,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+// Add to wishlist request
+#[derive(Debug, Deserialize)]
+pub struct AddWishlistItem {
+    pub product_id: Uuid,
+    // Also subscribe to a back-in-stock notification
+    pub notify_when_in_stock: Option<bool>,
+}
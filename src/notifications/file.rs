@@ -0,0 +1,73 @@
+use super::Notifier;
+use crate::models::notification::OutgoingNotification;
+use anyhow::Result;
+use axum::async_trait;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// Development sink that appends one JSON line per notification to a file
+pub struct FileNotifier {
+    path: PathBuf,
+}
+
+impl FileNotifier {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Notifier for FileNotifier {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<()> {
+        let mut line = serde_json::to_string(notification)?;
+        line.push('\n');
+
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_file_notifier_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("notifications-{}.log", Uuid::new_v4()));
+        let notifier = FileNotifier::new(&path);
+        let notification = OutgoingNotification {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            email: "user@example.com".to_string(),
+            username: "user".to_string(),
+            kind: "back_in_stock".to_string(),
+            subject: "Widget is back in stock".to_string(),
+            body: "Good news".to_string(),
+            attempts: 0,
+            created_at: OffsetDateTime::now_utc(),
+        };
+
+        notifier.send(&notification).await.unwrap();
+        notifier.send(&notification).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.ok();
+
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(parsed["email"], "user@example.com");
+    }
+}
@@ -0,0 +1,25 @@
+use super::Notifier;
+use crate::models::notification::OutgoingNotification;
+use anyhow::Result;
+use axum::async_trait;
+
+/// Development sink that writes notifications to the application log
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<()> {
+        tracing::info!(
+            "[notification] to={} kind={} subject={:?} body={:?}",
+            notification.email,
+            notification.kind,
+            notification.subject,
+            notification.body
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+pub mod file;
+pub mod log;
+
+use crate::db::db_con::DatabasePool;
+use crate::db::notificationq::*;
+use crate::models::notification::OutgoingNotification;
+use anyhow::Result;
+use axum::async_trait;
+use dotenvy::dotenv;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Give up on a notification after this many failed sends
+pub const MAX_ATTEMPTS: i32 = 5;
+
+const BATCH_SIZE: i64 = 50;
+
+// A claimed batch is left alone by other dispatchers for this long
+const CLAIM_MINUTES: i64 = 5;
+
+/// Delivery channel for queued notifications (log, file, email, ...)
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Short identifier used in logs (e.g. "log", "file")
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<()>;
+}
+
+/// Pick the sink from NOTIFIER ("log" or "file"); the file sink writes to NOTIFIER_FILE
+pub fn notifier_from_env() -> Arc<dyn Notifier> {
+    dotenv().ok();
+    match std::env::var("NOTIFIER").as_deref() {
+        Ok("file") => {
+            let path = std::env::var("NOTIFIER_FILE").unwrap_or_else(|_| "notifications.log".to_string());
+            Arc::new(file::FileNotifier::new(path))
+        }
+        _ => Arc::new(log::LogNotifier),
+    }
+}
+
+/// Claim and send one batch of pending notifications. Returns how many were sent.
+pub async fn dispatch_pending(pool: &DatabasePool, notifier: &dyn Notifier) -> Result<usize> {
+    let pending = claim_pending_notifications(pool, MAX_ATTEMPTS, BATCH_SIZE, CLAIM_MINUTES).await?;
+    let mut sent = 0;
+
+    for notification in &pending {
+        match notifier.send(notification).await {
+            Ok(()) => {
+                mark_notification_sent(pool, notification.id).await?;
+                sent += 1;
+            }
+            Err(e) => {
+                tracing::warn!("{} notifier failed for {}: {:?}", notifier.name(), notification.id, e);
+                mark_notification_failed(pool, notification.id, &e.to_string(), MAX_ATTEMPTS).await?;
+            }
+        }
+    }
+
+    Ok(sent)
+}
+
+/// Drain the notification outbox in the background; safe to run on several instances
+pub fn spawn_dispatcher(pool: DatabasePool, notifier: Arc<dyn Notifier>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = dispatch_pending(&pool, notifier.as_ref()).await {
+                tracing::error!("Notification dispatch failed: {:?}", e);
+            }
+        }
+    });
+}
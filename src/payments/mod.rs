@@ -0,0 +1,79 @@
+pub mod mock;
+
+use anyhow::{bail, Result};
+use axum::async_trait;
+use dotenvy::dotenv;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::models::payment::PaymentStatus;
+
+// Header carrying the webhook signature
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-payment-signature";
+
+// Payment intent created with the provider
+#[derive(Debug, Clone)]
+pub struct PaymentIntent {
+    pub id: String,
+    pub client_secret: String,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+// Result of a capture or refund call
+#[derive(Debug, Clone)]
+pub struct ProviderResult {
+    pub reference: String,
+    pub status: PaymentStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum WebhookEventKind {
+    #[serde(rename = "payment.succeeded")]
+    PaymentSucceeded,
+    #[serde(rename = "payment.failed")]
+    PaymentFailed,
+    #[serde(rename = "refund.succeeded")]
+    RefundSucceeded,
+}
+
+// Verified webhook event
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: WebhookEventKind,
+    pub intent_id: String,
+    pub amount: Decimal,
+    pub currency: String,
+}
+
+/// Payment vendor integration used by the service layer
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    /// Short identifier stored alongside payments (e.g. "mock", "stripe")
+    fn name(&self) -> &'static str;
+
+    async fn create_intent(&self, order_id: Uuid, amount: Decimal, currency: &str) -> Result<PaymentIntent>;
+
+    async fn capture(&self, intent_id: &str) -> Result<ProviderResult>;
+
+    async fn refund(&self, intent_id: &str, amount: Decimal) -> Result<ProviderResult>;
+
+    /// Check the signature and parse the event; fails if the signature does not match
+    fn verify_webhook(&self, payload: &[u8], signature: &str) -> Result<WebhookEvent>;
+}
+
+/// Pick the provider from PAYMENT_PROVIDER. There is no default: the mock marks orders paid
+/// from its own signed webhooks, so it only runs when asked for by name.
+pub fn payment_provider_from_env() -> Result<Arc<dyn PaymentProvider>> {
+    dotenv().ok();
+    let provider: Arc<dyn PaymentProvider> = match std::env::var("PAYMENT_PROVIDER").as_deref() {
+        Ok("mock") => Arc::new(mock::MockPaymentProvider::from_env()?),
+        Ok(other) => bail!("Unknown PAYMENT_PROVIDER '{}'", other),
+        Err(_) => bail!("PAYMENT_PROVIDER must be set (\"mock\" is the only provider so far)"),
+    };
+
+    Ok(provider)
+}
@@ -0,0 +1,201 @@
+use crate::db::{apikeyq::*, securityq::record_security_event, userq::find_by_id};
+use crate::middleware::auth::AuthUser;
+use crate::models::{
+    api_key::*,
+    auth::ClientInfo,
+    permission::normalize_permissions,
+    security::{API_KEY_CREATED, API_KEY_REVOKED},
+    user::User,
+};
+use crate::services::{mfa::mfa_required_for, roles::resolve_permissions, users::require_can_manage};
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::utils::token::TokenHasher;
+use crate::db::db_con::DatabasePool;
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use validator::Validate;
+
+fn api_key_not_found() -> AppError {
+    AppError::NotFound("API key not found".to_string())
+}
+
+/// Create a key for `owner`. Its permissions must be held by both the owner and the creator,
+/// so a key never grants more than either of them has.
+async fn issue_api_key(
+    pool: &DatabasePool,
+    hasher: &TokenHasher,
+    actor: &AuthUser,
+    owner: &User,
+    client: &ClientInfo,
+    request: CreateApiKey,
+) -> AppResult<CreatedApiKey> {
+    if actor.is_impersonated() {
+        return Err(AppError::impersonation_forbidden());
+    }
+    if actor.api_key_id.is_some() {
+        return Err(AppError::Authorization("API keys cannot create other API keys".to_string()));
+    }
+    // Keys skip the second factor, so they are only handed out by sessions that passed it
+    if mfa_required_for(&actor.role) && !actor.mfa {
+        return Err(AppError::Authorization(
+            "Two-factor authentication is required: enable it under /api/profile/mfa and log in again".to_string(),
+        ));
+    }
+    if let Err(e) = request.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("Name cannot be empty".to_string()));
+    }
+    if request.expires_at.is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc()) {
+        return Err(AppError::Validation("Expiry must be in the future".to_string()));
+    }
+
+    let permissions = normalize_permissions(&request.permissions)
+        .map_err(|permission| AppError::Validation(format!("Unknown permission '{}'", permission)))?;
+    let held = resolve_permissions(pool, owner).await?;
+    if let Some(permission) = permissions.iter().find(|p| !held.contains(p) || !actor.has_permission(p)) {
+        return Err(AppError::Validation(format!("Permission '{}' cannot be granted to this key", permission)));
+    }
+
+    let (token, stored) = hasher.generate_split_token();
+    let api_key = create_api_key_db(
+        pool,
+        owner.id,
+        name,
+        &stored,
+        &permissions,
+        actor.user_id,
+        actor.mfa,
+        request.expires_at,
+    )
+    .await?;
+    record_security_event(
+        pool,
+        Some(owner.id),
+        API_KEY_CREATED,
+        client,
+        serde_json::json!({ "api_key_id": api_key.id, "prefix": api_key.prefix, "by": actor.user_id }),
+    )
+    .await?;
+
+    Ok(CreatedApiKey { api_key, key: format_api_key(&token) })
+}
+
+async fn revoke_key(
+    pool: &DatabasePool,
+    actor: &AuthUser,
+    user_id: Uuid,
+    api_key_id: Uuid,
+    client: &ClientInfo,
+) -> AppResult<Json<serde_json::Value>> {
+    if !revoke_api_key(pool, user_id, api_key_id).await? {
+        return Err(api_key_not_found());
+    }
+    record_security_event(
+        pool,
+        Some(user_id),
+        API_KEY_REVOKED,
+        client,
+        serde_json::json!({ "api_key_id": api_key_id, "by": actor.user_id }),
+    )
+    .await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "API key revoked"
+    })))
+}
+
+// List the current user's API keys
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<Vec<ApiKey>>> {
+    let pool = state.db_pool;
+    let api_keys = find_user_api_keys(&pool, auth_user.user_id).await?;
+    Ok(Json(api_keys))
+}
+
+// Create an API key for the current user; the key is only shown in this response
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    Json(request): Json<CreateApiKey>,
+) -> AppResult<(StatusCode, Json<CreatedApiKey>)> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, auth_user.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let created = issue_api_key(&pool, &state.token_hasher, &auth_user, &user, &client, request).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+// Revoke one of the current user's API keys
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    revoke_key(&pool, &auth_user, auth_user.user_id, id, &client).await
+}
+
+// List a user's API keys (admin only)
+pub async fn list_user_api_keys(
+    State(state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<Vec<ApiKey>>> {
+    let pool = state.db_pool;
+    find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let api_keys = find_user_api_keys(&pool, id).await?;
+    Ok(Json(api_keys))
+}
+
+// Create an API key for a user, e.g. a service account for a sync job (admin only)
+pub async fn create_user_api_key(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+    Json(request): Json<CreateApiKey>,
+) -> AppResult<(StatusCode, Json<CreatedApiKey>)> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    require_can_manage(&auth_user, &user)?;
+
+    let created = issue_api_key(&pool, &state.token_hasher, &auth_user, &user, &client, request).await?;
+    Ok((StatusCode::CREATED, Json(created)))
+}
+
+// Revoke a user's API key (admin only)
+pub async fn delete_user_api_key(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    Path((id, api_key_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    require_can_manage(&auth_user, &user)?;
+
+    revoke_key(&pool, &auth_user, id, api_key_id, &client).await
+}
@@ -1,17 +1,39 @@
-use crate::db::{authq::*, userq::*};
-use crate::models::{auth::*, user::*};
+use crate::db::{
+    authq::*, cartq::merge_guest_cart, db_con::DatabasePool, lockoutq::*, mfaq::*, securityq::record_security_event,
+    userq::*,
+};
+use crate::mail::{send_in_background, templates};
+use crate::models::{
+    auth::*,
+    lockout::{ThrottlePolicy, ThrottleScope},
+    mfa::{MfaChallengeResponse, UserTotp, VerifyMfaRequest},
+    security::{LOGIN_LOCKOUT, MFA_RECOVERY_CODE_USED, PASSWORD_RESET, REFRESH_TOKEN_REUSE},
+    user::*,
+};
 use crate::utils::error::{AppError, AppResult};
-use crate::utils::auth::{hash_password, verify_password};
-use crate::utils::jwt::{create_access_token, generate_refresh_token, get_access_token_duration, get_refresh_token_duration};
+use crate::utils::auth::{hash_password, validate_password, verify_password};
+use crate::utils::revocation::RevocationList;
+use crate::utils::jwt::{
+    create_access_token, generate_refresh_token, get_access_token_duration, get_max_sessions_per_user,
+    get_mfa_challenge_duration, get_password_reset_duration, get_refresh_token_duration, JwtKeys,
+};
+use crate::utils::token::TokenHasher;
+use crate::utils::totp::normalize_recovery_code;
+use crate::services::mfa::check_totp_code;
+use crate::services::roles::resolve_permissions;
+use crate::services::verification::send_verification_email;
 use axum::http::status;
+use jsonwebtoken::jwk::JwkSet;
 use axum::{extract::State, Json};
 use crate::AppState;
+use time::OffsetDateTime;
+use uuid::Uuid;
 use validator::Validate;
-//use uuid::Uuid;
 
 // Register new user
 pub async fn register(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(user_data): Json<CreateUser>,
 ) -> AppResult<Json<AuthResponse>> {
     // Validate input
@@ -23,9 +45,7 @@ pub async fn register(
     if user_data.email.trim().is_empty() {
         return Err(AppError::Validation("Email cannot be empty".to_string()));
     }
-    if user_data.password.len() < 6 {
-        return Err(AppError::Validation("Password must be at least 6 characters".to_string()));
-    }
+    validate_password(&user_data.password)?;
    if let Err(e) = user_data.validate() {
        return Err(AppError::Validation(e.to_string()));
    }
@@ -42,13 +62,21 @@ pub async fn register(
 
     // Create user
     let user = create_user(&pool, user_data, password_hash).await?;
+    send_verification_email(&pool, &state.token_hasher, state.mailer, user.id, &user.email).await?;
 
-    // Generate tokens
-    let access_token = create_access_token(user.id, &user.username, user.role.clone(), &keys)?;
-    let refresh_token = generate_refresh_token();
-
-    // Store refresh token in database
-    create_refresh_token(&pool, user.id, &refresh_token, get_refresh_token_duration()).await?;
+    // Generate tokens; the refresh token row is the new session
+    let (refresh_token, stored_token) = generate_refresh_token(&state.token_hasher);
+    let session = create_refresh_token(&pool, user.id, &stored_token, get_refresh_token_duration(), None, false, &client).await?;
+    let permissions = resolve_permissions(&pool, &user).await?;
+    let access_token = create_access_token(
+        user.id,
+        &user.username,
+        user.role.clone(),
+        Some(session.family_id),
+        false,
+        permissions,
+        &keys,
+    )?;
 
     let response = AuthResponse {
         access_token,
@@ -61,71 +89,177 @@ pub async fn register(
     Ok(Json(response))
 }
 
-// Login user
+// Login user. With two-factor authentication enabled this only returns a challenge for /mfa/verify.
 pub async fn login(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(login_data): Json<LoginRequest>,
-) -> AppResult<Json<AuthResponse>> {
+) -> AppResult<Json<LoginResponse>> {
     let pool = state.db_pool;
     let keys = state.jwt_keys;
 
     if let Err(e) = login_data.validate() {
         return Err(AppError::Validation(e.to_string()));
     }
-    // Find user by email
-    let user = find_by_email(&pool, &login_data.email)
-        .await?
-        .ok_or_else(|| AppError::invalid_credentials())?;
+    // Refuse early while the email or the client's IP is backing off
+    let email_key = login_data.email.trim().to_lowercase();
+    check_login_throttles(&pool, &email_key, client.ip_address.as_deref()).await?;
 
-    // Verify password
-    if !verify_password(&login_data.password, &user.password_hash)? {
-        return Err(AppError::invalid_credentials());
+    // Find user by email and verify password
+    let user = find_by_email(&pool, &login_data.email).await?;
+    let user = match user {
+        Some(user) if verify_password(&login_data.password, &user.password_hash)? => user,
+        user => {
+            record_failed_login(&pool, &email_key, user.map(|u| u.id), &client).await?;
+            return Err(AppError::invalid_credentials());
+        }
+    };
+    // Checked after the password so the response does not reveal the status to guessers
+    if user.status != UserStatus::Active {
+        return Err(AppError::account_inactive(user.status));
     }
+    let device_label = login_data.device_label.as_deref().map(str::trim).filter(|label| !label.is_empty());
 
-    // Generate tokens
-    let access_token = create_access_token(user.id, &user.username, user.role.clone(), &keys)?;
-    let refresh_token = generate_refresh_token();
+    // The throttle stays in place until the second factor is passed as well
+    if find_user_totp(&pool, user.id).await?.is_some_and(|totp| totp.is_enabled()) {
+        let (mfa_token, stored_token) = state.token_hasher.generate_split_token();
+        let minutes = get_mfa_challenge_duration();
+        create_mfa_challenge(&pool, user.id, &stored_token, device_label, login_data.cart_token.as_deref(), minutes).await?;
 
-    // Store refresh token in database (remove old ones first)
-    delete_user_refresh_tokens(&pool, user.id).await?;
-    create_refresh_token(&pool, user.id, &refresh_token, get_refresh_token_duration()).await?;
+        return Ok(Json(LoginResponse::MfaRequired(MfaChallengeResponse {
+            mfa_required: true,
+            mfa_token,
+            expires_in: minutes * 60,
+        })));
+    }
+    clear_login_throttle(&pool, ThrottleScope::Email, &email_key).await?;
 
-    let response = AuthResponse {
-        access_token,
-        refresh_token,
-        token_type: "Bearer".to_string(),
-        expires_in: get_access_token_duration(),
-        user: user.into(),
+    let response = start_session(&pool, &keys, &state.token_hasher, user, device_label, login_data.cart_token.as_deref(), false, &client).await?;
+    Ok(Json(LoginResponse::Authenticated(response)))
+}
+
+// Second login step: exchange the challenge from login and a TOTP or recovery code for a session
+pub async fn verify_mfa(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(request): Json<VerifyMfaRequest>,
+) -> AppResult<Json<AuthResponse>> {
+    let pool = state.db_pool;
+    let keys = state.jwt_keys;
+
+    let invalid_challenge = || AppError::Authentication("Invalid or expired MFA token".to_string());
+    let challenge = find_mfa_challenge(&pool, &state.token_hasher, &request.mfa_token)
+        .await?
+        .ok_or_else(invalid_challenge)?;
+    let user = find_by_id(&pool, challenge.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    if user.status != UserStatus::Active {
+        return Err(AppError::account_inactive(user.status));
+    }
+
+    // Wrong codes count as failed logins, so guessing runs into the same back-off and lockout
+    let email_key = user.email.trim().to_lowercase();
+    check_login_throttles(&pool, &email_key, client.ip_address.as_deref()).await?;
+
+    let verified = match (request.code.as_deref(), request.recovery_code.as_deref()) {
+        (Some(code), _) => match find_user_totp(&pool, user.id).await?.filter(UserTotp::is_enabled) {
+            Some(totp) => check_totp_code(&pool, &totp, code).await?,
+            None => false,
+        },
+        (None, Some(recovery_code)) => {
+            let code_hash = state.token_hasher.hash_secret(&normalize_recovery_code(recovery_code));
+            let used = use_recovery_code(&pool, user.id, &code_hash).await?;
+            if used {
+                let remaining = count_recovery_codes(&pool, user.id).await?;
+                let details = serde_json::json!({ "remaining": remaining });
+                record_security_event(&pool, Some(user.id), MFA_RECOVERY_CODE_USED, &client, details).await?;
+            }
+            used
+        }
+        (None, None) => return Err(AppError::Validation("A code or recovery code is required".to_string())),
     };
 
+    if !verified {
+        record_mfa_attempt(&pool, challenge.id).await?;
+        record_failed_login(&pool, &email_key, Some(user.id), &client).await?;
+        return Err(AppError::Authentication("Invalid verification code".to_string()));
+    }
+
+    // Single use: a concurrent verify of the same challenge loses
+    if !delete_mfa_challenge(&pool, challenge.id).await? {
+        return Err(invalid_challenge());
+    }
+    clear_login_throttle(&pool, ThrottleScope::Email, &email_key).await?;
+
+    let response = start_session(
+        &pool,
+        &keys,
+        &state.token_hasher,
+        user,
+        challenge.device_label.as_deref(),
+        challenge.cart_token.as_deref(),
+        true,
+        &client,
+    )
+    .await?;
     Ok(Json(response))
 }
 
 // Refresh access token
 pub async fn refresh_token(
     State(state): State<AppState>,
+    client: ClientInfo,
     Json(refresh_data): Json<RefreshTokenRequest>,
 ) -> AppResult<Json<AuthResponse>> {
     let pool = state.db_pool;
     let keys = state.jwt_keys;
 
     // Find and validate refresh token
-    let refresh_token = find_refresh_token(&pool, &refresh_data.refresh_token)
+    let refresh_token = find_refresh_token(&pool, &state.token_hasher, &refresh_data.refresh_token)
         .await?
+        .filter(|token| token.revoked_at.is_none())
         .ok_or_else(|| AppError::Authentication("Invalid refresh token".to_string()))?;
 
+    // A token that was already exchanged is being replayed: assume it leaked and end the session
+    if refresh_token.rotated_at.is_some() {
+        return Err(reject_reused_token(&pool, &state.revocations, &refresh_token, &client).await?);
+    }
+
     // Get user
     let user = find_by_id(&pool, refresh_token.user_id)
         .await?
         .ok_or_else(|| AppError::user_not_found())?;
+    if user.status != UserStatus::Active {
+        return Err(AppError::account_inactive(user.status));
+    }
 
-    // Generate new tokens
-    let access_token = create_access_token(user.id, &user.username, user.role.clone(), &keys)?;
-    let new_refresh_token = generate_refresh_token();
+    // Rotate within the family so the session keeps its id and device details
+    let (new_refresh_token, stored_token) = generate_refresh_token(&state.token_hasher);
+    let rotated = rotate_refresh_token(
+        &pool,
+        &refresh_token,
+        &stored_token,
+        get_refresh_token_duration(),
+        &client,
+    )
+    .await?;
 
-    // Replace old refresh token with new one
-    delete_refresh_token(&pool, &refresh_data.refresh_token).await?;
-    create_refresh_token(&pool, user.id, &new_refresh_token, get_refresh_token_duration()).await?;
+    // Lost a race with another refresh of the same token, which is reuse as well
+    let Some(session) = rotated else {
+        return Err(reject_reused_token(&pool, &state.revocations, &refresh_token, &client).await?);
+    };
+
+    let access_token = create_access_token(
+        user.id,
+        &user.username,
+        user.role.clone(),
+        Some(session.family_id),
+        session.mfa_verified,
+        resolve_permissions(&pool, &user).await?,
+        &keys,
+    )?;
 
     let response = AuthResponse {
         access_token,
@@ -143,9 +277,12 @@ pub async fn logout(
     State(state): State<AppState>,
     Json(refresh_data): Json<RefreshTokenRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
-    // Delete the refresh token
+    // End the session the refresh token belongs to
     let pool = state.db_pool;
-    delete_refresh_token(&pool, &refresh_data.refresh_token).await?;
+    if let Some(token) = find_refresh_token(&pool, &state.token_hasher, &refresh_data.refresh_token).await? {
+        delete_user_session(&pool, token.user_id, token.family_id).await?;
+        state.revocations.revoke(&pool, token.family_id, token.user_id).await?;
+    }
     let body = serde_json::json!({
         "status": status::StatusCode::OK.as_u16(),
         "message": "Logged out successfully"
@@ -153,3 +290,193 @@ pub async fn logout(
 
     Ok(Json(body))
 }
+
+// Email a password reset link. Always succeeds so the response does not reveal which emails have accounts.
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    if let Err(e) = request.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    if let Some(user) = find_by_email(&pool, &request.email).await? {
+        let (token, stored_token) = state.token_hasher.generate_split_token();
+        let minutes = get_password_reset_duration();
+        create_password_reset_token(&pool, user.id, &stored_token, minutes).await?;
+        send_in_background(state.mailer, templates::password_reset(&user.email, &token, minutes));
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": status::StatusCode::OK.as_u16(),
+        "message": "If an account exists for that email, a password reset link has been sent"
+    })))
+}
+
+// Set a new password with an emailed reset token, ending every session of the user
+pub async fn reset_password(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Json(request): Json<ResetPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    validate_password(&request.new_password)?;
+
+    let invalid_token = || AppError::BadRequest("Invalid or expired reset token".to_string());
+    let reset_token = find_password_reset_token(&pool, &state.token_hasher, &request.token)
+        .await?
+        .ok_or_else(invalid_token)?;
+
+    let password_hash = hash_password(&request.new_password)?;
+    if !reset_password_with_token(&pool, &reset_token, &password_hash).await? {
+        return Err(invalid_token());
+    }
+
+    // Refresh tokens are gone; cut off access tokens already handed out as well
+    state.revocations.revoke_all(&pool, reset_token.user_id).await?;
+    record_security_event(&pool, Some(reset_token.user_id), PASSWORD_RESET, &client, serde_json::json!({})).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": status::StatusCode::OK.as_u16(),
+        "message": "Password has been reset, please log in again"
+    })))
+}
+
+// Public keys for verifying our access tokens
+pub async fn jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.jwt_keys.jwks().clone())
+}
+
+/// Start a new session alongside the user's other devices, evicting the oldest over the cap
+#[allow(clippy::too_many_arguments)]
+async fn start_session(
+    pool: &DatabasePool,
+    keys: &JwtKeys,
+    hasher: &TokenHasher,
+    user: User,
+    device_label: Option<&str>,
+    cart_token: Option<&str>,
+    mfa_verified: bool,
+    client: &ClientInfo,
+) -> AppResult<AuthResponse> {
+    let (refresh_token, stored_token) = generate_refresh_token(hasher);
+    let session = create_refresh_token(
+        pool,
+        user.id,
+        &stored_token,
+        get_refresh_token_duration(),
+        device_label,
+        mfa_verified,
+        client,
+    )
+    .await?;
+    evict_excess_sessions(pool, user.id, get_max_sessions_per_user()).await?;
+
+    let access_token = create_access_token(
+        user.id,
+        &user.username,
+        user.role.clone(),
+        Some(session.family_id),
+        mfa_verified,
+        resolve_permissions(pool, &user).await?,
+        keys,
+    )?;
+
+    // Carry over anything collected before logging in
+    if let Some(cart_token) = cart_token {
+        merge_guest_cart(pool, cart_token, user.id).await?;
+    }
+
+    Ok(AuthResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: get_access_token_duration(),
+        user,
+    })
+}
+
+/// Fail with `LoginLocked` while the email or IP is locked or backing off
+async fn check_login_throttles(pool: &DatabasePool, email_key: &str, ip_address: Option<&str>) -> AppResult<()> {
+    let now = OffsetDateTime::now_utc();
+    let mut retry_after = None;
+
+    let keys = [(ThrottleScope::Email, Some(email_key)), (ThrottleScope::Ip, ip_address)];
+    for (scope, key) in keys {
+        if let Some(key) = key
+            && let Some(throttle) = find_login_throttle(pool, scope, key).await?
+        {
+            retry_after = retry_after.max(throttle.retry_after(now));
+        }
+    }
+
+    match retry_after {
+        Some(retry_after_seconds) => Err(AppError::LoginLocked { retry_after_seconds }),
+        None => Ok(()),
+    }
+}
+
+/// Count a failed login against the email and the client IP, recording lockouts
+async fn record_failed_login(
+    pool: &DatabasePool,
+    email_key: &str,
+    user_id: Option<Uuid>,
+    client: &ClientInfo,
+) -> AppResult<()> {
+    let policy = ThrottlePolicy::EMAIL;
+    let throttle = record_login_failure(pool, ThrottleScope::Email, email_key, &policy).await?;
+    if policy.is_lockout(throttle.failures) {
+        tracing::warn!("Login locked for {} after {} failures", email_key, throttle.failures);
+        record_security_event(
+            pool,
+            user_id,
+            LOGIN_LOCKOUT,
+            client,
+            serde_json::json!({
+                "email": email_key,
+                "failures": throttle.failures,
+                "locked_until": throttle.locked_until.map(|at| at.unix_timestamp()),
+            }),
+        )
+        .await?;
+    }
+
+    if let Some(ref ip_address) = client.ip_address {
+        record_login_failure(pool, ThrottleScope::Ip, ip_address, &ThrottlePolicy::IP).await?;
+    }
+
+    Ok(())
+}
+
+/// Revoke the family of a replayed refresh token and record the incident
+async fn reject_reused_token(
+    pool: &DatabasePool,
+    revocations: &RevocationList,
+    token: &RefreshToken,
+    client: &ClientInfo,
+) -> AppResult<AppError> {
+    let revoked = revoke_token_family(pool, token.family_id).await?;
+    revocations.revoke(pool, token.family_id, token.user_id).await?;
+    tracing::warn!(
+        "Refresh token reuse for user {} (family {}), revoked {} token(s)",
+        token.user_id,
+        token.family_id,
+        revoked
+    );
+
+    record_security_event(
+        pool,
+        Some(token.user_id),
+        REFRESH_TOKEN_REUSE,
+        client,
+        serde_json::json!({
+            "family_id": token.family_id,
+            "token_id": token.id,
+            "rotated_at": token.rotated_at.map(|at| at.unix_timestamp()),
+        }),
+    )
+    .await?;
+
+    Ok(AppError::Authentication("Refresh token reuse detected, please log in again".to_string()))
+}
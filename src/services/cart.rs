@@ -0,0 +1,275 @@
+use crate::db::cartq::*;
+use crate::db::productq::find_product_by_id;
+use crate::db::promotionq::{find_promotion_by_code, find_promotion_candidates};
+use crate::db::variantq::find_variant_by_id;
+use crate::db::db_con::DatabasePool;
+use crate::middleware::auth::AuthUser;
+use crate::models::cart::*;
+use crate::models::promotion::{evaluate_promotions, ApplyCoupon};
+use crate::services::currencies::require_currency;
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{extract::State, http::HeaderMap, Json};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Header carrying the anonymous cart token
+pub const CART_TOKEN_HEADER: &str = "x-cart-token";
+
+/// Price the cart and apply automatic promotions plus the cart's coupon
+pub async fn cart_view(pool: &DatabasePool, cart: &Cart) -> AppResult<CartView> {
+    let lines = find_cart_lines(pool, cart.id).await?;
+    let mut view = CartView::from_lines(cart, lines);
+
+    let currency = require_currency(pool, &cart.currency).await?;
+    let candidates =
+        find_promotion_candidates(pool, cart.coupon_code.as_deref(), cart.user_id, &currency.code).await?;
+    let outcome = evaluate_promotions(&candidates, &view.promotion_lines(), &currency, OffsetDateTime::now_utc());
+    view.apply_promotions(outcome);
+
+    Ok(view)
+}
+
+async fn add_to_cart(pool: &DatabasePool, cart: &Cart, item: AddCartItem) -> AppResult<CartView> {
+    if item.quantity < 1 {
+        return Err(AppError::Validation("Quantity must be at least 1".to_string()));
+    }
+
+    let product = find_product_by_id(pool, item.product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    // Variants carry their own stock
+    let available = match item.variant_id {
+        Some(variant_id) => {
+            find_variant_by_id(pool, product.id, variant_id)
+                .await?
+                .ok_or_else(AppError::variant_not_found)?
+                .stock
+        }
+        None => product.stock,
+    };
+
+    let in_cart = find_cart_quantity(pool, cart.id, item.product_id, item.variant_id).await?;
+    if in_cart + item.quantity > available {
+        return Err(AppError::insufficient_stock());
+    }
+
+    add_cart_item(pool, cart.id, item.product_id, item.variant_id, item.quantity).await?;
+    cart_view(pool, cart).await
+}
+
+async fn change_cart_currency(pool: &DatabasePool, mut cart: Cart, data: SetCartCurrency) -> AppResult<CartView> {
+    let currency = require_currency(pool, &data.currency).await?;
+
+    set_cart_currency(pool, cart.id, &currency.code).await?;
+    cart.currency = currency.code;
+    cart_view(pool, &cart).await
+}
+
+async fn update_in_cart(
+    pool: &DatabasePool,
+    cart: &Cart,
+    item_id: Uuid,
+    update: UpdateCartItem,
+) -> AppResult<CartView> {
+    if update.quantity < 1 {
+        return Err(AppError::Validation("Quantity must be at least 1".to_string()));
+    }
+
+    let line = find_cart_line(pool, cart.id, item_id)
+        .await?
+        .ok_or_else(AppError::cart_item_not_found)?;
+
+    if update.quantity > line.available_stock {
+        return Err(AppError::insufficient_stock());
+    }
+
+    update_cart_item_quantity(pool, cart.id, item_id, update.quantity).await?;
+    cart_view(pool, cart).await
+}
+
+async fn remove_from_cart(pool: &DatabasePool, cart: &Cart, item_id: Uuid) -> AppResult<CartView> {
+    find_cart_line(pool, cart.id, item_id)
+        .await?
+        .ok_or_else(AppError::cart_item_not_found)?;
+
+    delete_cart_item(pool, cart.id, item_id).await?;
+    cart_view(pool, cart).await
+}
+
+fn cart_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(CART_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|token| !token.trim().is_empty())
+}
+
+async fn require_guest_cart(pool: &DatabasePool, headers: &HeaderMap) -> AppResult<Cart> {
+    let token = cart_token(headers)
+        .ok_or_else(|| AppError::BadRequest("Missing cart token".to_string()))?;
+
+    let cart = find_guest_cart(pool, token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Cart not found".to_string()))?;
+
+    Ok(cart)
+}
+
+// Get the current user's cart
+pub async fn get_cart(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    Ok(Json(cart_view(&pool, &cart).await?))
+}
+
+// Add an item to the current user's cart
+pub async fn add_item(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(item): Json<AddCartItem>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    Ok(Json(add_to_cart(&pool, &cart, item).await?))
+}
+
+// Change the quantity of an item in the current user's cart
+pub async fn update_item(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(item_id): UuidPath,
+    Json(update): Json<UpdateCartItem>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    Ok(Json(update_in_cart(&pool, &cart, item_id, update).await?))
+}
+
+// Remove an item from the current user's cart
+pub async fn remove_item(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(item_id): UuidPath,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    Ok(Json(remove_from_cart(&pool, &cart, item_id).await?))
+}
+
+// Empty the current user's cart
+pub async fn clear_items(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    clear_cart(&pool, cart.id).await?;
+    Ok(Json(cart_view(&pool, &cart).await?))
+}
+
+// Apply a coupon code to the current user's cart
+pub async fn apply_coupon(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(coupon): Json<ApplyCoupon>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let code = coupon.code.trim().to_uppercase();
+
+    find_promotion_by_code(&pool, &code)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Coupon not found".to_string()))?;
+
+    let mut cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    set_cart_coupon(&pool, cart.id, Some(&code)).await?;
+    cart.coupon_code = Some(code);
+
+    Ok(Json(cart_view(&pool, &cart).await?))
+}
+
+// Remove the coupon code from the current user's cart
+pub async fn remove_coupon(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let mut cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    set_cart_coupon(&pool, cart.id, None).await?;
+    cart.coupon_code = None;
+
+    Ok(Json(cart_view(&pool, &cart).await?))
+}
+
+// Change the currency the current user's cart is priced in
+pub async fn set_currency(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(data): Json<SetCartCurrency>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    Ok(Json(change_cart_currency(&pool, cart, data).await?))
+}
+
+// Get an anonymous cart by its token
+pub async fn get_guest_cart(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = require_guest_cart(&pool, &headers).await?;
+    Ok(Json(cart_view(&pool, &cart).await?))
+}
+
+// Add an item to an anonymous cart, creating the cart when no token is sent
+pub async fn add_guest_item(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(item): Json<AddCartItem>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = match cart_token(&headers) {
+        Some(_) => require_guest_cart(&pool, &headers).await?,
+        None => create_guest_cart(&pool, &Uuid::new_v4().simple().to_string()).await?,
+    };
+    Ok(Json(add_to_cart(&pool, &cart, item).await?))
+}
+
+// Change the quantity of an item in an anonymous cart
+pub async fn update_guest_item(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    UuidPath(item_id): UuidPath,
+    Json(update): Json<UpdateCartItem>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = require_guest_cart(&pool, &headers).await?;
+    Ok(Json(update_in_cart(&pool, &cart, item_id, update).await?))
+}
+
+// Remove an item from an anonymous cart
+pub async fn remove_guest_item(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    UuidPath(item_id): UuidPath,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = require_guest_cart(&pool, &headers).await?;
+    Ok(Json(remove_from_cart(&pool, &cart, item_id).await?))
+}
+
+// Change the currency an anonymous cart is priced in
+pub async fn set_guest_currency(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(data): Json<SetCartCurrency>,
+) -> AppResult<Json<CartView>> {
+    let pool = state.db_pool;
+    let cart = require_guest_cart(&pool, &headers).await?;
+    Ok(Json(change_cart_currency(&pool, cart, data).await?))
+}
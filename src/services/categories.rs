@@ -2,7 +2,7 @@ use crate::db::categoryq::*;
 use crate::models::category::*;
 use crate::utils::error::{AppError, AppResult};
 use axum::{
-    extract::State,
+    extract::{Query, State},
     Json,
     http::status::StatusCode,
     response::{IntoResponse,Response}
@@ -17,17 +17,27 @@ pub async fn list_categories(State(state): State<AppState>) -> AppResult<Json<Ve
     Ok(Json(categories))
 }
 
+// Get the nested category tree
+pub async fn get_category_tree(State(state): State<AppState>) -> AppResult<Json<Vec<CategoryNode>>> {
+    let pool = state.db_pool;
+    let categories = find_all_categories(&pool).await?;
+
+    Ok(Json(CategoryNode::build_tree(categories)))
+}
+
 // // Get category by ID
 pub async fn get_category(
     State(state): State<AppState>,
     UuidPath(id): UuidPath,
-) -> AppResult<Json<Category>> {
+) -> AppResult<Json<CategoryWithAncestors>> {
     let pool = state.db_pool;
     let category = find_category_by_id(&pool, id)
         .await?
-        .ok_or_else(|| AppError::category_not_found())?;
+        .ok_or_else(AppError::category_not_found)?;
 
-    Ok(Json(category))
+    let ancestors = find_category_ancestors(&pool, id).await?;
+
+    Ok(Json(CategoryWithAncestors { category, ancestors }))
 }
 
 
@@ -46,6 +56,14 @@ pub async fn create_category(
     if existing_categories.iter().any(|c| c.name.to_lowercase() == category_data.name.to_lowercase()) {
         return Err(AppError::category_name_exists());
     }
+
+    // Verify parent exists if provided
+    if let Some(parent_id) = category_data.parent_id {
+        find_category_by_id(&pool, parent_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Parent category not found".to_string()))?;
+    }
+
     let category = create_category_db(&pool, category_data).await?;
     let response: Response = (StatusCode::CREATED, Json(category)).into_response();
     Ok(response)
@@ -62,7 +80,7 @@ pub async fn update_category(
     // Check if category exists
     find_category_by_id(&pool, id)
         .await?
-        .ok_or_else(|| AppError::category_not_found())?;
+        .ok_or_else(AppError::category_not_found)?;
 
     // Validate input
     if let Some(ref name) = update_data.name {
@@ -77,7 +95,20 @@ pub async fn update_category(
         }
     }
 
-    let category = update_category_db(&pool, id, update_data).await?;
+    if let Some(parent_id) = update_data.parent_id
+        && !update_data.make_root.unwrap_or(false)
+    {
+        find_category_by_id(&pool, parent_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Parent category not found".to_string()))?;
+    }
+
+    // Prevent cycles: the new parent cannot be the category itself or one of its descendants
+    let category = update_category_db(&pool, id, update_data)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(
+            "A category cannot be moved under itself or one of its subcategories".to_string(),
+        ))?;
     Ok(Json(category))
 }
 
@@ -85,12 +116,13 @@ pub async fn update_category(
 pub async fn delete_category(
     State(state): State<AppState>,
     UuidPath(id): UuidPath,
+    Query(query): Query<DeleteCategoryQuery>,
 ) -> AppResult<Json<serde_json::Value>> {
     let pool = state.db_pool;
     // Check if category exists
     find_category_by_id(&pool, id)
         .await?
-        .ok_or_else(|| AppError::category_not_found())?;
+        .ok_or_else(AppError::category_not_found)?;
 
     // Check if category has products
     let product_count = sqlx::query_scalar!(
@@ -106,7 +138,18 @@ pub async fn delete_category(
         ));
     }
 
-    delete_category_db(&pool, id).await?;
+    // Refuse to orphan subcategories unless asked to move them up a level
+    let child_count = count_child_categories(&pool, id).await?;
+    if child_count > 0 {
+        if !query.reparent.unwrap_or(false) {
+            return Err(AppError::BadRequest(
+                "Cannot delete category with subcategories (use ?reparent=true to move them up)".to_string(),
+            ));
+        }
+        reparent_children_and_delete(&pool, id).await?;
+    } else {
+        delete_category_db(&pool, id).await?;
+    }
 
     Ok(Json(serde_json::json!({
         "status": StatusCode::OK.as_u16(),
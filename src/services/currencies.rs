@@ -0,0 +1,89 @@
+use crate::db::currencyq::*;
+use crate::db::db_con::DatabasePool;
+use crate::middleware::auth::AuthUser;
+use crate::models::currency::*;
+use crate::utils::error::{AppError, AppResult};
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::status::StatusCode,
+    Json,
+};
+use rust_decimal::Decimal;
+
+/// Look up a currency code (case-insensitive), rejecting unknown codes
+pub async fn require_currency(pool: &DatabasePool, code: &str) -> AppResult<Currency> {
+    let currency = find_currency(pool, &code.trim().to_uppercase())
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Unsupported currency: {}", code)))?;
+
+    Ok(currency)
+}
+
+/// Conversion from a stored price currency into the requested one
+pub async fn price_conversion(pool: &DatabasePool, from: &str, currency: &Currency) -> AppResult<PriceConversion> {
+    let rate = find_conversion_rate(pool, from, &currency.code)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(format!("No exchange rate from {} to {}", from, currency.code)))?;
+
+    Ok(PriceConversion {
+        currency: currency.clone(),
+        rate,
+    })
+}
+
+// List supported currencies
+pub async fn list_currencies(State(app_state): State<AppState>) -> AppResult<Json<Vec<Currency>>> {
+    let pool = app_state.db_pool;
+    let currencies = find_all_currencies(&pool).await?;
+    Ok(Json(currencies))
+}
+
+// List stored exchange rates (admin only)
+pub async fn list_exchange_rates(State(app_state): State<AppState>) -> AppResult<Json<Vec<ExchangeRate>>> {
+    let pool = app_state.db_pool;
+    let rates = find_exchange_rates(&pool).await?;
+    Ok(Json(rates))
+}
+
+// Create or replace the rate for a currency pair (admin only)
+pub async fn set_exchange_rate(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path((base, quote)): Path<(String, String)>,
+    Json(rate_data): Json<SetExchangeRate>,
+) -> AppResult<Json<ExchangeRate>> {
+    let pool = app_state.db_pool;
+    let base = require_currency(&pool, &base).await?;
+    let quote = require_currency(&pool, &quote).await?;
+
+    if base.code == quote.code {
+        return Err(AppError::Validation("Base and quote currency must differ".to_string()));
+    }
+
+    let rate = match Decimal::from_str_exact(&rate_data.rate) {
+        Ok(rate) if rate > Decimal::ZERO => rate,
+        Ok(_) => return Err(AppError::Validation("Rate must be greater than zero".to_string())),
+        Err(_) => return Err(AppError::Validation("Invalid rate format".to_string())),
+    };
+
+    let rate = upsert_exchange_rate(&pool, &base.code, &quote.code, rate, Some(auth_user.user_id)).await?;
+    Ok(Json(rate))
+}
+
+// Delete the rate for a currency pair (admin only)
+pub async fn delete_exchange_rate_pair(
+    State(app_state): State<AppState>,
+    Path((base, quote)): Path<(String, String)>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = app_state.db_pool;
+
+    if !delete_exchange_rate(&pool, &base.to_uppercase(), &quote.to_uppercase()).await? {
+        return Err(AppError::NotFound("Exchange rate not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Exchange rate deleted successfully"
+    })))
+}
@@ -0,0 +1,41 @@
+use crate::db::lockoutq::*;
+use crate::models::lockout::*;
+use crate::models::other::PaginatedResponse;
+use crate::utils::error::{AppError, AppResult};
+use crate::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+
+// List failed-login counters, optionally only active lockouts (admin only)
+pub async fn list_lockouts(
+    State(app_state): State<AppState>,
+    Query(query): Query<LockoutQuery>,
+) -> AppResult<Json<PaginatedResponse<LoginThrottle>>> {
+    let pool = app_state.db_pool;
+    let throttles = find_login_throttles(&pool, &query).await?;
+    Ok(Json(throttles))
+}
+
+// Clear the failures and lockout of an email or IP (admin only)
+pub async fn clear_lockout(
+    State(app_state): State<AppState>,
+    Path((scope, key)): Path<(ThrottleScope, String)>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = app_state.db_pool;
+    let key = match scope {
+        ThrottleScope::Email => key.trim().to_lowercase(),
+        ThrottleScope::Ip => key,
+    };
+
+    if !clear_login_throttle(&pool, scope, &key).await? {
+        return Err(AppError::NotFound("Lockout not found".to_string()));
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Lockout cleared"
+    })))
+}
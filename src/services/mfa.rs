@@ -0,0 +1,207 @@
+use crate::db::{mfaq::*, securityq::record_security_event, userq::find_by_id};
+use crate::middleware::auth::AuthUser;
+use crate::models::{
+    auth::ClientInfo,
+    mfa::*,
+    security::{MFA_DISABLED, MFA_ENABLED},
+    user::{User, UserRole},
+};
+use crate::services::profile::verify_current_password;
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::token::TokenHasher;
+use crate::utils::totp::*;
+use crate::db::db_con::DatabasePool;
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use dotenvy::dotenv;
+use time::OffsetDateTime;
+
+const MFA_ISSUER: &str = "tests3";
+
+/// Roles listed in MFA_REQUIRED_ROLES (comma separated, e.g. "admin") must use two-factor
+/// authentication: their sessions need a second factor for admin routes and they cannot turn it off
+pub fn mfa_required_for(role: &UserRole) -> bool {
+    dotenv().ok();
+    std::env::var("MFA_REQUIRED_ROLES")
+        .map(|roles| roles.split(',').any(|r| r.trim().eq_ignore_ascii_case(role.as_str())))
+        .unwrap_or(false)
+}
+
+fn mfa_issuer() -> String {
+    dotenv().ok();
+    std::env::var("MFA_ISSUER").unwrap_or_else(|_| MFA_ISSUER.to_string())
+}
+
+fn invalid_code() -> AppError {
+    AppError::Validation("Invalid verification code".to_string())
+}
+
+fn mfa_not_enabled() -> AppError {
+    AppError::BadRequest("Two-factor authentication is not enabled".to_string())
+}
+
+/// Fresh recovery codes for the user, and the hashes to store
+fn new_recovery_codes(hasher: &TokenHasher) -> (Vec<String>, Vec<String>) {
+    let codes = generate_recovery_codes(RECOVERY_CODE_COUNT);
+    let hashes = codes.iter().map(|code| hasher.hash_secret(&normalize_recovery_code(code))).collect();
+    (codes, hashes)
+}
+
+/// Check a code against the user's enabled authenticator, spending its time step
+pub(crate) async fn check_totp_code(pool: &DatabasePool, totp: &UserTotp, code: &str) -> AppResult<bool> {
+    let step = totp
+        .secret_bytes()
+        .and_then(|secret| verify_totp_code(&secret, code, OffsetDateTime::now_utc().unix_timestamp()));
+
+    match step {
+        Some(step) => Ok(use_totp_step(pool, totp.user_id, step).await?),
+        None => Ok(false),
+    }
+}
+
+/// Two-factor settings are credentials, which an impersonating admin must not change
+fn forbid_impersonation(auth_user: &AuthUser) -> AppResult<()> {
+    if auth_user.is_impersonated() {
+        return Err(AppError::impersonation_forbidden());
+    }
+    Ok(())
+}
+
+async fn require_user(pool: &DatabasePool, auth_user: &AuthUser) -> AppResult<User> {
+    let user = find_by_id(pool, auth_user.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    Ok(user)
+}
+
+// Two-factor status of the current user
+pub async fn get_mfa_status(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<MfaStatus>> {
+    let pool = state.db_pool;
+    let totp = find_user_totp(&pool, auth_user.user_id).await?.filter(UserTotp::is_enabled);
+    let recovery_codes_remaining = match totp {
+        Some(_) => count_recovery_codes(&pool, auth_user.user_id).await?,
+        None => 0,
+    };
+
+    Ok(Json(MfaStatus {
+        enabled: totp.is_some(),
+        required: mfa_required_for(&auth_user.role),
+        enabled_at: totp.and_then(|totp| totp.confirmed_at),
+        recovery_codes_remaining,
+    }))
+}
+
+// Start enrollment: a new secret and its provisioning URI for the authenticator app
+pub async fn enroll(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<MfaEnrollment>> {
+    let pool = state.db_pool;
+    forbid_impersonation(&auth_user)?;
+    let user = require_user(&pool, &auth_user).await?;
+
+    let secret = generate_totp_secret();
+    upsert_pending_totp(&pool, user.id, &hex::encode(&secret))
+        .await?
+        .ok_or_else(|| AppError::Conflict("Two-factor authentication is already enabled".to_string()))?;
+
+    Ok(Json(MfaEnrollment {
+        secret: base32_encode(&secret),
+        provisioning_uri: provisioning_uri(&secret, &mfa_issuer(), &user.email),
+    }))
+}
+
+// Finish enrollment with a code from the authenticator; the recovery codes are only shown here
+pub async fn confirm(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    Json(request): Json<MfaCodeRequest>,
+) -> AppResult<(StatusCode, Json<RecoveryCodes>)> {
+    let pool = state.db_pool;
+    forbid_impersonation(&auth_user)?;
+    let totp = find_user_totp(&pool, auth_user.user_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Start enrollment first".to_string()))?;
+    if totp.is_enabled() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    let step = totp
+        .secret_bytes()
+        .and_then(|secret| verify_totp_code(&secret, &request.code, OffsetDateTime::now_utc().unix_timestamp()))
+        .ok_or_else(invalid_code)?;
+
+    let (recovery_codes, code_hashes) = new_recovery_codes(&state.token_hasher);
+    if !confirm_totp(&pool, auth_user.user_id, step, &code_hashes).await? {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+    record_security_event(&pool, Some(auth_user.user_id), MFA_ENABLED, &client, serde_json::json!({})).await?;
+
+    Ok((StatusCode::CREATED, Json(RecoveryCodes { recovery_codes })))
+}
+
+// Replace the recovery codes, e.g. after using some of them
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(request): Json<MfaCodeRequest>,
+) -> AppResult<Json<RecoveryCodes>> {
+    let pool = state.db_pool;
+    forbid_impersonation(&auth_user)?;
+    let totp = find_user_totp(&pool, auth_user.user_id)
+        .await?
+        .filter(UserTotp::is_enabled)
+        .ok_or_else(mfa_not_enabled)?;
+    if !check_totp_code(&pool, &totp, &request.code).await? {
+        return Err(invalid_code());
+    }
+
+    let (recovery_codes, code_hashes) = new_recovery_codes(&state.token_hasher);
+    replace_recovery_codes(&pool, auth_user.user_id, &code_hashes).await?;
+
+    Ok(Json(RecoveryCodes { recovery_codes }))
+}
+
+// Turn two-factor authentication off; needs the password and a code or recovery code
+pub async fn disable(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    Json(request): Json<DisableMfaRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    forbid_impersonation(&auth_user)?;
+    if mfa_required_for(&auth_user.role) {
+        return Err(AppError::Authorization("Two-factor authentication is required for your role".to_string()));
+    }
+
+    let user = require_user(&pool, &auth_user).await?;
+    verify_current_password(&user, Some(&request.current_password))?;
+
+    let totp = find_user_totp(&pool, user.id)
+        .await?
+        .filter(UserTotp::is_enabled)
+        .ok_or_else(mfa_not_enabled)?;
+    let verified = match (request.code.as_deref(), request.recovery_code.as_deref()) {
+        (Some(code), _) => check_totp_code(&pool, &totp, code).await?,
+        (None, Some(recovery_code)) => {
+            use_recovery_code(&pool, user.id, &state.token_hasher.hash_secret(&normalize_recovery_code(recovery_code))).await?
+        }
+        (None, None) => return Err(AppError::Validation("A code or recovery code is required".to_string())),
+    };
+    if !verified {
+        return Err(invalid_code());
+    }
+
+    delete_user_totp(&pool, user.id).await?;
+    record_security_event(&pool, Some(user.id), MFA_DISABLED, &client, serde_json::json!({})).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Two-factor authentication disabled"
+    })))
+}
@@ -1,4 +1,18 @@
+pub mod api_keys;
 pub mod auth;
+pub mod cart;
 pub mod profile;
 pub mod categories;
-pub mod products;
\ No newline at end of file
+pub mod currencies;
+pub mod lockouts;
+pub mod mfa;
+pub mod orders;
+pub mod payments;
+pub mod products;
+pub mod promotions;
+pub mod reviews;
+pub mod roles;
+pub mod users;
+pub mod variants;
+pub mod verification;
+pub mod wishlist;
\ No newline at end of file
@@ -0,0 +1,166 @@
+use crate::db::cartq::{find_cart_lines, find_or_create_user_cart};
+use crate::db::db_con::DatabasePool;
+use crate::db::orderq::*;
+use crate::middleware::auth::AuthUser;
+use crate::models::cart::Cart;
+use crate::models::order::*;
+use crate::models::other::PaginatedResponse;
+use crate::services::cart::cart_view;
+use crate::services::verification::{require_verified_email, CHECKOUT};
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::status::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+async fn change_status(
+    pool: &DatabasePool,
+    order: Order,
+    next: OrderStatus,
+    acting_user: Option<Uuid>,
+) -> AppResult<OrderWithItems> {
+    if !order.status.can_transition_to(next) {
+        return Err(AppError::invalid_order_transition(order.status, next));
+    }
+
+    transition_order_status(pool, order.id, order.status, next, acting_user)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Order was modified concurrently, please retry".to_string()))?;
+
+    let order = find_order_with_items(pool, order.id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    Ok(order)
+}
+
+// Convert the current user's cart into a pending order
+pub async fn checkout(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<impl IntoResponse> {
+    let pool = state.db_pool;
+    require_verified_email(&pool, auth_user.user_id, CHECKOUT).await?;
+    let cart: Cart = find_or_create_user_cart(&pool, auth_user.user_id).await?;
+    let lines = find_cart_lines(&pool, cart.id).await?;
+
+    if lines.is_empty() {
+        return Err(AppError::BadRequest("Cart is empty".to_string()));
+    }
+
+    // Refuse to check out while any line is out of stock or has no price in the cart's currency
+    let view = cart_view(&pool, &cart).await?;
+    if view.has_issues {
+        return Err(AppError::Conflict(
+            "Some items in your cart are unavailable, please review your cart".to_string(),
+        ));
+    }
+
+    let outcome =
+        create_order_from_cart(&pool, auth_user.user_id, &cart, &lines, &view.applied_promotions).await?;
+    let order = match outcome {
+        CheckoutOutcome::Placed(order) => order,
+        CheckoutOutcome::OutOfStock => return Err(AppError::insufficient_stock()),
+        CheckoutOutcome::PromotionUnavailable(name) => {
+            return Err(AppError::Conflict(format!("Promotion '{}' is no longer available", name)));
+        }
+    };
+
+    let response: Response = (StatusCode::CREATED, Json(order)).into_response();
+    Ok(response)
+}
+
+// List the current user's orders
+pub async fn list_my_orders(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(mut filter): Query<OrderFilter>,
+) -> AppResult<Json<PaginatedResponse<Order>>> {
+    let pool = state.db_pool;
+    filter.user_id = Some(auth_user.user_id);
+
+    let orders = find_orders(&pool, &filter).await?;
+    Ok(Json(orders))
+}
+
+// Get one of the current user's orders
+pub async fn get_my_order(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<OrderWithItems>> {
+    let pool = state.db_pool;
+    let order = find_order_with_items(&pool, id)
+        .await?
+        .filter(|o| o.order.user_id == Some(auth_user.user_id))
+        .ok_or_else(AppError::order_not_found)?;
+
+    Ok(Json(order))
+}
+
+// Cancel one of the current user's orders (only while pending)
+pub async fn cancel_my_order(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<OrderWithItems>> {
+    let pool = state.db_pool;
+    let order = find_order_by_id(&pool, id)
+        .await?
+        .filter(|o| o.user_id == Some(auth_user.user_id))
+        .ok_or_else(AppError::order_not_found)?;
+
+    let order = change_status(&pool, order, OrderStatus::Cancelled, Some(auth_user.user_id)).await?;
+    Ok(Json(order))
+}
+
+// List all orders with filtering (admin only)
+pub async fn list_orders(
+    State(state): State<AppState>,
+    Query(filter): Query<OrderFilter>,
+) -> AppResult<Json<PaginatedResponse<Order>>> {
+    let pool = state.db_pool;
+    let orders = find_orders(&pool, &filter).await?;
+    Ok(Json(orders))
+}
+
+// Get any order (admin only)
+pub async fn get_order(
+    State(state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<OrderWithItems>> {
+    let pool = state.db_pool;
+    let order = find_order_with_items(&pool, id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    Ok(Json(order))
+}
+
+// Move an order to a new status (admin only); refunds go through the refund endpoint
+pub async fn update_order_status(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+    Json(update): Json<UpdateOrderStatus>,
+) -> AppResult<Json<OrderWithItems>> {
+    let pool = state.db_pool;
+    if update.status.is_set_by_payments() {
+        return Err(AppError::BadRequest(format!(
+            "Orders can only become {} through a refund (POST /api/admin/orders/:id/refund)",
+            update.status.as_str()
+        )));
+    }
+
+    let order = find_order_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    let order = change_status(&pool, order, update.status, Some(auth_user.user_id)).await?;
+    Ok(Json(order))
+}
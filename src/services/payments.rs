@@ -0,0 +1,285 @@
+use crate::db::db_con::DatabasePool;
+use crate::db::orderq::{find_order_by_id, transition_order_status};
+use crate::db::paymentq::*;
+use crate::middleware::auth::AuthUser;
+use crate::models::order::{Order, OrderStatus};
+use crate::models::payment::*;
+use crate::payments::{PaymentProvider, WebhookEvent, WebhookEventKind, WEBHOOK_SIGNATURE_HEADER};
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{status::StatusCode, HeaderMap},
+    Json,
+};
+use uuid::Uuid;
+
+/// Move an order forward if it isn't there yet. Events that no longer apply
+/// (e.g. a late success for a cancelled order) are logged and skipped.
+async fn advance_order(pool: &DatabasePool, order: &Order, next: OrderStatus, acting_user: Option<Uuid>) -> AppResult<()> {
+    if order.status == next {
+        return Ok(());
+    }
+
+    if !order.status.can_transition_to(next) {
+        tracing::warn!(
+            "Ignoring payment update for order {}: cannot move from {} to {}",
+            order.id,
+            order.status.as_str(),
+            next.as_str()
+        );
+        return Ok(());
+    }
+
+    transition_order_status(pool, order.id, order.status, next, acting_user).await?;
+    Ok(())
+}
+
+/// Whether the event is for exactly what the order was charged: the full amount in its currency
+fn covers_order(event: &WebhookEvent, payment: &Payment, order: &Order) -> bool {
+    event.amount == payment.amount
+        && event.amount == order.total
+        && event.currency.eq_ignore_ascii_case(&order.currency)
+}
+
+async fn handle_webhook_event(pool: &DatabasePool, provider: &dyn PaymentProvider, event: &WebhookEvent) -> AppResult<()> {
+    let Some(payment) = find_payment_by_ref(pool, provider.name(), &event.intent_id, PaymentKind::Payment).await? else {
+        tracing::warn!("Webhook event {} references unknown intent {}", event.id, event.intent_id);
+        return Ok(());
+    };
+
+    let order = find_order_by_id(pool, payment.order_id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    match event.kind {
+        WebhookEventKind::PaymentSucceeded => {
+            // A success for a different amount or currency than the order leaves it unpaid
+            if !covers_order(event, &payment, &order) {
+                tracing::warn!(
+                    "Webhook event {} paid {} {} for order {} expecting {} {}; marking the payment failed",
+                    event.id,
+                    event.amount,
+                    event.currency,
+                    order.id,
+                    order.total,
+                    order.currency
+                );
+                update_payment_status(pool, payment.id, PaymentStatus::Failed).await?;
+                return Ok(());
+            }
+
+            update_payment_status(pool, payment.id, PaymentStatus::Succeeded).await?;
+            advance_order(pool, &order, OrderStatus::Paid, None).await?;
+        }
+        WebhookEventKind::PaymentFailed => {
+            if payment.status == PaymentStatus::Pending {
+                update_payment_status(pool, payment.id, PaymentStatus::Failed).await?;
+            }
+        }
+        WebhookEventKind::RefundSucceeded => {
+            // Only full refunds are supported; a partial or mismatched one is left for an admin to
+            // look at rather than marking the whole order refunded
+            if !covers_order(event, &payment, &order) {
+                tracing::warn!(
+                    "Webhook event {} refunded {} {} for order {} charged {} {}; not marking it refunded",
+                    event.id,
+                    event.amount,
+                    event.currency,
+                    order.id,
+                    order.total,
+                    order.currency
+                );
+                return Ok(());
+            }
+
+            create_payment(
+                pool,
+                order.id,
+                provider.name(),
+                &event.intent_id,
+                PaymentKind::Refund,
+                PaymentStatus::Succeeded,
+                event.amount,
+            )
+            .await?;
+            update_refund_status(pool, provider.name(), &event.intent_id, PaymentStatus::Succeeded).await?;
+            advance_order(pool, &order, OrderStatus::Refunded, None).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn update_refund_status(pool: &DatabasePool, provider: &str, intent_id: &str, status: PaymentStatus) -> AppResult<()> {
+    if let Some(refund) = find_payment_by_ref(pool, provider, intent_id, PaymentKind::Refund).await? {
+        update_payment_status(pool, refund.id, status).await?;
+    }
+    Ok(())
+}
+
+// Start paying for one of the current user's pending orders
+pub async fn pay_order(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<PaymentIntentResponse>> {
+    let pool = state.db_pool;
+    let provider = state.payments;
+    let order = find_order_by_id(&pool, id)
+        .await?
+        .filter(|o| o.user_id == Some(auth_user.user_id))
+        .ok_or_else(AppError::order_not_found)?;
+
+    if order.status != OrderStatus::Pending {
+        return Err(AppError::Conflict("Only pending orders can be paid".to_string()));
+    }
+
+    let intent = provider.create_intent(order.id, order.total, &order.currency).await?;
+    create_payment(
+        &pool,
+        order.id,
+        provider.name(),
+        &intent.id,
+        PaymentKind::Payment,
+        PaymentStatus::Pending,
+        intent.amount,
+    )
+    .await?;
+
+    Ok(Json(PaymentIntentResponse {
+        order_id: order.id,
+        provider: provider.name().to_string(),
+        intent_id: intent.id,
+        client_secret: intent.client_secret,
+        amount: intent.amount,
+        currency: intent.currency,
+    }))
+}
+
+// Signed provider webhook; redelivered events are acknowledged without reprocessing
+pub async fn webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let provider = state.payments;
+
+    let signature = headers
+        .get(WEBHOOK_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| AppError::Authentication("Missing webhook signature".to_string()))?;
+
+    let event = provider
+        .verify_webhook(&body, signature)
+        .map_err(|_| AppError::Authentication("Invalid webhook signature".to_string()))?;
+
+    if !record_webhook_event(&pool, provider.name(), &event.id).await? {
+        return Ok(Json(serde_json::json!({
+            "status": StatusCode::OK.as_u16(),
+            "message": "Event already processed"
+        })));
+    }
+
+    if let Err(e) = handle_webhook_event(&pool, provider.as_ref(), &event).await {
+        forget_webhook_event(&pool, provider.name(), &event.id).await?;
+        return Err(e);
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Event processed"
+    })))
+}
+
+// Payments and refunds recorded for an order (admin only)
+pub async fn list_order_payments(
+    State(state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<Vec<Payment>>> {
+    let pool = state.db_pool;
+    find_order_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    let payments = find_payments_by_order(&pool, id).await?;
+    Ok(Json(payments))
+}
+
+// Capture an authorized payment (admin only)
+pub async fn capture_order_payment(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<Payment>> {
+    let pool = state.db_pool;
+    let provider = state.payments;
+    let order = find_order_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    let payment = find_payments_by_order(&pool, id)
+        .await?
+        .into_iter()
+        .rfind(|p| p.kind == PaymentKind::Payment && p.status == PaymentStatus::Pending)
+        .ok_or_else(|| AppError::NotFound("No pending payment for this order".to_string()))?;
+
+    let result = provider.capture(&payment.provider_ref).await?;
+    update_payment_status(&pool, payment.id, result.status).await?;
+
+    if result.status == PaymentStatus::Succeeded {
+        advance_order(&pool, &order, OrderStatus::Paid, Some(auth_user.user_id)).await?;
+    }
+
+    let payment = find_payment_by_ref(&pool, provider.name(), &payment.provider_ref, PaymentKind::Payment)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Payment not found".to_string()))?;
+
+    Ok(Json(payment))
+}
+
+// Refund the full amount of a paid order (admin only)
+pub async fn refund_order(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<Payment>> {
+    let pool = state.db_pool;
+    let provider = state.payments;
+    let order = find_order_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::order_not_found)?;
+
+    if !order.status.can_transition_to(OrderStatus::Refunded) {
+        return Err(AppError::invalid_order_transition(order.status, OrderStatus::Refunded));
+    }
+
+    let payment = find_payments_by_order(&pool, id)
+        .await?
+        .into_iter()
+        .rfind(|p| p.kind == PaymentKind::Payment && p.status == PaymentStatus::Succeeded)
+        .ok_or_else(|| AppError::NotFound("No captured payment for this order".to_string()))?;
+
+    let result = provider.refund(&payment.provider_ref, payment.amount).await?;
+    tracing::info!("Refund {} issued for order {}", result.reference, order.id);
+
+    let refund = create_payment(
+        &pool,
+        order.id,
+        provider.name(),
+        &payment.provider_ref,
+        PaymentKind::Refund,
+        result.status,
+        payment.amount,
+    )
+    .await?;
+
+    if result.status == PaymentStatus::Succeeded {
+        advance_order(&pool, &order, OrderStatus::Refunded, Some(auth_user.user_id)).await?;
+    }
+
+    Ok(Json(refund))
+}
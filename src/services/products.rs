@@ -1,7 +1,12 @@
 use crate::db::categoryq::find_category_by_id;
+use crate::db::inventoryq::{adjust_stock, find_stock_movements};
 use crate::db::productq::*;
+use crate::middleware::auth::AuthUser;
+use crate::models::currency::{CurrencyQuery, PriceConversion};
+use crate::models::inventory::*;
 use crate::models::product::*;
 use crate::models::other::PaginatedResponse;
+use crate::services::currencies::{price_conversion, require_currency};
 use crate::utils::error::{AppError, AppResult};
 use crate::AppState;
 use axum::{
@@ -9,6 +14,7 @@ use axum::{
     Json,
     http::status,
 };
+use std::collections::HashMap;
 use std::path::Path as StdPath;
 use uuid::Uuid;
 use crate::utils::extractor::UuidPath;
@@ -16,11 +22,29 @@ use crate::utils::extractor::UuidPath;
 // List products with search and filtering
 pub async fn list_products(
     State(app_state): State<AppState>,
-    Query(query): Query<ProductFilter>,
+    Query(mut query): Query<ProductFilter>,
 ) -> AppResult<Json<PaginatedResponse<Product>>> {
     let pool = app_state.db_pool;
-    let products = search_products(&pool, &query).await?;
-    
+    let currency = match &query.currency {
+        Some(code) => Some(require_currency(&pool, code).await?),
+        None => None,
+    };
+    query.currency = currency.as_ref().map(|c| c.code.clone());
+
+    let mut products = search_products(&pool, &query).await?;
+
+    // Convert prices, looking up each stored currency's rate once
+    if let Some(currency) = currency {
+        let mut conversions: HashMap<String, PriceConversion> = HashMap::new();
+        for product in &mut products.data {
+            if !conversions.contains_key(&product.currency) {
+                let conversion = price_conversion(&pool, &product.currency, &currency).await?;
+                conversions.insert(product.currency.clone(), conversion);
+            }
+            product.convert_price(&conversions[&product.currency]);
+        }
+    }
+
     let current_page = products.current_page;
     let total_items = products.total_items;
     let per_page = products.per_page;
@@ -43,19 +67,27 @@ pub async fn list_products(
 pub async fn get_product(
     State(app_state): State<AppState>,
     UuidPath(id): UuidPath,
+    Query(query): Query<CurrencyQuery>,
 ) -> AppResult<Json<ProductWithCategory>> {
     let pool = app_state.db_pool;
-    let product = find_product_with_category_by_id(&pool, id)
+    let mut product = find_product_with_category_by_id(&pool, id)
         .await?
         .ok_or_else(|| AppError::product_not_found())?;
 
+    if let Some(code) = query.currency {
+        let currency = require_currency(&pool, &code).await?;
+        let conversion = price_conversion(&pool, &product.currency, &currency).await?;
+        product.convert_price(&conversion);
+    }
+
     Ok(Json(product))
 }
 
 // Create new product (admin only)
 pub async fn create_product(
     State(app_state): State<AppState>,
-    Json(product_data): Json<CreateProduct>,
+    auth_user: AuthUser,
+    Json(mut product_data): Json<CreateProduct>,
 ) -> AppResult<Json<ProductWithCategory>> {
     let pool = app_state.db_pool;
     // Validate input
@@ -77,13 +109,17 @@ pub async fn create_product(
         return Err(AppError::Validation("Stock cannot be negative".to_string()));
     }
 
+    if let Some(ref code) = product_data.currency {
+        product_data.currency = Some(require_currency(&pool, code).await?.code);
+    }
+
     // Verify category exists
     find_category_by_id(&pool, product_data.category_id)
         .await?
         .ok_or_else(|| AppError::category_not_found())?;
 
     // Create product
-    let product = create_product_db(&pool, product_data).await?;
+    let product = create_product_db(&pool, product_data, Some(auth_user.user_id)).await?;
 
     // Return product with category name
     let product_with_category = find_product_with_category_by_id(&pool, product.id)
@@ -99,6 +135,7 @@ pub async fn create_product(
 // Update product (admin only)
 pub async fn update_product(
     State(app_state): State<AppState>,
+    auth_user: AuthUser,
     UuidPath(id): UuidPath,
     Json(update_data): Json<UpdateProduct>,
 ) -> AppResult<Json<ProductWithCategory>> {
@@ -131,6 +168,11 @@ pub async fn update_product(
         }
     }
 
+    let currency = match update_data.currency {
+        Some(ref code) => Some(require_currency(&pool, code).await?.code),
+        None => None,
+    };
+
     // Verify category exists if provided
     if let Some(category_id) = update_data.category_id {
         find_category_by_id(&pool, category_id)
@@ -143,12 +185,13 @@ pub async fn update_product(
         name: update_data.name,
         description: update_data.description,
         price: update_data.price,
+        currency,
         category_id: update_data.category_id,
         stock: update_data.stock,
     };
 
     // Update product
-    update_product_db(&pool, id, updated_product).await?;
+    update_product_db(&pool, id, updated_product, Some(auth_user.user_id)).await?;
 
     // Return updated product with category name
     let product_with_category = find_product_with_category_by_id(&pool, id)
@@ -269,4 +312,53 @@ pub async fn upload_image(
         .ok_or_else(|| AppError::product_not_found())?;
 
     Ok(Json(product_with_category))
-}
\ No newline at end of file
+}
+
+// Adjust stock by a delta and record the movement (admin only)
+pub async fn adjust_product_stock(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+    Json(adjust_data): Json<AdjustStockRequest>,
+) -> AppResult<Json<StockMovement>> {
+    let pool = app_state.db_pool;
+    // Check if product exists
+    find_product_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    if !adjust_data.kind.allows_delta(adjust_data.delta) {
+        return Err(AppError::Validation(
+            "Delta must be non-zero, positive for receipts and returns, negative for sales".to_string(),
+        ));
+    }
+
+    let movement = adjust_stock(
+        &pool,
+        id,
+        adjust_data.delta,
+        adjust_data.kind,
+        adjust_data.reason,
+        Some(auth_user.user_id),
+    )
+    .await?
+    .ok_or_else(AppError::insufficient_stock)?;
+
+    Ok(Json(movement))
+}
+
+// Stock movement history for a product (admin only)
+pub async fn stock_history(
+    State(app_state): State<AppState>,
+    UuidPath(id): UuidPath,
+    Query(query): Query<StockHistoryQuery>,
+) -> AppResult<Json<PaginatedResponse<StockMovement>>> {
+    let pool = app_state.db_pool;
+    // Check if product exists
+    find_product_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    let history = find_stock_movements(&pool, id, query.page, query.per_page).await?;
+    Ok(Json(history))
+}
@@ -1,10 +1,31 @@
 use crate::middleware::auth::AuthUser;
-use crate::db::userq::*;
-use crate::models::user::*;
+use crate::db::{apikeyq::find_user_api_keys, authq::*, securityq::*, userq::*};
+use crate::db::{cartq::find_user_cart, mfaq::find_user_totp, orderq::find_user_orders_with_items};
+use crate::db::{reviewq::find_reviews_by_user, roleq::find_user_extra_roles, wishlistq::find_wishlist};
+use crate::models::{auth::{ClientInfo, Session}, mfa::UserTotp, security::{ACCOUNT_DELETED, PASSWORD_CHANGED}, user::*};
+use crate::services::cart::cart_view;
+use crate::utils::auth::{hash_password, validate_password, verify_password};
 use crate::utils::error::{AppError, AppResult};
-use axum::{extract::State, Json};
+use crate::services::verification::send_verification_email;
+use crate::utils::extractor::UuidPath;
+use axum::{extract::State, http::StatusCode, Json};
+use time::OffsetDateTime;
 use crate::AppState;
 use validator::Validate;
+
+/// Re-verify the user before sensitive changes
+pub(crate) fn verify_current_password(user: &User, current_password: Option<&str>) -> AppResult<()> {
+    let current_password = current_password
+        .filter(|password| !password.is_empty())
+        .ok_or_else(|| AppError::Validation("Current password is required".to_string()))?;
+
+    if !verify_password(current_password, &user.password_hash)? {
+        return Err(AppError::Authorization("Current password is incorrect".to_string()));
+    }
+
+    Ok(())
+}
+
 // Get current user profile
 pub async fn get_profile(
     State(state): State<AppState>,
@@ -38,24 +59,213 @@ pub async fn update_profile(
         }
     }
 
+    let mut pending_email = None;
     if let Some(ref email) = update_data.email {
         if email.trim().is_empty() {
             return Err(AppError::Validation("Email cannot be empty".to_string()));
         }
-        
-        // Check if email is already taken by another user
-        if let Some(existing_user) = find_by_email(&pool, &email).await.map_err(AppError::from)? {
-            if existing_user.id != auth_user.user_id {
-                return Err(AppError::insufficient_permissions());
-            }
-            if existing_user.username == *update_data.username.as_ref().unwrap() {
-                return Err(AppError::username_already_exists());
+
+        // The email receives password reset links, so changing it needs the current password
+        let user = find_by_id(&pool, auth_user.user_id)
+            .await?
+            .ok_or_else(AppError::user_not_found)?;
+        if *email != user.email {
+            verify_current_password(&user, update_data.current_password.as_deref())?;
+
+            // Check if email is already taken by another user
+            if let Some(existing_user) = find_by_email(&pool, email).await.map_err(AppError::from)? {
+                if existing_user.id != auth_user.user_id {
+                    return Err(AppError::insufficient_permissions());
+                }
+                if existing_user.username == *update_data.username.as_ref().unwrap() {
+                    return Err(AppError::username_already_exists());
+                }
+                return Err(AppError::email_already_exists());
             }
-            return Err(AppError::email_already_exists());
+            pending_email = Some(email.clone());
         }
     }
 
-    let updated_user = update_user(&pool, auth_user.user_id, update_data.username, update_data.email).await?;
+    let mut updated_user = update_user(&pool, auth_user.user_id, update_data.username, None).await?;
+
+    // The new address only replaces the current one once confirmed through the emailed link
+    if let Some(email) = pending_email {
+        updated_user = set_pending_email(&pool, auth_user.user_id, Some(&email)).await?;
+        send_verification_email(&pool, &state.token_hasher, state.mailer, auth_user.user_id, &email).await?;
+    }
 
     Ok(Json(updated_user))
-}
\ No newline at end of file
+}
+// Change the current user's password, logging out every other session
+pub async fn change_password(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    Json(request): Json<ChangePasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    if auth_user.is_impersonated() {
+        return Err(AppError::impersonation_forbidden());
+    }
+    let user = find_by_id(&pool, auth_user.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    verify_current_password(&user, Some(&request.current_password))?;
+    validate_password(&request.new_password)?;
+    if request.new_password == request.current_password {
+        return Err(AppError::Validation("New password must be different from the current password".to_string()));
+    }
+
+    let password_hash = hash_password(&request.new_password)?;
+    update_password(&pool, user.id, &password_hash).await?;
+    delete_password_reset_tokens(&pool, user.id).await?;
+
+    let revoked = delete_other_sessions(&pool, user.id, auth_user.session_id).await?;
+    for session_id in &revoked {
+        state.revocations.revoke(&pool, *session_id, user.id).await?;
+    }
+
+    record_security_event(
+        &pool,
+        Some(user.id),
+        PASSWORD_CHANGED,
+        &client,
+        serde_json::json!({ "sessions_revoked": revoked.len() }),
+    )
+    .await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Password changed",
+        "sessions_revoked": revoked.len()
+    })))
+}
+
+// List the current user's active sessions
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<Vec<Session>>> {
+    let pool = state.db_pool;
+    let mut sessions = find_user_sessions(&pool, auth_user.user_id).await?;
+    for session in &mut sessions {
+        session.current = auth_user.session_id == Some(session.id);
+    }
+
+    Ok(Json(sessions))
+}
+
+// Revoke one of the current user's sessions
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    if auth_user.is_impersonated() {
+        return Err(AppError::impersonation_forbidden());
+    }
+    if !delete_user_session(&pool, auth_user.user_id, id).await? {
+        return Err(AppError::NotFound("Session not found".to_string()));
+    }
+    // Access tokens already issued for the session stop working as well
+    state.revocations.revoke(&pool, id, auth_user.user_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Session revoked"
+    })))
+}
+
+// Log out everywhere except the session making the request
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    if auth_user.is_impersonated() {
+        return Err(AppError::impersonation_forbidden());
+    }
+    let revoked = delete_other_sessions(&pool, auth_user.user_id, auth_user.session_id).await?;
+    for session_id in &revoked {
+        state.revocations.revoke(&pool, *session_id, auth_user.user_id).await?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Logged out of all other sessions",
+        "revoked": revoked.len()
+    })))
+}
+
+// Delete the current user's account. Orders and reviews are kept, attributed to an
+// anonymised user; sessions, carts, wishlists and two-factor settings are removed.
+pub async fn delete_account(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(request): Json<DeleteAccountRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    if auth_user.is_impersonated() {
+        return Err(AppError::impersonation_forbidden());
+    }
+    let user = find_by_id(&pool, auth_user.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    verify_current_password(&user, Some(&request.current_password))?;
+
+    match anonymize_account(&pool, user.id).await? {
+        Some(AccountDeletion::Deleted) => {}
+        Some(AccountDeletion::LastSuperAdmin) => {
+            return Err(AppError::Conflict(
+                "The last superadmin cannot be deleted; promote another user first".to_string(),
+            ));
+        }
+        None => return Err(AppError::user_not_found()),
+    }
+    state.revocations.revoke_all(&pool, user.id).await?;
+
+    // No client details: the event outlives the account and must not hold its PII
+    record_security_event(&pool, Some(user.id), ACCOUNT_DELETED, &ClientInfo::default(), serde_json::json!({})).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Account deleted"
+    })))
+}
+
+// Everything held about the current user, as one JSON document
+pub async fn export_account(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<AccountExport>> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, auth_user.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let mut sessions = find_user_sessions(&pool, user.id).await?;
+    for session in &mut sessions {
+        session.current = auth_user.session_id == Some(session.id);
+    }
+    let cart = match find_user_cart(&pool, user.id).await? {
+        Some(cart) => Some(cart_view(&pool, &cart).await?),
+        None => None,
+    };
+
+    Ok(Json(AccountExport {
+        exported_at: OffsetDateTime::now_utc(),
+        extra_roles: find_user_extra_roles(&pool, user.id).await?,
+        mfa_enabled: find_user_totp(&pool, user.id).await?.filter(UserTotp::is_enabled).is_some(),
+        sessions,
+        api_keys: find_user_api_keys(&pool, user.id).await?,
+        orders: find_user_orders_with_items(&pool, user.id).await?,
+        reviews: find_reviews_by_user(&pool, user.id).await?,
+        wishlist: find_wishlist(&pool, user.id).await?,
+        cart,
+        security_events: find_user_security_events(&pool, user.id).await?,
+        user,
+    }))
+}
@@ -0,0 +1,196 @@
+use crate::db::categoryq::find_category_by_id;
+use crate::db::productq::find_product_by_id;
+use crate::db::promotionq::*;
+use crate::models::promotion::*;
+use crate::services::currencies::require_currency;
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::status::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use rust_decimal::Decimal;
+use time::OffsetDateTime;
+
+fn validate_amount(value: &str, field: &str) -> AppResult<Decimal> {
+    match Decimal::from_str_exact(value) {
+        Ok(amount) if amount < Decimal::ZERO => Err(AppError::Validation(format!("{} cannot be negative", field))),
+        Ok(amount) => Ok(amount),
+        Err(_) => Err(AppError::Validation(format!("Invalid {} format", field.to_lowercase()))),
+    }
+}
+
+fn validate_discount(discount_type: DiscountType, value: &str) -> AppResult<()> {
+    let value = validate_amount(value, "Discount value")?;
+    if value.is_zero() {
+        return Err(AppError::Validation("Discount value must be greater than zero".to_string()));
+    }
+    if discount_type == DiscountType::Percentage && value > Decimal::ONE_HUNDRED {
+        return Err(AppError::Validation("Percentage discount cannot exceed 100".to_string()));
+    }
+    Ok(())
+}
+
+fn validate_limits(usage_limit: Option<i32>, per_user_limit: Option<i32>) -> AppResult<()> {
+    if usage_limit.is_some_and(|limit| limit < 1) || per_user_limit.is_some_and(|limit| limit < 1) {
+        return Err(AppError::Validation("Usage limits must be at least 1".to_string()));
+    }
+    Ok(())
+}
+
+fn validate_window(starts_at: Option<OffsetDateTime>, ends_at: Option<OffsetDateTime>) -> AppResult<()> {
+    if let (Some(starts_at), Some(ends_at)) = (starts_at, ends_at)
+        && ends_at <= starts_at
+    {
+        return Err(AppError::Validation("Promotion must end after it starts".to_string()));
+    }
+    Ok(())
+}
+
+// List all promotions (admin only)
+pub async fn list_promotions(State(app_state): State<AppState>) -> AppResult<Json<Vec<Promotion>>> {
+    let pool = app_state.db_pool;
+    let promotions = find_all_promotions(&pool).await?;
+    Ok(Json(promotions))
+}
+
+// Get a promotion (admin only)
+pub async fn get_promotion(
+    State(app_state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<Promotion>> {
+    let pool = app_state.db_pool;
+    let promotion = find_promotion_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::promotion_not_found)?;
+
+    Ok(Json(promotion))
+}
+
+// Create a promotion (admin only)
+pub async fn create_promotion(
+    State(app_state): State<AppState>,
+    Json(mut promotion_data): Json<CreatePromotion>,
+) -> AppResult<impl IntoResponse> {
+    let pool = app_state.db_pool;
+
+    // Validate input
+    if promotion_data.name.trim().is_empty() {
+        return Err(AppError::Validation("Promotion name cannot be empty".to_string()));
+    }
+
+    validate_discount(promotion_data.discount_type, &promotion_data.discount_value)?;
+
+    if let Some(ref min_spend) = promotion_data.min_spend {
+        validate_amount(min_spend, "Minimum spend")?;
+    }
+
+    if let Some(ref code) = promotion_data.currency {
+        promotion_data.currency = Some(require_currency(&pool, code).await?.code);
+    }
+
+    validate_window(promotion_data.starts_at, promotion_data.ends_at)?;
+    validate_limits(promotion_data.usage_limit, promotion_data.per_user_limit)?;
+
+    match promotion_data.scope {
+        PromotionScope::Cart => {
+            promotion_data.product_id = None;
+            promotion_data.category_id = None;
+        }
+        PromotionScope::Product => {
+            let product_id = promotion_data
+                .product_id
+                .ok_or_else(|| AppError::Validation("Product promotions need a product_id".to_string()))?;
+            find_product_by_id(&pool, product_id)
+                .await?
+                .ok_or_else(AppError::product_not_found)?;
+            promotion_data.category_id = None;
+        }
+        PromotionScope::Category => {
+            let category_id = promotion_data
+                .category_id
+                .ok_or_else(|| AppError::Validation("Category promotions need a category_id".to_string()))?;
+            find_category_by_id(&pool, category_id)
+                .await?
+                .ok_or_else(AppError::category_not_found)?;
+            promotion_data.product_id = None;
+        }
+    }
+
+    // Coupon codes are matched case-insensitively by storing them uppercase
+    promotion_data.code = promotion_data
+        .code
+        .map(|code| code.trim().to_uppercase())
+        .filter(|code| !code.is_empty());
+
+    if let Some(ref code) = promotion_data.code
+        && find_promotion_by_code(&pool, code).await?.is_some()
+    {
+        return Err(AppError::Conflict("Coupon code already exists".to_string()));
+    }
+
+    let promotion = create_promotion_db(&pool, promotion_data).await?;
+    let response: Response = (StatusCode::CREATED, Json(promotion)).into_response();
+    Ok(response)
+}
+
+// Update a promotion (admin only)
+pub async fn update_promotion(
+    State(app_state): State<AppState>,
+    UuidPath(id): UuidPath,
+    Json(mut update_data): Json<UpdatePromotion>,
+) -> AppResult<Json<Promotion>> {
+    let pool = app_state.db_pool;
+    let existing = find_promotion_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::promotion_not_found)?;
+
+    // Validate input
+    if let Some(ref name) = update_data.name
+        && name.trim().is_empty()
+    {
+        return Err(AppError::Validation("Promotion name cannot be empty".to_string()));
+    }
+
+    if let Some(ref discount_value) = update_data.discount_value {
+        validate_discount(existing.discount_type, discount_value)?;
+    }
+
+    if let Some(ref min_spend) = update_data.min_spend {
+        validate_amount(min_spend, "Minimum spend")?;
+    }
+
+    if let Some(ref code) = update_data.currency {
+        update_data.currency = Some(require_currency(&pool, code).await?.code);
+    }
+
+    validate_window(
+        update_data.starts_at.or(existing.starts_at),
+        update_data.ends_at.or(existing.ends_at),
+    )?;
+    validate_limits(update_data.usage_limit, update_data.per_user_limit)?;
+
+    let promotion = update_promotion_db(&pool, id, update_data).await?;
+    Ok(Json(promotion))
+}
+
+// Delete a promotion (admin only)
+pub async fn delete_promotion(
+    State(app_state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = app_state.db_pool;
+    find_promotion_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::promotion_not_found)?;
+
+    delete_promotion_db(&pool, id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Promotion deleted successfully"
+    })))
+}
@@ -0,0 +1,122 @@
+use crate::db::productq::find_product_by_id;
+use crate::db::reviewq::*;
+use crate::middleware::auth::AuthUser;
+use crate::models::other::PaginatedResponse;
+use crate::models::review::*;
+use crate::services::verification::{require_verified_email, REVIEWS};
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::status::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+// Leave a review on a product (one per user per product)
+pub async fn create_review(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(product_id): UuidPath,
+    Json(review_data): Json<CreateReview>,
+) -> AppResult<impl IntoResponse> {
+    let pool = app_state.db_pool;
+    require_verified_email(&pool, auth_user.user_id, REVIEWS).await?;
+    find_product_by_id(&pool, product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    // Validate input
+    if !(1..=5).contains(&review_data.rating) {
+        return Err(AppError::Validation("Rating must be between 1 and 5".to_string()));
+    }
+
+    let comment_length = review_data.comment.trim().chars().count();
+    if comment_length == 0 {
+        return Err(AppError::Validation("Review text cannot be empty".to_string()));
+    }
+    if comment_length > 2000 {
+        return Err(AppError::Validation("Review text cannot exceed 2000 characters".to_string()));
+    }
+
+    // The unique (product, user) constraint settles concurrent submissions
+    let verified_purchase = has_purchased_product(&pool, auth_user.user_id, product_id).await?;
+    let review = create_review_db(&pool, product_id, auth_user.user_id, review_data, verified_purchase)
+        .await?
+        .ok_or_else(|| AppError::Conflict("You have already reviewed this product".to_string()))?;
+
+    let response: Response = (StatusCode::CREATED, Json(review)).into_response();
+    Ok(response)
+}
+
+// List approved reviews of a product
+pub async fn list_product_reviews(
+    State(app_state): State<AppState>,
+    UuidPath(product_id): UuidPath,
+    Query(query): Query<ReviewQuery>,
+) -> AppResult<Json<PaginatedResponse<Review>>> {
+    let pool = app_state.db_pool;
+    find_product_by_id(&pool, product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    let reviews = find_reviews(
+        &pool,
+        Some(product_id),
+        Some(ReviewStatus::Approved),
+        query.page,
+        query.per_page,
+    )
+    .await?;
+
+    Ok(Json(reviews))
+}
+
+// List reviews across products, e.g. `?status=pending` for the moderation queue (admin only)
+pub async fn list_reviews(
+    State(app_state): State<AppState>,
+    Query(query): Query<ReviewQuery>,
+) -> AppResult<Json<PaginatedResponse<Review>>> {
+    let pool = app_state.db_pool;
+    let reviews = find_reviews(&pool, None, query.status, query.page, query.per_page).await?;
+    Ok(Json(reviews))
+}
+
+// Approve or hide a review (admin only)
+pub async fn moderate_review(
+    State(app_state): State<AppState>,
+    UuidPath(id): UuidPath,
+    Json(moderation): Json<ModerateReview>,
+) -> AppResult<Json<Review>> {
+    let pool = app_state.db_pool;
+    find_review_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::review_not_found)?;
+
+    set_review_status(&pool, id, moderation.status).await?;
+
+    let review = find_review_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::review_not_found)?;
+
+    Ok(Json(review))
+}
+
+// Delete a review (admin only)
+pub async fn delete_review(
+    State(app_state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = app_state.db_pool;
+    find_review_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::review_not_found)?;
+
+    delete_review_db(&pool, id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Review deleted successfully"
+    })))
+}
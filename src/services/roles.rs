@@ -0,0 +1,167 @@
+use crate::db::{db_con::DatabasePool, roleq::*, userq::find_by_id};
+use crate::models::permission::*;
+use crate::models::user::{User, UserRole};
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::utils::revocation::RevocationList;
+use crate::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use validator::Validate;
+
+/// Permissions to put in the user's access tokens. Superadmins always get every permission,
+/// including ones added after their role was seeded.
+pub async fn resolve_permissions(pool: &DatabasePool, user: &User) -> AppResult<Vec<String>> {
+    if matches!(user.role, UserRole::SuperAdmin) {
+        return Ok(PERMISSIONS.iter().map(|p| p.to_string()).collect());
+    }
+    Ok(find_user_permissions(pool, user.id).await?)
+}
+
+fn role_not_found() -> AppError {
+    AppError::NotFound("Role not found".to_string())
+}
+
+fn check_permissions(permissions: &[String]) -> AppResult<Vec<String>> {
+    normalize_permissions(permissions)
+        .map_err(|permission| AppError::Validation(format!("Unknown permission '{}'", permission)))
+}
+
+/// Tokens carry permissions, so holders of a changed role must pick up new tokens
+async fn revoke_role_holders(pool: &DatabasePool, revocations: &RevocationList, role: &str) -> AppResult<()> {
+    for user_id in find_role_user_ids(pool, role).await? {
+        revocations.revoke_all(pool, user_id).await?;
+    }
+    Ok(())
+}
+
+// Every permission routes can require
+pub async fn list_permissions() -> Json<&'static [&'static str]> {
+    Json(PERMISSIONS)
+}
+
+// List roles with their permissions
+pub async fn list_roles(State(state): State<AppState>) -> AppResult<Json<Vec<Role>>> {
+    let pool = state.db_pool;
+    let roles = find_roles(&pool).await?;
+    Ok(Json(roles))
+}
+
+// Create a custom role
+pub async fn create_role(
+    State(state): State<AppState>,
+    Json(request): Json<CreateRole>,
+) -> AppResult<(StatusCode, Json<Role>)> {
+    let pool = state.db_pool;
+    if let Err(e) = request.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+    let name = request.name.trim();
+    if !is_valid_role_name(name) {
+        return Err(AppError::Validation(
+            "Role names use lowercase letters, digits, '-' and '_' (at most 50 characters)".to_string(),
+        ));
+    }
+    let permissions = check_permissions(&request.permissions)?;
+
+    if !create_role_db(&pool, name, request.description.as_deref(), &permissions).await? {
+        return Err(AppError::Conflict("Role already exists".to_string()));
+    }
+
+    let role = find_role(&pool, name).await?.ok_or_else(role_not_found)?;
+    Ok((StatusCode::CREATED, Json(role)))
+}
+
+// Change a role's description or permissions; the superadmin role is fixed
+pub async fn update_role(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(request): Json<UpdateRole>,
+) -> AppResult<Json<Role>> {
+    let pool = state.db_pool;
+    if let Err(e) = request.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+    if name == SUPERADMIN_ROLE {
+        return Err(AppError::Authorization("The superadmin role cannot be changed".to_string()));
+    }
+    find_role(&pool, &name).await?.ok_or_else(role_not_found)?;
+
+    let permissions = request.permissions.as_deref().map(check_permissions).transpose()?;
+    update_role_db(&pool, &name, request.description.as_deref(), permissions.as_deref()).await?;
+    if permissions.is_some() {
+        revoke_role_holders(&pool, &state.revocations, &name).await?;
+    }
+
+    let role = find_role(&pool, &name).await?.ok_or_else(role_not_found)?;
+    Ok(Json(role))
+}
+
+// Delete a custom role, removing it from every user
+pub async fn delete_role(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let role = find_role(&pool, &name).await?.ok_or_else(role_not_found)?;
+    if role.builtin {
+        return Err(AppError::Authorization("Built-in roles cannot be deleted".to_string()));
+    }
+
+    let holders = find_role_user_ids(&pool, &name).await?;
+    if !delete_role_db(&pool, &name).await? {
+        return Err(role_not_found());
+    }
+    for user_id in holders {
+        state.revocations.revoke_all(&pool, user_id).await?;
+    }
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Role deleted"
+    })))
+}
+
+// Extra roles of a user
+pub async fn get_user_roles(
+    State(state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<AssignRoles>> {
+    let pool = state.db_pool;
+    find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let roles = find_user_extra_roles(&pool, id).await?;
+    Ok(Json(AssignRoles { roles }))
+}
+
+// Replace the extra roles of a user; built-in roles are set through the user's role instead
+pub async fn set_user_roles(
+    State(state): State<AppState>,
+    UuidPath(id): UuidPath,
+    Json(request): Json<AssignRoles>,
+) -> AppResult<Json<AssignRoles>> {
+    let pool = state.db_pool;
+    find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let mut roles: Vec<String> = request.roles.iter().map(|r| r.trim().to_string()).collect();
+    roles.sort();
+    roles.dedup();
+    for role in &roles {
+        let known = find_role(&pool, role).await?.ok_or_else(|| AppError::Validation(format!("Unknown role '{}'", role)))?;
+        if known.builtin {
+            return Err(AppError::Validation(format!("'{}' is a built-in role; change the user's role instead", role)));
+        }
+    }
+
+    set_user_extra_roles(&pool, id, &roles).await?;
+    state.revocations.revoke_all(&pool, id).await?;
+
+    Ok(Json(AssignRoles { roles }))
+}
@@ -0,0 +1,295 @@
+use crate::db::{authq::*, mfaq::find_user_totp, securityq::record_security_event, userq::*};
+use crate::middleware::auth::AuthUser;
+use crate::models::auth::{Actor, ClientInfo};
+use crate::models::other::PaginatedResponse;
+use crate::models::security::{
+    ACCOUNT_REACTIVATED, ACCOUNT_SUSPENDED, FORCED_LOGOUT, IMPERSONATION_ENDED, IMPERSONATION_STARTED, ROLE_CHANGED,
+};
+use crate::models::user::*;
+use crate::services::roles::resolve_permissions;
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::jwt::{create_impersonation_token, get_impersonation_duration};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+
+/// Admin accounts can only be managed by a superadmin
+pub(crate) fn require_can_manage(actor: &AuthUser, target: &User) -> AppResult<()> {
+    if target.role.is_admin() && !matches!(actor.role, UserRole::SuperAdmin) {
+        return Err(AppError::Authorization("Only a superadmin can manage admin accounts".to_string()));
+    }
+    Ok(())
+}
+
+// List and search users (admin only)
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(query): Query<UserQuery>,
+) -> AppResult<Json<PaginatedResponse<User>>> {
+    let pool = state.db_pool;
+    let users = find_users(&pool, &query).await?;
+    Ok(Json(users))
+}
+
+// Get a user with their active sessions (admin only)
+pub async fn get_user(
+    State(state): State<AppState>,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<AdminUserView>> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    let mfa_enabled = find_user_totp(&pool, id).await?.is_some_and(|totp| totp.is_enabled());
+    let sessions = find_user_sessions(&pool, id).await?;
+
+    Ok(Json(AdminUserView { user, mfa_enabled, sessions }))
+}
+
+// Promote or demote a user (superadmin only); the last superadmin keeps their role
+pub async fn update_user_role(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+    Json(update): Json<UpdateUserRole>,
+) -> AppResult<Json<User>> {
+    let pool = state.db_pool;
+    if !matches!(auth_user.role, UserRole::SuperAdmin) {
+        return Err(AppError::Authorization("Only a superadmin can change roles".to_string()));
+    }
+
+    let previous = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let user = match change_user_role(&pool, id, update.role).await? {
+        Some(RoleChange::Updated(user)) => user,
+        Some(RoleChange::LastSuperAdmin) => {
+            return Err(AppError::Conflict("The last superadmin cannot be demoted".to_string()));
+        }
+        None => return Err(AppError::user_not_found()),
+    };
+
+    // Access tokens carry the role; refreshing issues new ones with the new role
+    state.revocations.revoke_all(&pool, user.id).await?;
+    record_security_event(
+        &pool,
+        Some(user.id),
+        ROLE_CHANGED,
+        &client,
+        serde_json::json!({
+            "from": previous.role.as_str(),
+            "to": user.role.as_str(),
+            "changed_by": auth_user.user_id,
+        }),
+    )
+    .await?;
+
+    Ok(Json(user))
+}
+
+// Suspend a user and end all their sessions (admin only)
+pub async fn suspend_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<User>> {
+    let pool = state.db_pool;
+    if id == auth_user.user_id {
+        return Err(AppError::BadRequest("You cannot suspend your own account".to_string()));
+    }
+
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    require_can_manage(&auth_user, &user)?;
+
+    let user = set_user_status(&pool, id, UserStatus::Suspended)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    delete_user_refresh_tokens(&pool, id).await?;
+    state.revocations.revoke_all(&pool, id).await?;
+    record_security_event(&pool, Some(id), ACCOUNT_SUSPENDED, &client, serde_json::json!({ "by": auth_user.user_id })).await?;
+
+    Ok(Json(user))
+}
+
+// Lift a suspension (admin only)
+pub async fn reactivate_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<User>> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    require_can_manage(&auth_user, &user)?;
+
+    let user = set_user_status(&pool, id, UserStatus::Active)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    record_security_event(&pool, Some(id), ACCOUNT_REACTIVATED, &client, serde_json::json!({ "by": auth_user.user_id })).await?;
+
+    Ok(Json(user))
+}
+
+// End every session of a user (admin only)
+pub async fn force_logout(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    require_can_manage(&auth_user, &user)?;
+
+    delete_user_refresh_tokens(&pool, id).await?;
+    state.revocations.revoke_all(&pool, id).await?;
+    record_security_event(&pool, Some(id), FORCED_LOGOUT, &client, serde_json::json!({ "by": auth_user.user_id })).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "User logged out of all sessions"
+    })))
+}
+
+// Log in as a user to see what they see (admin only). The token is short-lived, names the
+// admin in its `act` claim and cannot be used to change the user's credentials.
+pub async fn impersonate_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+    UuidPath(id): UuidPath,
+) -> AppResult<Json<ImpersonationResponse>> {
+    let pool = state.db_pool;
+    if auth_user.is_impersonated() || auth_user.api_key_id.is_some() {
+        return Err(AppError::Authorization("Impersonation needs your own session".to_string()));
+    }
+    if id == auth_user.user_id {
+        return Err(AppError::BadRequest("You cannot impersonate yourself".to_string()));
+    }
+
+    let user = find_by_id(&pool, id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    require_can_manage(&auth_user, &user)?;
+    if user.status != UserStatus::Active {
+        return Err(AppError::account_inactive(user.status));
+    }
+
+    // Extra roles can give a plain user permissions the admin lacks; impersonating them would hand those over
+    let permissions = resolve_permissions(&pool, &user).await?;
+    if permissions.iter().any(|p| !auth_user.has_permission(p)) {
+        return Err(AppError::Authorization(
+            "You cannot impersonate a user with permissions you do not hold".to_string(),
+        ));
+    }
+    let actor = Actor { sub: auth_user.user_id, username: auth_user.username.clone() };
+    let (access_token, token_id) =
+        create_impersonation_token(user.id, &user.username, user.role.clone(), permissions, actor, &state.jwt_keys)?;
+    let expires_in = get_impersonation_duration() * 60;
+
+    record_security_event(
+        &pool,
+        Some(user.id),
+        IMPERSONATION_STARTED,
+        &client,
+        serde_json::json!({ "by": auth_user.user_id, "token_id": token_id, "expires_in": expires_in }),
+    )
+    .await?;
+
+    Ok(Json(ImpersonationResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in,
+        user,
+    }))
+}
+
+// End the impersonation the request is made with, revoking its token
+pub async fn end_impersonation(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    client: ClientInfo,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let (Some(actor), Some(token_id)) = (auth_user.act.as_ref(), auth_user.token_id) else {
+        return Err(AppError::BadRequest("This session is not an impersonation".to_string()));
+    };
+
+    state.revocations.revoke(&pool, token_id, auth_user.user_id).await?;
+    record_security_event(
+        &pool,
+        Some(auth_user.user_id),
+        IMPERSONATION_ENDED,
+        &client,
+        serde_json::json!({ "by": actor.sub, "token_id": token_id }),
+    )
+    .await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Impersonation ended"
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+    use uuid::Uuid;
+
+    fn user(role: UserRole) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: "someone".to_string(),
+            email: "someone@example.com".to_string(),
+            password_hash: String::new(),
+            role,
+            status: UserStatus::Active,
+            email_verified_at: None,
+            pending_email: None,
+            created_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn actor(role: UserRole) -> AuthUser {
+        AuthUser {
+            user_id: Uuid::new_v4(),
+            username: "actor".to_string(),
+            role,
+            session_id: None,
+            mfa: false,
+            permissions: Vec::new(),
+            api_key_id: None,
+            token_id: None,
+            act: None,
+        }
+    }
+
+    #[test]
+    fn admins_manage_plain_users() {
+        assert!(require_can_manage(&actor(UserRole::Admin), &user(UserRole::User)).is_ok());
+        assert!(require_can_manage(&actor(UserRole::SuperAdmin), &user(UserRole::User)).is_ok());
+    }
+
+    #[test]
+    fn only_superadmins_manage_admins() {
+        for target in [UserRole::Admin, UserRole::SuperAdmin] {
+            let result = require_can_manage(&actor(UserRole::Admin), &user(target.clone()));
+            assert!(matches!(result, Err(AppError::Authorization(_))));
+            assert!(require_can_manage(&actor(UserRole::SuperAdmin), &user(target)).is_ok());
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::db::productq::find_product_by_id;
+use crate::db::variantq::*;
+use crate::middleware::auth::AuthUser;
+use crate::models::product::*;
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use rust_decimal::Decimal;
+use axum::{
+    extract::{Path, State},
+    http::status::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use uuid::Uuid;
+
+/// Parse a price the same way the queries do, so anything accepted here also stores cleanly
+fn validate_price(price: &str) -> AppResult<Decimal> {
+    match Decimal::from_str_exact(price) {
+        Ok(price) if price < Decimal::ZERO => Err(AppError::Validation("Price cannot be negative".to_string())),
+        Ok(price) => Ok(price),
+        Err(_) => Err(AppError::Validation("Invalid price format".to_string())),
+    }
+}
+
+// List variants of a product
+pub async fn list_variants(
+    State(app_state): State<AppState>,
+    UuidPath(product_id): UuidPath,
+) -> AppResult<Json<Vec<ProductVariant>>> {
+    let pool = app_state.db_pool;
+    find_product_by_id(&pool, product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    let variants = find_variants_by_product(&pool, product_id).await?;
+    Ok(Json(variants))
+}
+
+// Create a variant for a product (admin only)
+pub async fn create_variant(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(product_id): UuidPath,
+    Json(variant_data): Json<CreateVariant>,
+) -> AppResult<impl IntoResponse> {
+    let pool = app_state.db_pool;
+    find_product_by_id(&pool, product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    // Validate input
+    if variant_data.sku.trim().is_empty() {
+        return Err(AppError::Validation("SKU cannot be empty".to_string()));
+    }
+
+    if let Some(ref price) = variant_data.price {
+        validate_price(price)?;
+    }
+
+    if variant_data.stock < 0 {
+        return Err(AppError::Validation("Stock cannot be negative".to_string()));
+    }
+
+    if find_variant_by_sku(&pool, &variant_data.sku).await?.is_some() {
+        return Err(AppError::sku_already_exists());
+    }
+
+    let variant = create_variant_db(&pool, product_id, variant_data, Some(auth_user.user_id)).await?;
+    let response: Response = (StatusCode::CREATED, Json(variant)).into_response();
+    Ok(response)
+}
+
+// Update a variant (admin only)
+pub async fn update_variant(
+    State(app_state): State<AppState>,
+    auth_user: AuthUser,
+    Path((product_id, variant_id)): Path<(Uuid, Uuid)>,
+    Json(update_data): Json<UpdateVariant>,
+) -> AppResult<Json<ProductVariant>> {
+    let pool = app_state.db_pool;
+    find_variant_by_id(&pool, product_id, variant_id)
+        .await?
+        .ok_or_else(AppError::variant_not_found)?;
+
+    // Validate input
+    if let Some(ref sku) = update_data.sku {
+        if sku.trim().is_empty() {
+            return Err(AppError::Validation("SKU cannot be empty".to_string()));
+        }
+
+        // Check for duplicate SKU (excluding current variant)
+        if let Some(existing) = find_variant_by_sku(&pool, sku).await?
+            && existing.id != variant_id
+        {
+            return Err(AppError::sku_already_exists());
+        }
+    }
+
+    if let Some(ref price) = update_data.price {
+        validate_price(price)?;
+    }
+
+    if let Some(stock) = update_data.stock
+        && stock < 0
+    {
+        return Err(AppError::Validation("Stock cannot be negative".to_string()));
+    }
+
+    let variant = update_variant_db(&pool, variant_id, update_data, Some(auth_user.user_id)).await?;
+    Ok(Json(variant))
+}
+
+// Delete a variant (admin only)
+pub async fn delete_variant(
+    State(app_state): State<AppState>,
+    Path((product_id, variant_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = app_state.db_pool;
+    find_variant_by_id(&pool, product_id, variant_id)
+        .await?
+        .ok_or_else(AppError::variant_not_found)?;
+
+    delete_variant_db(&pool, variant_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Variant deleted successfully"
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_decimal_prices() {
+        assert_eq!(validate_price("12.50").unwrap(), Decimal::new(1250, 2));
+        assert_eq!(validate_price("0").unwrap(), Decimal::ZERO);
+    }
+
+    #[test]
+    fn rejects_negative_prices() {
+        assert!(matches!(validate_price("-1.00"), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn rejects_what_the_queries_cannot_store() {
+        for price in ["1e5", "NaN", "inf", "-inf", "", "abc", "1,50"] {
+            assert!(matches!(validate_price(price), Err(AppError::Validation(_))), "{price}");
+        }
+    }
+}
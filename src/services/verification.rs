@@ -0,0 +1,117 @@
+use crate::db::{authq::*, db_con::DatabasePool, userq::*};
+use crate::mail::{send_in_background, templates, Mailer};
+use crate::middleware::auth::AuthUser;
+use crate::models::{auth::VerifyEmailRequest, user::User};
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::jwt::{get_email_verification_duration, get_email_verification_resend_interval};
+use crate::utils::token::TokenHasher;
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use dotenvy::dotenv;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+// Actions that REQUIRE_VERIFIED_EMAIL can gate (comma separated, e.g. "checkout,reviews")
+pub const CHECKOUT: &str = "checkout";
+pub const REVIEWS: &str = "reviews";
+
+fn verification_required_for(action: &str) -> bool {
+    dotenv().ok();
+    std::env::var("REQUIRE_VERIFIED_EMAIL")
+        .map(|actions| actions.split(',').any(|a| a.trim() == action))
+        .unwrap_or(false)
+}
+
+/// Refuse `action` for users with an unverified email when the action is gated
+pub async fn require_verified_email(pool: &DatabasePool, user_id: Uuid, action: &str) -> AppResult<()> {
+    if !verification_required_for(action) {
+        return Ok(());
+    }
+
+    let user = find_by_id(pool, user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+    if user.email_verified_at.is_none() {
+        return Err(AppError::Authorization("Please verify your email address first".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Email a verification link for `email` (the user's current or pending address)
+pub async fn send_verification_email(
+    pool: &DatabasePool,
+    hasher: &TokenHasher,
+    mailer: Arc<dyn Mailer>,
+    user_id: Uuid,
+    email: &str,
+) -> AppResult<()> {
+    let (token, stored_token) = hasher.generate_split_token();
+    let hours = get_email_verification_duration();
+    create_email_verification_token(pool, user_id, email, &stored_token, hours).await?;
+    send_in_background(mailer, templates::email_verification(email, &token, hours));
+
+    Ok(())
+}
+
+// Confirm an email address with the emailed token
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Json(request): Json<VerifyEmailRequest>,
+) -> AppResult<Json<User>> {
+    let pool = state.db_pool;
+    let invalid_token = || AppError::BadRequest("Invalid or expired verification token".to_string());
+
+    let verification_token = find_email_verification_token(&pool, &state.token_hasher, &request.token)
+        .await?
+        .ok_or_else(invalid_token)?;
+
+    // Someone else may have claimed the address while it was pending
+    if let Some(owner) = find_by_email(&pool, &verification_token.email).await?
+        && owner.id != verification_token.user_id
+    {
+        return Err(AppError::email_already_exists());
+    }
+
+    // The unique constraint catches an address claimed between the check above and the update
+    match confirm_email_verification(&pool, &verification_token).await? {
+        EmailVerificationOutcome::Verified(user) => Ok(Json(user)),
+        EmailVerificationOutcome::Invalid => Err(invalid_token()),
+        EmailVerificationOutcome::EmailTaken => Err(AppError::email_already_exists()),
+    }
+}
+
+// Send the verification link again, at most once per resend interval
+pub async fn resend_verification(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let user = find_by_id(&pool, auth_user.user_id)
+        .await?
+        .ok_or_else(AppError::user_not_found)?;
+
+    let email = match (user.pending_email, user.email_verified_at) {
+        (Some(pending), _) => pending,
+        (None, None) => user.email,
+        (None, Some(_)) => return Err(AppError::Conflict("Email address is already verified".to_string())),
+    };
+
+    if let Some(sent_at) = find_last_verification_sent(&pool, user.id).await? {
+        let wait = get_email_verification_resend_interval() - (OffsetDateTime::now_utc() - sent_at).whole_seconds();
+        if wait > 0 {
+            return Err(AppError::too_many_requests(
+                "Please wait before requesting another verification email",
+                wait as u64,
+            ));
+        }
+    }
+
+    send_verification_email(&pool, &state.token_hasher, state.mailer, user.id, &email).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": format!("Verification email sent to {}", email)
+    })))
+}
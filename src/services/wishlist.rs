@@ -0,0 +1,91 @@
+use crate::db::productq::find_product_by_id;
+use crate::db::wishlistq::*;
+use crate::middleware::auth::AuthUser;
+use crate::models::wishlist::*;
+use crate::utils::error::{AppError, AppResult};
+use crate::utils::extractor::UuidPath;
+use crate::AppState;
+use axum::{extract::State, http::status::StatusCode, Json};
+
+// Get the current user's wishlist
+pub async fn get_wishlist(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> AppResult<Json<Vec<WishlistItem>>> {
+    let pool = state.db_pool;
+    let items = find_wishlist(&pool, auth_user.user_id).await?;
+    Ok(Json(items))
+}
+
+// Save a product to the current user's wishlist
+pub async fn add_item(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(item): Json<AddWishlistItem>,
+) -> AppResult<Json<Vec<WishlistItem>>> {
+    let pool = state.db_pool;
+    find_product_by_id(&pool, item.product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    add_wishlist_item(&pool, auth_user.user_id, item.product_id).await?;
+    if item.notify_when_in_stock.unwrap_or(false) {
+        subscribe_to_stock(&pool, auth_user.user_id, item.product_id).await?;
+    }
+
+    let items = find_wishlist(&pool, auth_user.user_id).await?;
+    Ok(Json(items))
+}
+
+// Remove a product from the current user's wishlist (path id is the product id)
+pub async fn remove_item(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(product_id): UuidPath,
+) -> AppResult<Json<Vec<WishlistItem>>> {
+    let pool = state.db_pool;
+    if !remove_wishlist_item(&pool, auth_user.user_id, product_id).await? {
+        return Err(AppError::NotFound("Product is not on your wishlist".to_string()));
+    }
+
+    let items = find_wishlist(&pool, auth_user.user_id).await?;
+    Ok(Json(items))
+}
+
+// Ask to be notified when a product is back in stock
+pub async fn subscribe(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(product_id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    let product = find_product_by_id(&pool, product_id)
+        .await?
+        .ok_or_else(AppError::product_not_found)?;
+
+    if product.stock > 0 {
+        return Err(AppError::Conflict("Product is already in stock".to_string()));
+    }
+
+    subscribe_to_stock(&pool, auth_user.user_id, product_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "You will be notified when this product is back in stock"
+    })))
+}
+
+// Cancel a back-in-stock notification
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    UuidPath(product_id): UuidPath,
+) -> AppResult<Json<serde_json::Value>> {
+    let pool = state.db_pool;
+    unsubscribe_from_stock(&pool, auth_user.user_id, product_id).await?;
+
+    Ok(Json(serde_json::json!({
+        "status": StatusCode::OK.as_u16(),
+        "message": "Back-in-stock notification cancelled"
+    })))
+}
@@ -19,6 +19,28 @@ fn create_argon2() -> Argon2<'static> {
         params                        // Custom parameters
     )
 }
+// Password policy
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+pub const PASSWORD_MAX_LENGTH: usize = 128; // Bounds the hashing work per request
+
+/// Check a new password against the policy: 8-128 characters with at least one letter and one non-letter
+pub fn validate_password(password: &str) -> AppResult<()> {
+    let length = password.chars().count();
+    if length < PASSWORD_MIN_LENGTH {
+        return Err(AppError::Validation(format!("Password must be at least {} characters", PASSWORD_MIN_LENGTH)));
+    }
+    if length > PASSWORD_MAX_LENGTH {
+        return Err(AppError::Validation(format!("Password cannot exceed {} characters", PASSWORD_MAX_LENGTH)));
+    }
+    if !password.chars().any(char::is_alphabetic) || password.chars().all(char::is_alphabetic) {
+        return Err(AppError::Validation(
+            "Password must contain at least one letter and one number or symbol".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
 // Password hashing functions
 pub fn hash_password(password: &str) -> AppResult<String> {
     if password.is_empty() {
@@ -55,4 +77,14 @@ mod tests {
         assert!(verify_password(password, &hash).unwrap());
         assert!(!verify_password("wrong_password", &hash).unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_password_policy() {
+        assert!(validate_password("secret12").is_ok());
+        assert!(validate_password("correct horse").is_ok());
+        assert!(validate_password("short1").is_err());
+        assert!(validate_password("onlyletters").is_err());
+        assert!(validate_password("1234567890").is_err());
+        assert!(validate_password(&format!("a1{}", "x".repeat(PASSWORD_MAX_LENGTH))).is_err());
+    }
+}
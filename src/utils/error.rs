@@ -1,11 +1,13 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 
 use serde_json::json;
 use thiserror::Error;
+use crate::models::order::OrderStatus;
+use crate::models::user::UserStatus;
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -38,10 +40,22 @@ pub enum AppError {
 
     #[error("JWT error: {0}")]
     Jwt(#[from] jsonwebtoken::errors::Error),
+
+    #[error("Too many requests: {message}")]
+    TooManyRequests { message: String, retry_after_seconds: u64 },
+
+    #[error("Login locked for {retry_after_seconds}s after repeated failures")]
+    LoginLocked { retry_after_seconds: u64 },
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match self {
+            AppError::TooManyRequests { retry_after_seconds, .. } => Some(retry_after_seconds),
+            AppError::LoginLocked { retry_after_seconds } => Some(retry_after_seconds),
+            _ => None,
+        };
+
         let (status, error_message, error_code) = match self {
             AppError::Database(ref e) => {
                 tracing::error!("Database error: {:?}", e);
@@ -88,6 +102,14 @@ impl IntoResponse for AppError {
                     "JWT_ERROR",
                 )
             }
+            AppError::TooManyRequests { ref message, .. } => {
+                (StatusCode::TOO_MANY_REQUESTS, message.clone(), "TOO_MANY_REQUESTS")
+            }
+            AppError::LoginLocked { .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many failed login attempts, please try again later".to_string(),
+                "LOGIN_LOCKED",
+            ),
         };
 
         let body = Json(json!({
@@ -97,7 +119,11 @@ impl IntoResponse for AppError {
             }
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(seconds) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
     }
 }
 
@@ -118,6 +144,22 @@ impl AppError {
         AppError::NotFound("Category not found".to_string())
     }
 
+    pub fn cart_item_not_found() -> Self {
+        AppError::NotFound("Cart item not found".to_string())
+    }
+
+    pub fn order_not_found() -> Self {
+        AppError::NotFound("Order not found".to_string())
+    }
+
+    pub fn invalid_order_transition(from: OrderStatus, to: OrderStatus) -> Self {
+        AppError::Conflict(format!("Cannot change order status from {} to {}", from.as_str(), to.as_str()))
+    }
+
+    pub fn variant_not_found() -> Self {
+        AppError::NotFound("Variant not found".to_string())
+    }
+
     pub fn invalid_credentials() -> Self {
         AppError::Authentication("Invalid email or password".to_string())
     }
@@ -126,6 +168,22 @@ impl AppError {
         AppError::Authentication("Invalid or expired token".to_string())
     }
 
+    pub fn token_revoked() -> Self {
+        AppError::Authentication("Token has been revoked".to_string())
+    }
+
+    pub fn impersonation_forbidden() -> Self {
+        AppError::Authorization("Not allowed while impersonating a user".to_string())
+    }
+
+    /// Error for a user whose status is not active
+    pub fn account_inactive(status: UserStatus) -> Self {
+        match status {
+            UserStatus::Deleted => AppError::Authentication("Account has been deleted".to_string()),
+            _ => AppError::Authorization("Account is suspended".to_string()),
+        }
+    }
+
     pub fn insufficient_permissions() -> Self {
         AppError::Authorization("Insufficient permissions".to_string())
     }
@@ -141,4 +199,27 @@ impl AppError {
     pub fn category_name_exists() -> Self {
         AppError::Conflict("Category name already exists".to_string())
     }
+
+    pub fn insufficient_stock() -> Self {
+        AppError::Conflict("Insufficient stock".to_string())
+    }
+
+    pub fn sku_already_exists() -> Self {
+        AppError::Conflict("SKU already exists".to_string())
+    }
+
+    pub fn promotion_not_found() -> Self {
+        AppError::NotFound("Promotion not found".to_string())
+    }
+
+    pub fn too_many_requests(message: &str, retry_after_seconds: u64) -> Self {
+        AppError::TooManyRequests {
+            message: message.to_string(),
+            retry_after_seconds,
+        }
+    }
+
+    pub fn review_not_found() -> Self {
+        AppError::NotFound("Review not found".to_string())
+    }
 }
\ No newline at end of file
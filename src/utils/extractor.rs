@@ -1,10 +1,14 @@
 use axum::{
     async_trait,
-    extract::{FromRequestParts, Path},
-    http::request::Parts,
+    extract::{ConnectInfo, FromRequestParts, Path},
+    http::{header::USER_AGENT, request::Parts},
 };
 use crate::utils::error::AppError;
 use crate::middleware::auth::AuthUser;
+use crate::models::auth::ClientInfo;
+use dotenvy::dotenv;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 use uuid::Uuid;
 
 
@@ -57,4 +61,90 @@ where
         tracing::debug!("Successfully parsed UUID: {}", uuid);
         Ok(UuidPath(uuid))
     }
-}
\ No newline at end of file
+}
+
+/// Proxies whose X-Forwarded-For entries are believed, from TRUSTED_PROXIES (comma separated IPs)
+fn trusted_proxies() -> Vec<IpAddr> {
+    dotenv().ok();
+    std::env::var("TRUSTED_PROXIES")
+        .map(|proxies| proxies.split(',').filter_map(|ip| ip.trim().parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// The peer address, unless it is a trusted proxy: then the rightmost X-Forwarded-For hop that
+/// no trusted proxy added. Hops further left are whatever the client chose to send.
+fn client_ip(peer: Option<IpAddr>, forwarded_for: Option<&str>, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let mut ip = peer?;
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        if !trusted.contains(&ip) {
+            break;
+        }
+        match hop.trim().parse() {
+            Ok(hop) => ip = hop,
+            Err(_) => break,
+        }
+    }
+    Some(ip)
+}
+
+/// Extractor for the caller's user agent and IP (see `client_ip` for proxies)
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let forwarded_for = header("x-forwarded-for");
+
+        Ok(ClientInfo {
+            user_agent: header(USER_AGENT.as_str()).map(|ua| ua.chars().take(512).collect()),
+            ip_address: client_ip(peer_ip, forwarded_for.as_deref(), &trusted_proxies()).map(|ip| ip.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_without_trusted_proxies() {
+        let client = client_ip(Some(ip("203.0.113.7")), Some("198.51.100.1"), &[]);
+        assert_eq!(client, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn trusted_proxies_give_the_rightmost_untrusted_hop() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        // The client made up the first entry; the proxies appended the rest
+        let client = client_ip(Some(ip("10.0.0.2")), Some("198.51.100.1, 203.0.113.7, 10.0.0.1"), &trusted);
+        assert_eq!(client, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn unparsable_hops_stop_at_the_last_trusted_proxy() {
+        let trusted = [ip("10.0.0.1")];
+        assert_eq!(client_ip(Some(ip("10.0.0.1")), Some("not-an-ip"), &trusted), Some(ip("10.0.0.1")));
+        assert_eq!(client_ip(Some(ip("10.0.0.1")), None, &trusted), Some(ip("10.0.0.1")));
+        assert_eq!(client_ip(None, Some("203.0.113.7"), &trusted), None);
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{anyhow, bail, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, KeyAlgorithm, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair};
+
+const DER_SEQUENCE: u8 = 0x30;
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OID: u8 = 0x06;
+
+// Algorithm OIDs in SubjectPublicKeyInfo: rsaEncryption (1.2.840.113549.1.1.1) and Ed25519 (1.3.101.112)
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+
+/// A key loaded from PEM: its public JWK, and an encoding key if the PEM held the private half
+pub struct PemKey {
+    pub jwk: Jwk,
+    pub decoding: DecodingKey,
+    pub encoding: Option<EncodingKey>,
+}
+
+/// Load an RS256 or EdDSA key from PEM. Private keys (PKCS#8, or PKCS#1 for RSA) can sign;
+/// public keys (SPKI, or PKCS#1 for RSA) are accepted for verification only.
+pub fn load_pem_key(kid: &str, algorithm: Algorithm, pem_bytes: &[u8]) -> Result<PemKey> {
+    let pem = pem::parse(pem_bytes)?;
+    let der = pem.contents();
+
+    let (params, encoding) = match (algorithm, pem.tag()) {
+        (Algorithm::RS256, "PRIVATE KEY") => {
+            let pair = RsaKeyPair::from_pkcs8(der).map_err(|e| anyhow!("Invalid RSA key: {}", e))?;
+            (rsa_params(&pair), Some(EncodingKey::from_rsa_pem(pem_bytes)?))
+        }
+        (Algorithm::RS256, "RSA PRIVATE KEY") => {
+            let pair = RsaKeyPair::from_der(der).map_err(|e| anyhow!("Invalid RSA key: {}", e))?;
+            (rsa_params(&pair), Some(EncodingKey::from_rsa_pem(pem_bytes)?))
+        }
+        (Algorithm::RS256, "RSA PUBLIC KEY") => (rsa_public_params(der)?, None),
+        (Algorithm::RS256, "PUBLIC KEY") => (rsa_public_params(spki_key(der, OID_RSA_ENCRYPTION)?)?, None),
+        (Algorithm::EdDSA, "PRIVATE KEY") => {
+            let pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der)
+                .map_err(|e| anyhow!("Invalid Ed25519 key: {}", e))?;
+            (ed25519_params(pair.public_key().as_ref()), Some(EncodingKey::from_ed_pem(pem_bytes)?))
+        }
+        (Algorithm::EdDSA, "PUBLIC KEY") => (ed25519_params(spki_key(der, OID_ED25519)?), None),
+        (algorithm, tag) => bail!("Unsupported {} key for {:?}", tag, algorithm),
+    };
+
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(match algorithm {
+                Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                _ => KeyAlgorithm::RS256,
+            }),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: params,
+    };
+    let decoding = DecodingKey::from_jwk(&jwk)?;
+
+    Ok(PemKey { jwk, decoding, encoding })
+}
+
+fn rsa_params(pair: &RsaKeyPair) -> AlgorithmParameters {
+    let components = PublicKeyComponents::<Vec<u8>>::from(pair.public());
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(components.n),
+        e: URL_SAFE_NO_PAD.encode(components.e),
+    })
+}
+
+/// PKCS#1 RSAPublicKey: SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+fn rsa_public_params(der: &[u8]) -> Result<AlgorithmParameters> {
+    let (key, _) = der_element(der, DER_SEQUENCE)?;
+    let (n, rest) = der_element(key, DER_INTEGER)?;
+    let (e, _) = der_element(rest, DER_INTEGER)?;
+
+    Ok(AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(strip_leading_zeros(n)),
+        e: URL_SAFE_NO_PAD.encode(strip_leading_zeros(e)),
+    }))
+}
+
+fn ed25519_params(public_key: &[u8]) -> AlgorithmParameters {
+    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: URL_SAFE_NO_PAD.encode(public_key),
+    })
+}
+
+/// SubjectPublicKeyInfo: SEQUENCE { algorithm SEQUENCE { OID, .. }, subjectPublicKey BIT STRING }.
+/// Fails unless the algorithm OID is `expected_oid`, so a key of the wrong type is never loaded.
+fn spki_key<'a>(der: &'a [u8], expected_oid: &[u8]) -> Result<&'a [u8]> {
+    let (info, _) = der_element(der, DER_SEQUENCE)?;
+    let (algorithm, rest) = der_element(info, DER_SEQUENCE)?;
+    let (oid, _) = der_element(algorithm, DER_OID)?;
+    if oid != expected_oid {
+        bail!("Public key is not of the configured algorithm");
+    }
+    let (bits, _) = der_element(rest, DER_BIT_STRING)?;
+
+    // First byte counts unused bits, always 0 for keys
+    match bits.split_first() {
+        Some((0, key)) => Ok(key),
+        _ => bail!("Malformed public key"),
+    }
+}
+
+/// Read one DER element of the expected tag; returns its contents and the remaining input
+fn der_element(input: &[u8], tag: u8) -> Result<(&[u8], &[u8])> {
+    let malformed = || anyhow!("Malformed DER key");
+    let (&found, rest) = input.split_first().ok_or_else(malformed)?;
+    if found != tag {
+        return Err(malformed());
+    }
+
+    let (&first, rest) = rest.split_first().ok_or_else(malformed)?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(malformed());
+        }
+        let len = rest[..count].iter().fold(0usize, |len, b| (len << 8) | *b as usize);
+        (len, &rest[count..])
+    };
+
+    if rest.len() < len {
+        return Err(malformed());
+    }
+    Ok(rest.split_at(len))
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+
+    fn ed25519_pems() -> (String, String) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+
+        // SPKI prefix for Ed25519: SEQUENCE { SEQUENCE { OID 1.3.101.112 }, BIT STRING }
+        let mut spki = vec![0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+        spki.extend_from_slice(pair.public_key().as_ref());
+
+        (
+            pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref())),
+            pem::encode(&pem::Pem::new("PUBLIC KEY", spki)),
+        )
+    }
+
+    fn rsa_pem(name: &str) -> Vec<u8> {
+        let path = format!("{}/tests/fixtures/jwt/{}.pem", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_private_and_public_pem_give_same_jwk() {
+        let (private_pem, public_pem) = ed25519_pems();
+
+        let private = load_pem_key("k1", Algorithm::EdDSA, private_pem.as_bytes()).unwrap();
+        let public = load_pem_key("k1", Algorithm::EdDSA, public_pem.as_bytes()).unwrap();
+
+        assert!(private.encoding.is_some());
+        assert!(public.encoding.is_none());
+        assert_eq!(private.jwk, public.jwk);
+        assert_eq!(private.jwk.common.key_id.as_deref(), Some("k1"));
+    }
+
+    #[test]
+    fn test_rejects_mismatched_algorithm() {
+        let (private_pem, public_pem) = ed25519_pems();
+        assert!(load_pem_key("k1", Algorithm::RS256, private_pem.as_bytes()).is_err());
+        assert!(load_pem_key("k1", Algorithm::RS256, public_pem.as_bytes()).is_err());
+
+        assert!(load_pem_key("k1", Algorithm::EdDSA, &rsa_pem("rsa_a_public")).is_err());
+    }
+
+    #[test]
+    fn test_rsa_pems_give_same_jwk() {
+        let load = |name| load_pem_key("k1", Algorithm::RS256, &rsa_pem(name)).unwrap();
+        let (pkcs8, pkcs1) = (load("rsa_a_pkcs8"), load("rsa_a_pkcs1"));
+        let (spki, public_pkcs1) = (load("rsa_a_public"), load("rsa_a_public_pkcs1"));
+
+        assert!(pkcs8.encoding.is_some() && pkcs1.encoding.is_some());
+        assert!(spki.encoding.is_none() && public_pkcs1.encoding.is_none());
+        assert_eq!(pkcs8.jwk, pkcs1.jwk);
+        assert_eq!(pkcs8.jwk, spki.jwk);
+        assert_eq!(pkcs8.jwk, public_pkcs1.jwk);
+    }
+
+    #[test]
+    fn test_der_element_long_form_length() {
+        let mut der = vec![DER_INTEGER, 0x81, 0x80];
+        der.extend(std::iter::repeat_n(1u8, 0x80));
+        let (contents, rest) = der_element(&der, DER_INTEGER).unwrap();
+        assert_eq!(contents.len(), 0x80);
+        assert!(rest.is_empty());
+    }
+}
@@ -1,40 +1,139 @@
-use anyhow::{anyhow,Result};
-use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use anyhow::{anyhow, bail, Context, Result};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use std::collections::HashMap;
 use uuid::Uuid;
 use dotenvy::dotenv;
 use crate::{models::user::UserRole, utils::error::{AppError, AppResult}};
 use chrono::{Utc,Duration};
-use crate::models::auth::Claims;
+use crate::models::auth::{Actor, Claims};
+use crate::utils::jwk::load_pem_key;
+use crate::utils::token::{SplitToken, TokenHasher};
 
 
 // JWT configuration
 const ACCESS_TOKEN_DURATION: i64 = 30; // 30 minutes
 const REFRESH_TOKEN_DURATION: i64 = 7; // 7 days
-const JWT_SECRET: &str = "your-secret-key-change-this-in-production";
+const MAX_SESSIONS_PER_USER: i64 = 10;
+const PASSWORD_RESET_DURATION: i64 = 60; // 60 minutes
+const EMAIL_VERIFICATION_DURATION: i64 = 24; // 24 hours
+const EMAIL_VERIFICATION_RESEND_INTERVAL: i64 = 60; // 60 seconds
+const MFA_CHALLENGE_DURATION: i64 = 5; // 5 minutes
+const IMPERSONATION_DURATION: i64 = 15; // 15 minutes
+const JWT_KEYS_DIR: &str = "keys";
 
+struct VerifyingKey {
+    algorithm: Algorithm,
+    decoding: DecodingKey,
+}
+
+/// Signing and verification keys. One key signs new tokens (its `kid` goes in the header);
+/// every loaded key, including retired ones, is accepted for verification.
 pub struct JwtKeys {
+    algorithm: Algorithm,
+    signing_kid: Option<String>,
     encoding: EncodingKey,
-    decoding: DecodingKey,
+    verifying: HashMap<Option<String>, VerifyingKey>,
+    jwks: JwkSet,
 }
 
 impl JwtKeys {
+    /// Single HS256 shared secret; tokens carry no `kid` and nothing is published in the JWKS
     pub fn new(secret: &str) -> Self {
+        let verifying = VerifyingKey {
+            algorithm: Algorithm::HS256,
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        };
+
         Self {
+            algorithm: Algorithm::HS256,
+            signing_kid: None,
             encoding: EncodingKey::from_secret(secret.as_bytes()),
-            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            verifying: HashMap::from([(None, verifying)]),
+            jwks: JwkSet { keys: Vec::new() },
         }
     }
 
-    pub fn from_env() -> Self {
+    /// RS256/EdDSA keys as `(kid, pem)` pairs; `active_kid` must name a private key
+    pub fn from_pem_keys(algorithm: Algorithm, active_kid: &str, pems: &[(String, Vec<u8>)]) -> Result<Self> {
+        if !matches!(algorithm, Algorithm::RS256 | Algorithm::EdDSA) {
+            bail!("Unsupported JWT algorithm {:?}", algorithm);
+        }
+
+        let mut encoding = None;
+        let mut verifying = HashMap::new();
+        let mut jwks = JwkSet { keys: Vec::new() };
+
+        for (kid, pem) in pems {
+            let key = load_pem_key(kid, algorithm, pem).with_context(|| format!("JWT key '{}'", kid))?;
+            if kid == active_kid {
+                encoding = key.encoding;
+            }
+            verifying.insert(Some(kid.clone()), VerifyingKey { algorithm, decoding: key.decoding });
+            jwks.keys.push(key.jwk);
+        }
+
+        let encoding = encoding.ok_or_else(|| anyhow!("Active JWT key '{}' is missing or not a private key", active_kid))?;
+
+        Ok(Self {
+            algorithm,
+            signing_kid: Some(active_kid.to_string()),
+            encoding,
+            verifying,
+            jwks,
+        })
+    }
+
+    /// `JWT_ALGORITHM` selects HS256 (default, `JWT_SECRET` is required) or RS256/EdDSA, in which
+    /// case every `<kid>.pem` in `JWT_KEYS_DIR` is loaded and `JWT_ACTIVE_KID` picks the signer
+    pub fn from_env() -> Result<Self> {
         dotenv().ok();
-        let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| JWT_SECRET.to_string());
-        Self::new(&secret)
+        let algorithm = std::env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string());
+
+        if algorithm == "HS256" {
+            let secret = std::env::var("JWT_SECRET").unwrap_or_default();
+            if secret.trim().is_empty() {
+                bail!("JWT_SECRET must be set when JWT_ALGORITHM is HS256");
+            }
+            return Ok(Self::new(&secret));
+        }
+
+        let algorithm: Algorithm = algorithm.parse().map_err(|_| anyhow!("Unknown JWT_ALGORITHM '{}'", algorithm))?;
+        let dir = std::env::var("JWT_KEYS_DIR").unwrap_or_else(|_| JWT_KEYS_DIR.to_string());
+        let active_kid = std::env::var("JWT_ACTIVE_KID").context("JWT_ACTIVE_KID must be set")?;
+
+        let mut pems = Vec::new();
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("Cannot read JWT_KEYS_DIR '{}'", dir))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "pem")
+                && let Some(kid) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                pems.push((kid.to_string(), std::fs::read(&path)?));
+            }
+        }
+        pems.sort_by(|a, b| a.0.cmp(&b.0));
+
+        tracing::info!("Loaded {} JWT key(s) from {}, signing with '{}'", pems.len(), dir, active_kid);
+        Self::from_pem_keys(algorithm, &active_kid, &pems)
+    }
+
+    /// Public keys for `/.well-known/jwks.json`
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
     }
 }
 
 
 // JWT token functions
-pub fn create_access_token(user_id: Uuid, username: &String, role: UserRole, keys: &JwtKeys) -> Result<String> {
+pub fn create_access_token(
+    user_id: Uuid,
+    username: &String,
+    role: UserRole,
+    session_id: Option<Uuid>,
+    mfa: bool,
+    permissions: Vec<String>,
+    keys: &JwtKeys,
+) -> Result<String> {
     let now = Utc::now();
     let access_token_duration = std::env::var("ACCESS_TOKEN_DURATION").unwrap_or_else(|_| ACCESS_TOKEN_DURATION.to_string());
     let expires_at = now + Duration::minutes(access_token_duration.parse::<i64>().unwrap());
@@ -43,11 +142,52 @@ pub fn create_access_token(user_id: Uuid, username: &String, role: UserRole, key
         sub: user_id.to_string(),
         username:username.to_string(),
         role:role,
+        sid: session_id,
+        jti: Uuid::new_v4(),
+        mfa,
+        perms: permissions,
+        act: None,
         exp: expires_at.timestamp() as usize,
         iat: now.timestamp() as usize,
     };
 
-    let encoded = encode(&Header::default(), &claims, &keys.encoding)
+    sign_claims(&claims, keys)
+}
+
+/// Access token letting `actor` act as the user. It belongs to no session, so it cannot be
+/// refreshed; returns the token and its `jti`, which ends the impersonation when revoked.
+pub fn create_impersonation_token(
+    user_id: Uuid,
+    username: &str,
+    role: UserRole,
+    permissions: Vec<String>,
+    actor: Actor,
+    keys: &JwtKeys,
+) -> Result<(String, Uuid)> {
+    let now = Utc::now();
+    let expires_at = now + Duration::minutes(get_impersonation_duration());
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        username: username.to_string(),
+        role,
+        sid: None,
+        jti: Uuid::new_v4(),
+        mfa: false,
+        perms: permissions,
+        act: Some(actor),
+        exp: expires_at.timestamp() as usize,
+        iat: now.timestamp() as usize,
+    };
+
+    Ok((sign_claims(&claims, keys)?, claims.jti))
+}
+
+fn sign_claims(claims: &Claims, keys: &JwtKeys) -> Result<String> {
+    let mut header = Header::new(keys.algorithm);
+    header.kid = keys.signing_kid.clone();
+
+    let encoded = encode(&header, claims, &keys.encoding)
         .map_err(|e| anyhow!("Failed to create access token: {}", e)).unwrap();
 
     Ok(encoded)
@@ -55,18 +195,27 @@ pub fn create_access_token(user_id: Uuid, username: &String, role: UserRole, key
 
 
 pub fn verify_access_token(token: &str, keys: &JwtKeys) -> AppResult<Claims> {
-    let mut validation = Validation::new(Algorithm::HS256);
+    // Pick the key named by the header's kid; it must also match the algorithm it was loaded for
+    let header = decode_header(token).map_err(|_| AppError::invalid_token())?;
+    let key = keys
+        .verifying
+        .get(&header.kid)
+        .filter(|key| key.algorithm == header.alg)
+        .ok_or_else(AppError::invalid_token)?;
+
+    let mut validation = Validation::new(key.algorithm);
     validation.validate_exp = true;
 
-    let token_data = decode::<Claims>(token, &keys.decoding, &validation)
+    let token_data = decode::<Claims>(token, &key.decoding, &validation)
         .map_err(|_| AppError::invalid_token())?;
 
     Ok(token_data.claims)
 }
 
 
-pub fn generate_refresh_token() -> String {
-    Uuid::new_v4().to_string()
+/// New refresh token: the string for the client and the selector/hash to store
+pub fn generate_refresh_token(hasher: &TokenHasher) -> (String, SplitToken) {
+    hasher.generate_split_token()
 }
 
 pub fn get_access_token_duration() -> i64 {
@@ -81,13 +230,81 @@ pub fn get_refresh_token_duration() -> i64 {
     refresh_token_duration.parse::<i64>().unwrap() // Return in seconds
 }
 
+/// Active sessions a user may hold before the oldest is evicted
+pub fn get_max_sessions_per_user() -> i64 {
+    dotenv().ok();
+    std::env::var("MAX_SESSIONS_PER_USER")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|max| *max > 0)
+        .unwrap_or(MAX_SESSIONS_PER_USER)
+}
+
+/// Minutes a password reset link stays valid
+pub fn get_password_reset_duration() -> i64 {
+    dotenv().ok();
+    std::env::var("PASSWORD_RESET_DURATION")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(PASSWORD_RESET_DURATION)
+}
+
+/// Hours an email verification link stays valid
+pub fn get_email_verification_duration() -> i64 {
+    dotenv().ok();
+    std::env::var("EMAIL_VERIFICATION_DURATION")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(EMAIL_VERIFICATION_DURATION)
+}
+
+/// Seconds a user must wait between verification emails
+pub fn get_email_verification_resend_interval() -> i64 {
+    dotenv().ok();
+    std::env::var("EMAIL_VERIFICATION_RESEND_INTERVAL")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|seconds| *seconds >= 0)
+        .unwrap_or(EMAIL_VERIFICATION_RESEND_INTERVAL)
+}
+
+/// Minutes a login has to complete its second factor
+pub fn get_mfa_challenge_duration() -> i64 {
+    dotenv().ok();
+    std::env::var("MFA_CHALLENGE_DURATION")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(MFA_CHALLENGE_DURATION)
+}
+
+/// Minutes an impersonation token lasts; never longer than an access token, which is as long
+/// as revocations are kept
+pub fn get_impersonation_duration() -> i64 {
+    dotenv().ok();
+    std::env::var("IMPERSONATION_DURATION")
+        .ok()
+        .and_then(|s| s.parse::<i64>().ok())
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(IMPERSONATION_DURATION)
+        .min(get_access_token_duration() / 60)
+}
+
+// Credentials accepted in the Authorization header
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuthToken<'a> {
+    Bearer(&'a str), // Access token (JWT)
+    ApiKey(&'a str),
+}
+
 // Extract token from Authorization header
-pub fn extract_token_from_header(auth_header: &str) -> Option<&str> {
-    if auth_header.starts_with("Bearer ") {
-        Some(&auth_header[7..])
-    } else {
-        None
+pub fn extract_token_from_header(auth_header: &str) -> Option<AuthToken<'_>> {
+    if let Some(token) = auth_header.strip_prefix("Bearer ") {
+        return Some(AuthToken::Bearer(token));
     }
+    auth_header.strip_prefix("ApiKey ").map(AuthToken::ApiKey)
 }
 
 #[cfg(test)]
@@ -100,19 +317,138 @@ mod tests {
         let user_id = Uuid::new_v4();
         let username = "testuser".to_string();
         let role = UserRole::User;
+        let session_id = Uuid::new_v4();
+
+        let permissions = vec!["orders:read".to_string()];
 
-        let token = create_access_token(user_id, &username, role.clone(), &keys).unwrap();
+        let token = create_access_token(user_id, &username, role.clone(), Some(session_id), true, permissions, &keys).unwrap();
         let claims = verify_access_token(&token, &keys).unwrap();
 
         assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.sid, Some(session_id));
+        assert!(claims.mfa);
+        assert_eq!(claims.perms, vec!["orders:read".to_string()]);
         assert_eq!(claims.username, username);
         assert!(matches!(claims.role, UserRole::User));
+        assert!(claims.act.is_none());
+    }
+
+    #[test]
+    fn test_impersonation_token() {
+        let keys = JwtKeys::new("test_secret");
+        let user_id = Uuid::new_v4();
+        let actor = Actor { sub: Uuid::new_v4(), username: "support".to_string() };
+        let actor_id = actor.sub;
+
+        let (token, jti) = create_impersonation_token(user_id, "customer", UserRole::User, Vec::new(), actor, &keys).unwrap();
+        let claims = verify_access_token(&token, &keys).unwrap();
+
+        assert_eq!(claims.sub, user_id.to_string());
+        assert_eq!(claims.jti, jti);
+        assert_eq!(claims.sid, None);
+        assert!(!claims.mfa);
+        assert_eq!(claims.act.map(|act| act.sub), Some(actor_id));
+    }
+
+    #[test]
+    fn test_kid_rotation() {
+        use ring::rand::SystemRandom;
+        use ring::signature::Ed25519KeyPair;
+
+        let pem = || {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref())).into_bytes()
+        };
+        let old = ("2024-01".to_string(), pem());
+        let new = ("2025-01".to_string(), pem());
+
+        let before = JwtKeys::from_pem_keys(Algorithm::EdDSA, "2024-01", std::slice::from_ref(&old)).unwrap();
+        let after = JwtKeys::from_pem_keys(Algorithm::EdDSA, "2025-01", &[old, new]).unwrap();
+        let user_id = Uuid::new_v4();
+        let username = "testuser".to_string();
+
+        // Tokens signed before the rotation still verify; new ones carry the new kid
+        let old_token = create_access_token(user_id, &username, UserRole::User, None, false, Vec::new(), &before).unwrap();
+        assert!(verify_access_token(&old_token, &after).is_ok());
+
+        let new_token = create_access_token(user_id, &username, UserRole::User, None, false, Vec::new(), &after).unwrap();
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("2025-01"));
+        assert!(verify_access_token(&new_token, &before).is_err());
+        assert_eq!(after.jwks().keys.len(), 2);
+
+        // A shared-secret token is not accepted by asymmetric keys
+        let hs_token = create_access_token(user_id, &username, UserRole::User, None, false, Vec::new(), &JwtKeys::new("s")).unwrap();
+        assert!(verify_access_token(&hs_token, &after).is_err());
+    }
+
+    fn rsa_pem(name: &str) -> Vec<u8> {
+        let path = format!("{}/tests/fixtures/jwt/{}.pem", env!("CARGO_MANIFEST_DIR"), name);
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_rs256_pkcs8_and_pkcs1_keys() {
+        let user_id = Uuid::new_v4();
+        let username = "testuser".to_string();
+
+        for private in ["rsa_a_pkcs8", "rsa_a_pkcs1"] {
+            let signer = JwtKeys::from_pem_keys(Algorithm::RS256, "a", &[("a".to_string(), rsa_pem(private))]).unwrap();
+            let token = create_access_token(user_id, &username, UserRole::User, None, false, Vec::new(), &signer).unwrap();
+
+            let header = decode_header(&token).unwrap();
+            assert_eq!(header.alg, Algorithm::RS256);
+            assert_eq!(header.kid.as_deref(), Some("a"));
+            assert_eq!(verify_access_token(&token, &signer).unwrap().sub, user_id.to_string());
+
+            // Another instance holding only the public half (SPKI or PKCS#1) verifies it too
+            for public in ["rsa_a_public", "rsa_a_public_pkcs1"] {
+                let pems = [("a".to_string(), rsa_pem(public)), ("b".to_string(), rsa_pem("rsa_b_pkcs8"))];
+                let verifier = JwtKeys::from_pem_keys(Algorithm::RS256, "b", &pems).unwrap();
+                assert!(verify_access_token(&token, &verifier).is_ok());
+            }
+        }
+
+        // A public key cannot be the signer
+        let public_only = [("a".to_string(), rsa_pem("rsa_a_public"))];
+        assert!(JwtKeys::from_pem_keys(Algorithm::RS256, "a", &public_only).is_err());
+    }
+
+    #[test]
+    fn test_rs256_kid_rotation() {
+        let key_a = ("a".to_string(), rsa_pem("rsa_a_pkcs8"));
+        let key_b = ("b".to_string(), rsa_pem("rsa_b_pkcs8"));
+        let before = JwtKeys::from_pem_keys(Algorithm::RS256, "a", std::slice::from_ref(&key_a)).unwrap();
+        let during = JwtKeys::from_pem_keys(Algorithm::RS256, "b", &[key_a, key_b.clone()]).unwrap();
+        let after = JwtKeys::from_pem_keys(Algorithm::RS256, "b", &[key_b]).unwrap();
+        let user_id = Uuid::new_v4();
+        let username = "testuser".to_string();
+
+        let old_token = create_access_token(user_id, &username, UserRole::User, None, false, Vec::new(), &before).unwrap();
+        let new_token = create_access_token(user_id, &username, UserRole::User, None, false, Vec::new(), &during).unwrap();
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("b"));
+        assert_eq!(during.jwks().keys.len(), 2);
+
+        // Both verify while the old key is still published; once it is retired, its tokens stop working
+        assert!(verify_access_token(&old_token, &during).is_ok());
+        assert!(verify_access_token(&new_token, &during).is_ok());
+        assert!(verify_access_token(&old_token, &after).is_err());
+        assert!(verify_access_token(&new_token, &after).is_ok());
+
+        // A token whose kid names another key does not verify
+        let mut header = decode_header(&old_token).unwrap();
+        header.kid = Some("b".to_string());
+        let claims = verify_access_token(&old_token, &before).unwrap();
+        let forged = encode(&header, &claims, &before.encoding).unwrap();
+        assert!(verify_access_token(&forged, &after).is_err());
     }
 
     #[test]
     fn test_extract_token_from_header() {
         let header = "Bearer abc123xyz";
-        assert_eq!(extract_token_from_header(header), Some("abc123xyz"));
+        assert_eq!(extract_token_from_header(header), Some(AuthToken::Bearer("abc123xyz")));
+
+        let api_key_header = "ApiKey sk_abc.xyz";
+        assert_eq!(extract_token_from_header(api_key_header), Some(AuthToken::ApiKey("sk_abc.xyz")));
 
         let invalid_header = "Basic abc123xyz";
         assert_eq!(extract_token_from_header(invalid_header), None);
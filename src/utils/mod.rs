@@ -1,4 +1,8 @@
 pub mod auth;
 pub mod error;
 pub mod jwt;
+pub mod jwk;
 pub mod extractor;
+pub mod token;
+pub mod revocation;
+pub mod totp;
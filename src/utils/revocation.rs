@@ -0,0 +1,146 @@
+use crate::db::db_con::DatabasePool;
+use crate::db::revocationq::*;
+use crate::models::auth::Claims;
+use crate::utils::jwt::get_access_token_duration;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+#[derive(Default)]
+struct Entries {
+    revoked: HashMap<Uuid, i64>,    // jti or sid -> unix time the entry can be dropped
+    watermarks: HashMap<Uuid, i64>, // user id -> tokens issued up to and including this second are invalid
+}
+
+/// In-memory copy of the revocation table and per-user watermarks, checked on every request.
+/// Local revocations apply immediately; other instances pick them up on the next refresh.
+#[derive(Default)]
+pub struct RevocationList {
+    entries: RwLock<Entries>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_revoked(&self, user_id: Uuid, claims: &Claims) -> bool {
+        let entries = self.entries.read().unwrap();
+
+        entries.revoked.contains_key(&claims.jti)
+            || claims.sid.is_some_and(|sid| entries.revoked.contains_key(&sid))
+            || entries
+                .watermarks
+                .get(&user_id)
+                .is_some_and(|valid_after| (claims.iat as i64) <= *valid_after)
+    }
+
+    /// Revoke one access token (by jti) or every access token of a session (by sid)
+    pub async fn revoke(&self, pool: &DatabasePool, id: Uuid, user_id: Uuid) -> Result<()> {
+        // No token covered by this id can outlive a full access token lifetime from now
+        let expires_at = OffsetDateTime::now_utc() + time::Duration::seconds(get_access_token_duration());
+        insert_revoked_token(pool, id, user_id, expires_at).await?;
+
+        let mut entries = self.entries.write().unwrap();
+        entries.revoked.insert(id, expires_at.unix_timestamp());
+        Ok(())
+    }
+
+    /// Reject every access token issued to the user until now. `iat` only has whole seconds,
+    /// so tokens issued later in the current second are rejected as well.
+    pub async fn revoke_all(&self, pool: &DatabasePool, user_id: Uuid) -> Result<()> {
+        let valid_after = set_tokens_valid_after(pool, user_id).await?;
+
+        let mut entries = self.entries.write().unwrap();
+        entries.watermarks.insert(user_id, valid_after.unix_timestamp());
+        Ok(())
+    }
+
+    /// Merge in entries written by other instances and drop the expired ones.
+    /// Revocations are never lifted, so merging cannot resurrect a token.
+    pub async fn refresh(&self, pool: &DatabasePool) -> Result<()> {
+        let now = OffsetDateTime::now_utc();
+        let oldest_live_token = now - time::Duration::seconds(get_access_token_duration());
+
+        delete_expired_revoked_tokens(pool).await?;
+        let revoked = find_revoked_tokens(pool).await?;
+        let watermarks = find_token_watermarks(pool, oldest_live_token).await?;
+
+        let mut entries = self.entries.write().unwrap();
+        for token in revoked {
+            entries.revoked.insert(token.id, token.expires_at.unix_timestamp());
+        }
+        for watermark in watermarks {
+            let valid_after = watermark.tokens_valid_after.unix_timestamp();
+            let current = entries.watermarks.entry(watermark.user_id).or_insert(valid_after);
+            *current = (*current).max(valid_after);
+        }
+
+        entries.revoked.retain(|_, expires_at| *expires_at > now.unix_timestamp());
+        entries
+            .watermarks
+            .retain(|_, valid_after| *valid_after > oldest_live_token.unix_timestamp());
+        Ok(())
+    }
+}
+
+pub fn spawn_revocation_refresher(pool: DatabasePool, list: Arc<RevocationList>, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = list.refresh(&pool).await {
+                tracing::error!("Token revocation refresh failed: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::UserRole;
+
+    fn claims(iat: i64, sid: Option<Uuid>) -> Claims {
+        Claims {
+            sub: Uuid::new_v4().to_string(),
+            username: "testuser".to_string(),
+            role: UserRole::User,
+            sid,
+            jti: Uuid::new_v4(),
+            mfa: false,
+            perms: Vec::new(),
+            act: None,
+            exp: (iat + 1800) as usize,
+            iat: iat as usize,
+        }
+    }
+
+    #[test]
+    fn test_revocation_checks() {
+        let list = RevocationList::new();
+        let user_id = Uuid::new_v4();
+        let sid = Uuid::new_v4();
+        let token = claims(1_000, Some(sid));
+        let far_future = i64::MAX;
+
+        assert!(!list.is_revoked(user_id, &token));
+
+        list.entries.write().unwrap().revoked.insert(token.jti, far_future);
+        assert!(list.is_revoked(user_id, &token));
+        assert!(!list.is_revoked(user_id, &claims(1_000, None)));
+
+        list.entries.write().unwrap().revoked.insert(sid, far_future);
+        assert!(list.is_revoked(user_id, &claims(1_000, Some(sid))));
+
+        // Issued in the watermark's second may predate the revocation, so only later ones are valid
+        list.entries.write().unwrap().watermarks.insert(user_id, 2_000);
+        assert!(list.is_revoked(user_id, &claims(1_999, None)));
+        assert!(list.is_revoked(user_id, &claims(2_000, None)));
+        assert!(!list.is_revoked(user_id, &claims(2_001, None)));
+        assert!(!list.is_revoked(Uuid::new_v4(), &claims(1_999, None)));
+    }
+}
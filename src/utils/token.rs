@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use dotenvy::dotenv;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SELECTOR_BYTES: usize = 12;
+const VERIFIER_BYTES: usize = 32;
+
+/// Secret token in the `<selector>.<verifier>` form handed to clients.
+/// Only the selector (for the indexed lookup) and a keyed hash of the verifier are stored.
+#[derive(Debug, Clone)]
+pub struct SplitToken {
+    pub selector: String,
+    pub verifier_hash: String,
+}
+
+fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Key for the hashes of stored secrets, loaded once at startup
+pub struct TokenHasher {
+    key: Vec<u8>,
+}
+
+impl TokenHasher {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// `TOKEN_HASH_SECRET` is required: without it every stored token would be hashed with a guessable key
+    pub fn from_env() -> Result<Self> {
+        dotenv().ok();
+        let secret = std::env::var("TOKEN_HASH_SECRET").unwrap_or_default();
+        if secret.trim().is_empty() {
+            return Err(anyhow!("TOKEN_HASH_SECRET must be set"));
+        }
+        Ok(Self::new(&secret))
+    }
+
+    fn mac_for(&self, verifier: &str) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any size");
+        mac.update(verifier.as_bytes());
+        mac
+    }
+
+    /// Keyed hash of a high-entropy secret, for secrets looked up by their hash (e.g. recovery codes)
+    pub fn hash_secret(&self, secret: &str) -> String {
+        hex::encode(self.mac_for(secret).finalize().into_bytes())
+    }
+
+    /// Generate a new token; returns the value for the client and what to store
+    pub fn generate_split_token(&self) -> (String, SplitToken) {
+        let selector = random_hex(SELECTOR_BYTES);
+        let verifier = random_hex(VERIFIER_BYTES);
+        let verifier_hash = self.hash_secret(&verifier);
+
+        (format!("{}.{}", selector, verifier), SplitToken { selector, verifier_hash })
+    }
+
+    /// Constant-time check of a presented verifier against the stored hash
+    pub fn verify_token_verifier(&self, verifier: &str, verifier_hash: &str) -> bool {
+        match hex::decode(verifier_hash) {
+            Ok(expected) => self.mac_for(verifier).verify_slice(&expected).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Split a presented token into its selector and verifier; `None` if malformed
+pub fn parse_split_token(token: &str) -> Option<(&str, &str)> {
+    let (selector, verifier) = token.split_once('.')?;
+    let well_formed = selector.len() == SELECTOR_BYTES * 2
+        && verifier.len() == VERIFIER_BYTES * 2
+        && selector.bytes().chain(verifier.bytes()).all(|b| b.is_ascii_hexdigit());
+
+    well_formed.then_some((selector, verifier))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_token_round_trip() {
+        let hasher = TokenHasher::new("test-secret");
+        let (token, stored) = hasher.generate_split_token();
+        let (selector, verifier) = parse_split_token(&token).unwrap();
+
+        assert_eq!(selector, stored.selector);
+        assert!(!token.contains(&stored.verifier_hash));
+        assert!(hasher.verify_token_verifier(verifier, &stored.verifier_hash));
+
+        let (_, other) = hasher.generate_split_token();
+        assert!(!hasher.verify_token_verifier(verifier, &other.verifier_hash));
+
+        // Hashes made with another key do not verify
+        assert!(!TokenHasher::new("other-secret").verify_token_verifier(verifier, &stored.verifier_hash));
+    }
+
+    #[test]
+    fn test_parse_rejects_legacy_and_malformed_tokens() {
+        assert!(parse_split_token("6f1c2d3e-0000-4000-8000-000000000000").is_none());
+        assert!(parse_split_token("abc.def").is_none());
+        assert!(parse_split_token("").is_none());
+    }
+}
@@ -0,0 +1,143 @@
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha1::Sha1;
+
+type HmacSha1 = Hmac<Sha1>;
+
+// RFC 6238 parameters every authenticator app understands
+pub const TOTP_STEP_SECONDS: i64 = 30;
+pub const TOTP_DIGITS: u32 = 6;
+const TOTP_SKEW_STEPS: i64 = 1; // Accept one step either side for clock drift
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_CHARS: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Random shared secret for a new enrollment
+pub fn generate_totp_secret() -> Vec<u8> {
+    let mut secret = vec![0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// RFC 4648 base32 without padding, the form authenticator apps expect
+pub fn base32_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+/// HOTP value (RFC 4226) for one counter
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = HmacSha1::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]]) & 0x7fff_ffff;
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// Code for the time step containing `unix_time`
+pub fn totp_code(secret: &[u8], unix_time: i64) -> String {
+    let step = unix_time.div_euclid(TOTP_STEP_SECONDS);
+    format!("{:0width$}", hotp(secret, step as u64), width = TOTP_DIGITS as usize)
+}
+
+/// Time step the code belongs to, if it is valid around `unix_time`.
+/// Callers store the step and refuse steps already used, so a code works only once.
+pub fn verify_totp_code(secret: &[u8], code: &str, unix_time: i64) -> Option<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let current = unix_time.div_euclid(TOTP_STEP_SECONDS);
+    (current - TOTP_SKEW_STEPS..=current + TOTP_SKEW_STEPS)
+        .find(|&step| step >= 0 && totp_code(secret, step * TOTP_STEP_SECONDS) == code)
+}
+
+/// `otpauth://` URI for QR codes; `account` is usually the user's email
+pub fn provisioning_uri(secret: &[u8], issuer: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        urlencoding::encode(issuer),
+        urlencoding::encode(account),
+        base32_encode(secret),
+        urlencoding::encode(issuer),
+        TOTP_DIGITS,
+        TOTP_STEP_SECONDS
+    )
+}
+
+/// One-time recovery codes in the `xxxxx-xxxxx` form shown to the user
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| {
+            let mut bytes = [0u8; RECOVERY_CODE_CHARS];
+            OsRng.fill_bytes(&mut bytes);
+            let chars: String = bytes
+                .iter()
+                .map(|b| BASE32_ALPHABET[(b & 0x1f) as usize].to_ascii_lowercase() as char)
+                .collect();
+            format!("{}-{}", &chars[..RECOVERY_CODE_CHARS / 2], &chars[RECOVERY_CODE_CHARS / 2..])
+        })
+        .collect()
+}
+
+/// Recovery codes are compared case-insensitively and without separators
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_totp_matches_rfc_6238_vectors() {
+        // SHA1 vectors from RFC 6238 appendix B, truncated to six digits
+        let secret = b"12345678901234567890";
+        assert_eq!(totp_code(secret, 59), "287082");
+        assert_eq!(totp_code(secret, 1111111109), "081804");
+        assert_eq!(totp_code(secret, 1234567890), "005924");
+        assert_eq!(base32_encode(secret), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+    }
+
+    #[test]
+    fn test_verify_totp_code_allows_one_step_of_drift() {
+        let secret = generate_totp_secret();
+        let now = 1_700_000_000;
+        let code = totp_code(&secret, now);
+
+        assert_eq!(verify_totp_code(&secret, &code, now), Some(now / TOTP_STEP_SECONDS));
+        assert!(verify_totp_code(&secret, &code, now + TOTP_STEP_SECONDS).is_some());
+        assert!(verify_totp_code(&secret, &code, now + 3 * TOTP_STEP_SECONDS).is_none());
+        assert!(verify_totp_code(&secret, "12345", now).is_none());
+    }
+
+    #[test]
+    fn test_recovery_codes_normalize() {
+        let codes = generate_recovery_codes(3);
+        assert_eq!(codes.len(), 3);
+        assert_eq!(codes[0].len(), RECOVERY_CODE_CHARS + 1);
+        assert_eq!(normalize_recovery_code(&codes[0].to_uppercase()), codes[0].replace('-', ""));
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use axum::extract::State;
+use axum::Json;
+use common::{auth_user, create_test_product, test_state};
+use sqlx::PgPool;
+use tests3::db::authq::find_user_sessions;
+use tests3::db::cartq::{add_cart_item, find_cart_lines, find_or_create_user_cart, find_user_cart};
+use tests3::db::orderq::{create_order_from_cart, find_user_orders_with_items, CheckoutOutcome};
+use tests3::db::reviewq::{create_review_db, find_reviews_by_user};
+use tests3::db::userq::{change_user_role, find_by_id};
+use tests3::db::wishlistq::{add_wishlist_item, find_wishlist};
+use tests3::models::auth::{ClientInfo, LoginRequest};
+use tests3::models::review::CreateReview;
+use tests3::models::user::{CreateUser, DeleteAccountRequest, User, UserRole, UserStatus};
+use tests3::services::auth::{login, register};
+use tests3::services::cart::cart_view;
+use tests3::services::profile::delete_account;
+use tests3::utils::error::AppError;
+use tests3::AppState;
+use uuid::Uuid;
+
+const PASSWORD: &str = "correct-horse-42";
+
+async fn register_user(state: &AppState) -> User {
+    let name = format!("user_{}", Uuid::new_v4().simple());
+    let data = CreateUser {
+        email: format!("{}@example.com", name),
+        username: name,
+        password: PASSWORD.to_string(),
+    };
+    let Json(response) = register(State(state.clone()), ClientInfo::default(), Json(data)).await.unwrap();
+    response.user
+}
+
+fn deletion(password: &str) -> Json<DeleteAccountRequest> {
+    Json(DeleteAccountRequest {
+        current_password: password.to_string(),
+    })
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn deleting_an_account_anonymises_it_and_keeps_orders_and_reviews(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    let product_id = create_test_product(&pool, "10.00", "USD", 10).await;
+
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    add_cart_item(&pool, cart.id, product_id, None, 1).await.unwrap();
+    let view = cart_view(&pool, &cart).await.unwrap();
+    let lines = find_cart_lines(&pool, cart.id).await.unwrap();
+    let order = match create_order_from_cart(&pool, user.id, &cart, &lines, &view.applied_promotions).await.unwrap() {
+        CheckoutOutcome::Placed(order) => order.order,
+        _ => panic!("checkout failed"),
+    };
+    let review = CreateReview {
+        rating: 5,
+        comment: "Does what it says".to_string(),
+    };
+    create_review_db(&pool, product_id, user.id, review, true).await.unwrap().unwrap();
+    add_wishlist_item(&pool, user.id, product_id).await.unwrap();
+
+    let wrong_password = delete_account(State(state.clone()), auth_user(&user), deletion("not-my-password")).await;
+    assert!(matches!(wrong_password, Err(AppError::Authorization(_))));
+
+    let Json(body) = delete_account(State(state.clone()), auth_user(&user), deletion(PASSWORD)).await.unwrap();
+    assert_eq!(body["message"], "Account deleted");
+
+    let deleted = find_by_id(&pool, user.id).await.unwrap().unwrap();
+    assert_eq!(deleted.status, UserStatus::Deleted);
+    assert_ne!(deleted.username, user.username);
+    assert_ne!(deleted.email, user.email);
+    assert!(deleted.email.ends_with("@deleted.invalid"));
+    assert_eq!(deleted.password_hash, "!");
+
+    // Orders and reviews stay for the shop's records
+    let orders = find_user_orders_with_items(&pool, user.id).await.unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order.id, order.id);
+    assert_eq!(find_reviews_by_user(&pool, user.id).await.unwrap().len(), 1);
+
+    // Everything else personal is gone
+    assert!(find_user_sessions(&pool, user.id).await.unwrap().is_empty());
+    assert!(find_user_cart(&pool, user.id).await.unwrap().is_none());
+    assert!(find_wishlist(&pool, user.id).await.unwrap().is_empty());
+
+    let credentials = LoginRequest {
+        email: user.email.clone(),
+        password: PASSWORD.to_string(),
+        cart_token: None,
+        device_label: None,
+    };
+    let result = login(State(state), ClientInfo::default(), Json(credentials)).await;
+    assert!(result.is_err());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn the_last_superadmin_cannot_delete_their_account(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    change_user_role(&pool, user.id, UserRole::SuperAdmin).await.unwrap();
+
+    let result = delete_account(State(state), auth_user(&user), deletion(PASSWORD)).await;
+    assert!(matches!(result, Err(AppError::Conflict(_))));
+    let unchanged = find_by_id(&pool, user.id).await.unwrap().unwrap();
+    assert_eq!(unchanged.status, UserStatus::Active);
+    assert_eq!(unchanged.email, user.email);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use common::{create_test_product, create_test_user, dec};
+use sqlx::PgPool;
+use tests3::db::cartq::{add_cart_item, find_cart_lines, find_or_create_user_cart, set_cart_currency};
+use tests3::db::currencyq::upsert_exchange_rate;
+use tests3::db::orderq::{create_order_from_cart, CheckoutOutcome};
+use tests3::db::promotionq::create_promotion_db;
+use tests3::models::promotion::{CreatePromotion, DiscountType, PromotionScope};
+use tests3::services::cart::cart_view;
+
+#[sqlx::test(migrations = "./migrations")]
+async fn mixed_currency_cart_is_priced_and_ordered_in_the_cart_currency(pool: PgPool) {
+    let laptop = create_test_product(&pool, "999.99", "USD", 5).await;
+    let console = create_test_product(&pool, "15000", "JPY", 5).await;
+    upsert_exchange_rate(&pool, "USD", "JPY", dec("150"), None).await.unwrap();
+
+    let user = create_test_user(&pool).await;
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    assert_eq!(cart.currency, "USD");
+    add_cart_item(&pool, cart.id, laptop, None, 1).await.unwrap();
+    add_cart_item(&pool, cart.id, console, None, 1).await.unwrap();
+
+    let view = cart_view(&pool, &cart).await.unwrap();
+    assert!(!view.has_issues);
+    assert_eq!(view.subtotal, dec("1099.99"));
+
+    set_cart_currency(&pool, cart.id, "JPY").await.unwrap();
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    let view = cart_view(&pool, &cart).await.unwrap();
+    assert_eq!(view.currency, "JPY");
+    assert_eq!(view.subtotal, dec("164999"));
+
+    let lines = find_cart_lines(&pool, cart.id).await.unwrap();
+    let order = match create_order_from_cart(&pool, user.id, &cart, &lines, &view.applied_promotions).await.unwrap() {
+        CheckoutOutcome::Placed(order) => order,
+        _ => panic!("checkout failed"),
+    };
+    assert_eq!(order.order.currency, "JPY");
+    assert_eq!(order.order.total, dec("164999"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn lines_without_an_exchange_rate_are_flagged(pool: PgPool) {
+    let product = create_test_product(&pool, "10.00", "EUR", 5).await;
+
+    let user = create_test_user(&pool).await;
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    add_cart_item(&pool, cart.id, product, None, 1).await.unwrap();
+
+    let view = cart_view(&pool, &cart).await.unwrap();
+    assert!(view.has_issues);
+    assert_eq!(view.subtotal, dec("0"));
+    assert_eq!(view.items[0].issue.as_deref(), Some("Price not available in USD"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn fixed_discounts_and_min_spend_are_converted(pool: PgPool) {
+    let product = create_test_product(&pool, "3000", "JPY", 5).await;
+    upsert_exchange_rate(&pool, "USD", "JPY", dec("150"), None).await.unwrap();
+    create_promotion_db(
+        &pool,
+        CreatePromotion {
+            name: "Five off fifteen".to_string(),
+            code: None,
+            discount_type: DiscountType::Fixed,
+            discount_value: "5".to_string(),
+            scope: PromotionScope::Cart,
+            product_id: None,
+            category_id: None,
+            min_spend: Some("15".to_string()),
+            currency: Some("USD".to_string()),
+            starts_at: None,
+            ends_at: None,
+            usage_limit: None,
+            per_user_limit: None,
+            active: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let user = create_test_user(&pool).await;
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    set_cart_currency(&pool, cart.id, "JPY").await.unwrap();
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+
+    // 3000 JPY clears the 15 USD (2250 JPY) minimum spend and 5 USD comes off as 750 JPY
+    add_cart_item(&pool, cart.id, product, None, 1).await.unwrap();
+    let view = cart_view(&pool, &cart).await.unwrap();
+    assert_eq!(view.applied_promotions.len(), 1);
+    assert_eq!(view.discount, dec("750"));
+    assert_eq!(view.total, dec("2250"));
+}
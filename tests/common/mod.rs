@@ -0,0 +1,86 @@
+//! Fixtures for the database-backed tests. Each `#[sqlx::test]` runs against a fresh
+//! database built from `migrations/`, so these only need DATABASE_URL to reach a server.
+#![allow(dead_code)]
+
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tests3::db::categoryq::create_category_db;
+use tests3::db::productq::create_product_db;
+use tests3::db::userq::create_user;
+use tests3::mail::log::LogMailer;
+use tests3::middleware::auth::AuthUser;
+use tests3::models::category::CreateCategory;
+use tests3::models::product::CreateProduct;
+use tests3::models::user::{CreateUser, User};
+use tests3::payments::mock::MockPaymentProvider;
+use tests3::utils::jwt::JwtKeys;
+use tests3::utils::revocation::RevocationList;
+use tests3::utils::token::TokenHasher;
+use tests3::AppState;
+use uuid::Uuid;
+
+pub const WEBHOOK_SECRET: &str = "test-webhook-secret";
+
+pub fn test_state(pool: PgPool) -> AppState {
+    AppState {
+        db_pool: pool,
+        jwt_keys: Arc::new(JwtKeys::new("test-jwt-secret")),
+        token_hasher: Arc::new(TokenHasher::new("test-token-hash-secret")),
+        revocations: Arc::new(RevocationList::new()),
+        payments: Arc::new(MockPaymentProvider::new(WEBHOOK_SECRET)),
+        mailer: Arc::new(LogMailer),
+    }
+}
+
+/// A user with a unique name and email; the password hash is not a real one
+pub async fn create_test_user(pool: &PgPool) -> User {
+    let name = format!("user_{}", Uuid::new_v4().simple());
+    let data = CreateUser {
+        email: format!("{}@example.com", name),
+        username: name,
+        password: String::new(),
+    };
+    create_user(pool, data, "not-a-hash".to_string()).await.unwrap()
+}
+
+/// What the auth middleware would inject for a plain session of `user`
+pub fn auth_user(user: &User) -> AuthUser {
+    AuthUser {
+        user_id: user.id,
+        username: user.username.clone(),
+        role: user.role.clone(),
+        session_id: None,
+        mfa: false,
+        permissions: Vec::new(),
+        api_key_id: None,
+        token_id: None,
+        act: None,
+    }
+}
+
+pub async fn create_test_category(pool: &PgPool) -> Uuid {
+    let data = CreateCategory {
+        name: format!("category_{}", Uuid::new_v4().simple()),
+        description: None,
+        parent_id: None,
+    };
+    create_category_db(pool, data).await.unwrap().id
+}
+
+pub async fn create_test_product(pool: &PgPool, price: &str, currency: &str, stock: i32) -> Uuid {
+    let category_id = create_test_category(pool).await;
+    let data = CreateProduct {
+        name: format!("product_{}", Uuid::new_v4().simple()),
+        description: None,
+        price: price.to_string(),
+        currency: Some(currency.to_string()),
+        category_id,
+        stock,
+    };
+    create_product_db(pool, data, None).await.unwrap().id
+}
+
+pub fn dec(value: &str) -> Decimal {
+    Decimal::from_str_exact(value).unwrap()
+}
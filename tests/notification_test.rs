@@ -0,0 +1,79 @@
+mod common;
+
+use anyhow::{anyhow, Result};
+use axum::async_trait;
+use common::{create_test_product, create_test_user};
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tests3::db::notificationq::{claim_pending_notifications, queue_back_in_stock_notifications};
+use tests3::db::wishlistq::subscribe_to_stock;
+use tests3::models::notification::OutgoingNotification;
+use tests3::notifications::{dispatch_pending, Notifier, MAX_ATTEMPTS};
+
+/// Counts sends, failing every one when `fail` is set
+#[derive(Default)]
+struct CountingNotifier {
+    sends: AtomicUsize,
+    fail: bool,
+}
+
+#[async_trait]
+impl Notifier for CountingNotifier {
+    fn name(&self) -> &'static str {
+        "counting"
+    }
+
+    async fn send(&self, _notification: &OutgoingNotification) -> Result<()> {
+        self.sends.fetch_add(1, Ordering::SeqCst);
+        if self.fail {
+            return Err(anyhow!("unreachable"));
+        }
+        Ok(())
+    }
+}
+
+/// Queue one back-in-stock notification
+async fn queue_notification(pool: &PgPool) {
+    let user = create_test_user(pool).await;
+    let product_id = create_test_product(pool, "10.00", "USD", 0).await;
+    subscribe_to_stock(pool, user.id, product_id).await.unwrap();
+
+    let mut tx = pool.begin().await.unwrap();
+    assert_eq!(queue_back_in_stock_notifications(&mut tx, product_id).await.unwrap(), 1);
+    tx.commit().await.unwrap();
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn concurrent_dispatchers_send_each_notification_once(pool: PgPool) {
+    queue_notification(&pool).await;
+    let notifier = CountingNotifier::default();
+
+    let (first, second) = tokio::join!(dispatch_pending(&pool, &notifier), dispatch_pending(&pool, &notifier));
+
+    assert_eq!(first.unwrap() + second.unwrap(), 1);
+    assert_eq!(notifier.sends.load(Ordering::SeqCst), 1);
+    assert_eq!(dispatch_pending(&pool, &notifier).await.unwrap(), 0);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn claimed_notifications_are_skipped_until_released(pool: PgPool) {
+    queue_notification(&pool).await;
+
+    assert_eq!(claim_pending_notifications(&pool, MAX_ATTEMPTS, 10, 5).await.unwrap().len(), 1);
+    assert!(claim_pending_notifications(&pool, MAX_ATTEMPTS, 10, 5).await.unwrap().is_empty());
+
+    // An expired claim (e.g. the dispatcher crashed) is picked up again
+    sqlx::query("UPDATE notifications SET claimed_until = NOW() - INTERVAL '1 second'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let notifier = CountingNotifier {
+        fail: true,
+        ..Default::default()
+    };
+    assert_eq!(dispatch_pending(&pool, &notifier).await.unwrap(), 0);
+    assert_eq!(notifier.sends.load(Ordering::SeqCst), 1);
+
+    // A failed send releases the claim so the next run retries it
+    assert_eq!(claim_pending_notifications(&pool, MAX_ATTEMPTS, 10, 5).await.unwrap()[0].attempts, 1);
+}
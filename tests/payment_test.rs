@@ -0,0 +1,126 @@
+mod common;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Json;
+use common::{auth_user, create_test_product, create_test_user, test_state, WEBHOOK_SECRET};
+use sqlx::PgPool;
+use tests3::db::cartq::{add_cart_item, find_cart_lines, find_or_create_user_cart};
+use tests3::db::orderq::{create_order_from_cart, find_order_by_id, CheckoutOutcome};
+use tests3::db::paymentq::find_payments_by_order;
+use tests3::models::order::{Order, OrderStatus};
+use tests3::models::payment::{PaymentKind, PaymentStatus};
+use tests3::payments::mock::MockPaymentProvider;
+use tests3::payments::WEBHOOK_SIGNATURE_HEADER;
+use tests3::services::cart::cart_view;
+use tests3::services::payments::{pay_order, webhook};
+use tests3::utils::extractor::UuidPath;
+use tests3::AppState;
+
+/// Place a 25.00 USD order for a new user and start paying for it; returns the order and intent id
+async fn pending_payment(state: &AppState) -> (Order, String) {
+    let pool = &state.db_pool;
+    let product_id = create_test_product(pool, "25.00", "USD", 5).await;
+    let user = create_test_user(pool).await;
+    let cart = find_or_create_user_cart(pool, user.id).await.unwrap();
+    add_cart_item(pool, cart.id, product_id, None, 1).await.unwrap();
+
+    let view = cart_view(pool, &cart).await.unwrap();
+    let lines = find_cart_lines(pool, cart.id).await.unwrap();
+    let order = match create_order_from_cart(pool, user.id, &cart, &lines, &view.applied_promotions).await.unwrap() {
+        CheckoutOutcome::Placed(order) => order.order,
+        _ => panic!("checkout failed"),
+    };
+
+    let Json(intent) = pay_order(State(state.clone()), auth_user(&user), UuidPath(order.id)).await.unwrap();
+    assert_eq!(intent.currency, "USD");
+    (order, intent.intent_id)
+}
+
+async fn send_event(state: &AppState, kind: &str, event_id: &str, intent_id: &str, amount: &str, currency: &str) {
+    let payload = serde_json::json!({
+        "id": event_id,
+        "type": kind,
+        "intent_id": intent_id,
+        "amount": amount,
+        "currency": currency,
+    })
+    .to_string();
+
+    let mut headers = HeaderMap::new();
+    let signature = MockPaymentProvider::new(WEBHOOK_SECRET).sign(payload.as_bytes());
+    headers.insert(WEBHOOK_SIGNATURE_HEADER, signature.parse().unwrap());
+
+    let _ = webhook(State(state.clone()), headers, Bytes::from(payload)).await.unwrap();
+}
+
+async fn order_and_payment_status(pool: &PgPool, order: &Order) -> (OrderStatus, PaymentStatus) {
+    let order = find_order_by_id(pool, order.id).await.unwrap().unwrap();
+    let payments = find_payments_by_order(pool, order.id).await.unwrap();
+    (order.status, payments[0].status)
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn matching_payment_marks_the_order_paid(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let (order, intent_id) = pending_payment(&state).await;
+
+    send_event(&state, "payment.succeeded", "evt_ok", &intent_id, "25.00", "USD").await;
+    assert_eq!(
+        order_and_payment_status(&pool, &order).await,
+        (OrderStatus::Paid, PaymentStatus::Succeeded)
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn payment_for_the_wrong_amount_leaves_the_order_unpaid(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let (order, intent_id) = pending_payment(&state).await;
+
+    send_event(&state, "payment.succeeded", "evt_short", &intent_id, "0.01", "USD").await;
+    assert_eq!(
+        order_and_payment_status(&pool, &order).await,
+        (OrderStatus::Pending, PaymentStatus::Failed)
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn payment_in_the_wrong_currency_leaves_the_order_unpaid(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let (order, intent_id) = pending_payment(&state).await;
+
+    send_event(&state, "payment.succeeded", "evt_yen", &intent_id, "25.00", "JPY").await;
+    assert_eq!(
+        order_and_payment_status(&pool, &order).await,
+        (OrderStatus::Pending, PaymentStatus::Failed)
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn full_refund_marks_the_order_refunded(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let (order, intent_id) = pending_payment(&state).await;
+    send_event(&state, "payment.succeeded", "evt_paid", &intent_id, "25.00", "USD").await;
+
+    send_event(&state, "refund.succeeded", "evt_refund", &intent_id, "25.00", "USD").await;
+    let order = find_order_by_id(&pool, order.id).await.unwrap().unwrap();
+    assert_eq!(order.status, OrderStatus::Refunded);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn partial_or_mismatched_refunds_leave_the_order_paid(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let (order, intent_id) = pending_payment(&state).await;
+    send_event(&state, "payment.succeeded", "evt_paid", &intent_id, "25.00", "USD").await;
+
+    send_event(&state, "refund.succeeded", "evt_partial", &intent_id, "5.00", "USD").await;
+    send_event(&state, "refund.succeeded", "evt_yen", &intent_id, "25.00", "JPY").await;
+
+    assert_eq!(
+        order_and_payment_status(&pool, &order).await,
+        (OrderStatus::Paid, PaymentStatus::Succeeded)
+    );
+    let payments = find_payments_by_order(&pool, order.id).await.unwrap();
+    assert!(payments.iter().all(|payment| payment.kind == PaymentKind::Payment));
+}
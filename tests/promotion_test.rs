@@ -0,0 +1,71 @@
+mod common;
+
+use common::{create_test_product, create_test_user};
+use sqlx::PgPool;
+use tests3::db::cartq::{add_cart_item, find_cart_lines, find_or_create_user_cart, set_cart_coupon};
+use tests3::db::orderq::{create_order_from_cart, transition_order_status, CheckoutOutcome};
+use tests3::db::promotionq::{count_promotion_uses, create_promotion_db};
+use tests3::models::order::OrderStatus;
+use tests3::models::promotion::{CreatePromotion, DiscountType, PromotionScope};
+use tests3::services::cart::cart_view;
+use uuid::Uuid;
+
+/// Check out the user's cart with `code` applied; returns the order id
+async fn checkout_with_coupon(pool: &PgPool, user_id: Uuid, product_id: Uuid, code: &str) -> Uuid {
+    let cart = find_or_create_user_cart(pool, user_id).await.unwrap();
+    add_cart_item(pool, cart.id, product_id, None, 1).await.unwrap();
+    set_cart_coupon(pool, cart.id, Some(code)).await.unwrap();
+
+    let cart = find_or_create_user_cart(pool, user_id).await.unwrap();
+    let view = cart_view(pool, &cart).await.unwrap();
+    assert_eq!(view.applied_promotions.len(), 1);
+
+    let lines = find_cart_lines(pool, cart.id).await.unwrap();
+    match create_order_from_cart(pool, user_id, &cart, &lines, &view.applied_promotions).await.unwrap() {
+        CheckoutOutcome::Placed(order) => order.order.id,
+        _ => panic!("checkout failed"),
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn cancelled_orders_give_their_coupon_use_back(pool: PgPool) {
+    let product_id = create_test_product(&pool, "20.00", "USD", 10).await;
+    let promotion = create_promotion_db(
+        &pool,
+        CreatePromotion {
+            name: "Once only".to_string(),
+            code: Some("ONCE".to_string()),
+            discount_type: DiscountType::Percentage,
+            discount_value: "10".to_string(),
+            scope: PromotionScope::Cart,
+            product_id: None,
+            category_id: None,
+            min_spend: None,
+            currency: None,
+            starts_at: None,
+            ends_at: None,
+            usage_limit: Some(1),
+            per_user_limit: None,
+            active: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let user = create_test_user(&pool).await;
+    let order_id = checkout_with_coupon(&pool, user.id, product_id, "ONCE").await;
+
+    let mut conn = pool.acquire().await.unwrap();
+    assert_eq!(count_promotion_uses(&mut conn, promotion.id, Some(user.id)).await.unwrap(), (1, 1));
+
+    transition_order_status(&pool, order_id, OrderStatus::Pending, OrderStatus::Cancelled, Some(user.id))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(count_promotion_uses(&mut conn, promotion.id, Some(user.id)).await.unwrap(), (0, 0));
+
+    // The use is free again, so another customer can redeem the single-use coupon
+    let other = create_test_user(&pool).await;
+    checkout_with_coupon(&pool, other.id, product_id, "ONCE").await;
+    assert_eq!(count_promotion_uses(&mut conn, promotion.id, None).await.unwrap(), (1, 0));
+}
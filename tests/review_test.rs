@@ -0,0 +1,101 @@
+mod common;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Json;
+use common::{auth_user, create_test_product, create_test_user, test_state};
+use sqlx::PgPool;
+use tests3::db::cartq::{add_cart_item, find_cart_lines, find_or_create_user_cart};
+use tests3::db::orderq::{create_order_from_cart, transition_order_status, CheckoutOutcome};
+use tests3::db::reviewq::{create_review_db, find_reviews_by_user};
+use tests3::models::order::OrderStatus;
+use tests3::models::review::CreateReview;
+use tests3::models::user::User;
+use tests3::services::cart::cart_view;
+use tests3::services::reviews::create_review;
+use tests3::utils::error::AppError;
+use tests3::utils::extractor::UuidPath;
+use uuid::Uuid;
+
+fn review(comment: &str) -> CreateReview {
+    CreateReview {
+        rating: 4,
+        comment: comment.to_string(),
+    }
+}
+
+/// Order the product for the user and leave the order in `status`
+async fn order_product(pool: &PgPool, user: &User, product_id: Uuid, status: OrderStatus) {
+    let cart = find_or_create_user_cart(pool, user.id).await.unwrap();
+    add_cart_item(pool, cart.id, product_id, None, 1).await.unwrap();
+    let view = cart_view(pool, &cart).await.unwrap();
+    let lines = find_cart_lines(pool, cart.id).await.unwrap();
+    let order = match create_order_from_cart(pool, user.id, &cart, &lines, &view.applied_promotions).await.unwrap() {
+        CheckoutOutcome::Placed(order) => order.order,
+        _ => panic!("checkout failed"),
+    };
+
+    if status != OrderStatus::Pending {
+        transition_order_status(pool, order.id, OrderStatus::Pending, status, None).await.unwrap().unwrap();
+    }
+}
+
+async fn verified_purchase(pool: &PgPool, user: &User) -> bool {
+    find_reviews_by_user(pool, user.id).await.unwrap()[0].verified_purchase
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn only_paid_orders_mark_a_review_as_verified_purchase(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let product_id = create_test_product(&pool, "10.00", "USD", 10).await;
+
+    let (stranger, unpaid, paid) = (
+        create_test_user(&pool).await,
+        create_test_user(&pool).await,
+        create_test_user(&pool).await,
+    );
+    order_product(&pool, &unpaid, product_id, OrderStatus::Pending).await;
+    order_product(&pool, &paid, product_id, OrderStatus::Paid).await;
+
+    for user in [&stranger, &unpaid, &paid] {
+        let response = create_review(State(state.clone()), auth_user(user), UuidPath(product_id), Json(review("Nice")))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    assert!(!verified_purchase(&pool, &stranger).await);
+    assert!(!verified_purchase(&pool, &unpaid).await);
+    assert!(verified_purchase(&pool, &paid).await);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn a_user_can_review_a_product_once(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let product_id = create_test_product(&pool, "10.00", "USD", 10).await;
+    let user = create_test_user(&pool).await;
+
+    create_review(State(state.clone()), auth_user(&user), UuidPath(product_id), Json(review("First")))
+        .await
+        .unwrap();
+    let second = create_review(State(state.clone()), auth_user(&user), UuidPath(product_id), Json(review("Again"))).await;
+
+    assert!(matches!(second, Err(AppError::Conflict(_))));
+    assert_eq!(find_reviews_by_user(&pool, user.id).await.unwrap().len(), 1);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn concurrent_reviews_by_one_user_store_only_one(pool: PgPool) {
+    let product_id = create_test_product(&pool, "10.00", "USD", 10).await;
+    let user = create_test_user(&pool).await;
+
+    let (first, second) = tokio::join!(
+        create_review_db(&pool, product_id, user.id, review("First"), false),
+        create_review_db(&pool, product_id, user.id, review("Second"), false),
+    );
+
+    let stored = [first.unwrap(), second.unwrap()].into_iter().flatten().count();
+    assert_eq!(stored, 1);
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use axum::extract::State;
+use axum::Json;
+use common::{auth_user, test_state};
+use sqlx::PgPool;
+use tests3::db::authq::{evict_excess_sessions, find_user_sessions};
+use tests3::db::securityq::find_user_security_events;
+use tests3::models::auth::{Actor, AuthResponse, ClientInfo, LoginRequest, LoginResponse, RefreshTokenRequest};
+use tests3::models::security::REFRESH_TOKEN_REUSE;
+use tests3::models::user::{CreateUser, User};
+use tests3::services::auth::{login, refresh_token, register};
+use tests3::services::profile::{list_sessions, revoke_other_sessions, revoke_session};
+use tests3::utils::error::{AppError, AppResult};
+use tests3::utils::extractor::UuidPath;
+use tests3::AppState;
+use uuid::Uuid;
+
+const PASSWORD: &str = "correct-horse-42";
+
+fn client(user_agent: &str) -> ClientInfo {
+    ClientInfo {
+        user_agent: Some(user_agent.to_string()),
+        ip_address: Some("203.0.113.7".to_string()),
+    }
+}
+
+async fn register_user(state: &AppState) -> User {
+    let name = format!("user_{}", Uuid::new_v4().simple());
+    let data = CreateUser {
+        email: format!("{}@example.com", name),
+        username: name,
+        password: PASSWORD.to_string(),
+    };
+    let Json(response) = register(State(state.clone()), client("Register"), Json(data)).await.unwrap();
+    response.user
+}
+
+async fn log_in(state: &AppState, user: &User, device: &str) -> AuthResponse {
+    let data = LoginRequest {
+        email: user.email.clone(),
+        password: PASSWORD.to_string(),
+        cart_token: None,
+        device_label: Some(device.to_string()),
+    };
+    match login(State(state.clone()), client(device), Json(data)).await.unwrap() {
+        Json(LoginResponse::Authenticated(response)) => response,
+        Json(LoginResponse::MfaRequired(_)) => panic!("no second factor is set up"),
+    }
+}
+
+async fn refresh(state: &AppState, token: &str) -> AppResult<AuthResponse> {
+    let request = RefreshTokenRequest {
+        refresh_token: token.to_string(),
+    };
+    let Json(response) = refresh_token(State(state.clone()), client("Laptop"), Json(request)).await?;
+    Ok(response)
+}
+
+/// Id of the session labelled `device`
+async fn session_id(pool: &PgPool, user: &User, device: &str) -> Uuid {
+    find_user_sessions(pool, user.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|session| session.device_label.as_deref() == Some(device))
+        .unwrap()
+        .id
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn logging_in_on_another_device_keeps_the_first_session(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    log_in(&state, &user, "Laptop").await;
+    log_in(&state, &user, "Phone").await;
+
+    let mut current = auth_user(&user);
+    current.session_id = Some(session_id(&pool, &user, "Phone").await);
+    let Json(sessions) = list_sessions(State(state.clone()), current.clone()).await.unwrap();
+
+    assert_eq!(sessions.len(), 3);
+    let phone = sessions.iter().find(|s| s.current).unwrap();
+    assert_eq!(phone.device_label.as_deref(), Some("Phone"));
+    assert_eq!(phone.user_agent.as_deref(), Some("Phone"));
+    assert_eq!(phone.ip_address.as_deref(), Some("203.0.113.7"));
+
+    // "Log out everywhere else" leaves only the session making the request
+    let Json(body) = revoke_other_sessions(State(state.clone()), current.clone()).await.unwrap();
+    assert_eq!(body["revoked"], 2);
+    let remaining = find_user_sessions(&pool, user.id).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(Some(remaining[0].id), current.session_id);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn an_impersonating_admin_cannot_log_the_user_out(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    log_in(&state, &user, "Laptop").await;
+    let laptop = session_id(&pool, &user, "Laptop").await;
+
+    let mut impersonated = auth_user(&user);
+    impersonated.token_id = Some(Uuid::new_v4());
+    impersonated.act = Some(Actor { sub: Uuid::new_v4(), username: "admin".to_string() });
+
+    let result = revoke_session(State(state.clone()), impersonated.clone(), UuidPath(laptop)).await;
+    assert!(matches!(result, Err(AppError::Authorization(_))));
+    let result = revoke_other_sessions(State(state.clone()), impersonated).await;
+    assert!(matches!(result, Err(AppError::Authorization(_))));
+    assert_eq!(find_user_sessions(&pool, user.id).await.unwrap().len(), 2);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn the_oldest_sessions_are_evicted_past_the_cap(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    log_in(&state, &user, "Laptop").await;
+    log_in(&state, &user, "Phone").await;
+
+    assert_eq!(evict_excess_sessions(&pool, user.id, 2).await.unwrap(), 1);
+
+    let mut devices: Vec<_> = find_user_sessions(&pool, user.id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|session| session.device_label)
+        .collect();
+    devices.sort();
+    assert_eq!(devices, [Some("Laptop".to_string()), Some("Phone".to_string())]);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn refreshing_rotates_the_token_within_the_session(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    let first = log_in(&state, &user, "Laptop").await;
+    let session = session_id(&pool, &user, "Laptop").await;
+
+    let second = refresh(&state, &first.refresh_token).await.unwrap();
+    assert_ne!(second.refresh_token, first.refresh_token);
+    let third = refresh(&state, &second.refresh_token).await.unwrap();
+    assert_ne!(third.refresh_token, second.refresh_token);
+
+    // No new sessions appear and the device keeps its session id
+    assert_eq!(find_user_sessions(&pool, user.id).await.unwrap().len(), 2);
+    assert_eq!(session_id(&pool, &user, "Laptop").await, session);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn replaying_a_rotated_token_revokes_the_whole_family(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = register_user(&state).await;
+    let stolen = log_in(&state, &user, "Laptop").await;
+    let rotated = refresh(&state, &stolen.refresh_token).await.unwrap();
+
+    let replay = refresh(&state, &stolen.refresh_token).await;
+    assert!(matches!(replay, Err(AppError::Authentication(_))));
+
+    // The legitimate chain is cut off too, other devices are not
+    let latest = refresh(&state, &rotated.refresh_token).await;
+    assert!(matches!(latest, Err(AppError::Authentication(_))));
+    let sessions = find_user_sessions(&pool, user.id).await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].device_label, None);
+
+    let events = find_user_security_events(&pool, user.id).await.unwrap();
+    assert_eq!(events.iter().filter(|e| e.kind == REFRESH_TOKEN_REUSE).count(), 1);
+}
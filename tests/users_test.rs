@@ -0,0 +1,87 @@
+mod common;
+
+use axum::extract::State;
+use axum::Json;
+use common::{auth_user, create_test_user, test_state};
+use sqlx::PgPool;
+use tests3::db::roleq::{create_role_db, set_user_extra_roles};
+use tests3::db::userq::{change_user_role, find_by_id};
+use tests3::models::auth::ClientInfo;
+use tests3::models::user::{UpdateUserRole, User, UserRole};
+use tests3::services::roles::resolve_permissions;
+use tests3::services::users::{impersonate_user, update_user_role};
+use tests3::utils::error::{AppError, AppResult};
+use tests3::utils::extractor::UuidPath;
+use tests3::AppState;
+
+async fn create_user_with_role(pool: &PgPool, role: UserRole) -> User {
+    let user = create_test_user(pool).await;
+    change_user_role(pool, user.id, role).await.unwrap();
+    find_by_id(pool, user.id).await.unwrap().unwrap()
+}
+
+async fn set_role(state: &AppState, actor: &User, target: &User, role: UserRole) -> AppResult<User> {
+    let Json(user) = update_user_role(
+        State(state.clone()),
+        auth_user(actor),
+        ClientInfo::default(),
+        UuidPath(target.id),
+        Json(UpdateUserRole { role }),
+    )
+    .await?;
+    Ok(user)
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn the_last_superadmin_cannot_be_demoted(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let superadmin = create_user_with_role(&pool, UserRole::SuperAdmin).await;
+
+    let result = set_role(&state, &superadmin, &superadmin, UserRole::Admin).await;
+    assert!(matches!(result, Err(AppError::Conflict(_))));
+    let unchanged = find_by_id(&pool, superadmin.id).await.unwrap().unwrap();
+    assert!(matches!(unchanged.role, UserRole::SuperAdmin));
+
+    // Once there is another superadmin the first one can step down
+    let other = create_test_user(&pool).await;
+    set_role(&state, &superadmin, &other, UserRole::SuperAdmin).await.unwrap();
+    let demoted = set_role(&state, &superadmin, &superadmin, UserRole::Admin).await.unwrap();
+    assert!(matches!(demoted.role, UserRole::Admin));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn only_a_superadmin_changes_roles(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let admin = create_user_with_role(&pool, UserRole::Admin).await;
+    let user = create_test_user(&pool).await;
+
+    let result = set_role(&state, &admin, &user, UserRole::Admin).await;
+    assert!(matches!(result, Err(AppError::Authorization(_))));
+    let unchanged = find_by_id(&pool, user.id).await.unwrap().unwrap();
+    assert!(matches!(unchanged.role, UserRole::User));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn impersonation_cannot_pick_up_permissions_the_admin_lacks(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let admin = create_user_with_role(&pool, UserRole::Admin).await;
+    let mut actor = auth_user(&admin);
+    actor.permissions = resolve_permissions(&pool, &admin).await.unwrap();
+    actor.permissions.push("users:impersonate".to_string());
+
+    // A plain customer can be impersonated
+    let customer = create_test_user(&pool).await;
+    let Json(response) = impersonate_user(State(state.clone()), actor.clone(), ClientInfo::default(), UuidPath(customer.id))
+        .await
+        .unwrap();
+    assert_eq!(response.user.id, customer.id);
+
+    // A user whose extra role grants more than the admin holds cannot
+    let roles = ["roles:manage".to_string()];
+    assert!(create_role_db(&pool, "role-manager", None, &roles).await.unwrap());
+    let manager = create_test_user(&pool).await;
+    set_user_extra_roles(&pool, manager.id, &["role-manager".to_string()]).await.unwrap();
+
+    let result = impersonate_user(State(state), actor, ClientInfo::default(), UuidPath(manager.id)).await;
+    assert!(matches!(result, Err(AppError::Authorization(_))));
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::{create_test_product, create_test_user, dec};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use tests3::db::cartq::{add_cart_item, find_cart_lines, find_or_create_user_cart};
+use tests3::db::inventoryq::{apply_variant_stock_delta, find_stock_movements};
+use tests3::db::orderq::{create_order_from_cart, transition_order_status, CheckoutOutcome};
+use tests3::db::productq::find_product_by_id;
+use tests3::db::variantq::{create_variant_db, delete_variant_db, update_variant_db};
+use tests3::models::inventory::StockMovementKind;
+use tests3::models::order::OrderStatus;
+use tests3::models::product::{CreateVariant, UpdateVariant};
+use tests3::services::cart::cart_view;
+
+fn variant(sku: &str, price: Option<&str>) -> CreateVariant {
+    CreateVariant {
+        sku: sku.to_string(),
+        options: BTreeMap::from([("size".to_string(), sku.to_string())]),
+        price: price.map(str::to_string),
+        stock: 5,
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn variant_without_price_falls_back_to_product_price(pool: PgPool) {
+    let product_id = create_test_product(&pool, "10.00", "USD", 5).await;
+    let plain = create_variant_db(&pool, product_id, variant("TEE-S", None), None).await.unwrap();
+    let priced = create_variant_db(&pool, product_id, variant("TEE-XL", Some("12.50")), None).await.unwrap();
+
+    let user = create_test_user(&pool).await;
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    add_cart_item(&pool, cart.id, product_id, Some(plain.id), 1).await.unwrap();
+    add_cart_item(&pool, cart.id, product_id, Some(priced.id), 1).await.unwrap();
+    add_cart_item(&pool, cart.id, product_id, None, 1).await.unwrap();
+
+    let lines = find_cart_lines(&pool, cart.id).await.unwrap();
+    let price_of = |variant_id| lines.iter().find(|l| l.variant_id == variant_id).unwrap().unit_price.unwrap();
+    assert_eq!(price_of(Some(plain.id)), dec("10.00"));
+    assert_eq!(price_of(Some(priced.id)), dec("12.50"));
+    assert_eq!(price_of(None), dec("10.00"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn variant_stock_changes_are_recorded_in_the_ledger(pool: PgPool) {
+    let product_id = create_test_product(&pool, "10.00", "USD", 0).await;
+    let created = create_variant_db(&pool, product_id, variant("MUG-RED", None), None).await.unwrap();
+
+    let update = UpdateVariant { sku: None, options: None, price: None, stock: Some(2) };
+    let updated = update_variant_db(&pool, created.id, update, None).await.unwrap();
+    assert_eq!(updated.stock, 2);
+
+    let mut tx = pool.begin().await.unwrap();
+    apply_variant_stock_delta(&mut tx, created.id, -1, StockMovementKind::Sale, None, None).await.unwrap().unwrap();
+    assert!(apply_variant_stock_delta(&mut tx, created.id, -5, StockMovementKind::Sale, None, None).await.unwrap().is_none());
+    tx.commit().await.unwrap();
+
+    let history = find_stock_movements(&pool, product_id, None, None).await.unwrap();
+    let movements: Vec<_> = history.data.iter().filter(|m| m.variant_id == Some(created.id)).collect();
+    assert_eq!(movements.len(), 3);
+    assert_eq!(movements.iter().map(|m| m.quantity).sum::<i32>(), 1);
+    assert_eq!(movements[0].balance_after, 1);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn cancelling_after_the_variant_is_deleted_leaves_product_stock_alone(pool: PgPool) {
+    let product_id = create_test_product(&pool, "10.00", "USD", 3).await;
+    let tee = create_variant_db(&pool, product_id, variant("TEE-M", None), None).await.unwrap();
+
+    let user = create_test_user(&pool).await;
+    let cart = find_or_create_user_cart(&pool, user.id).await.unwrap();
+    add_cart_item(&pool, cart.id, product_id, Some(tee.id), 2).await.unwrap();
+    let view = cart_view(&pool, &cart).await.unwrap();
+    let lines = find_cart_lines(&pool, cart.id).await.unwrap();
+    let order = match create_order_from_cart(&pool, user.id, &cart, &lines, &view.applied_promotions).await.unwrap() {
+        CheckoutOutcome::Placed(order) => order.order,
+        _ => panic!("checkout failed"),
+    };
+
+    delete_variant_db(&pool, tee.id).await.unwrap();
+    transition_order_status(&pool, order.id, OrderStatus::Pending, OrderStatus::Cancelled, None)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let product = find_product_by_id(&pool, product_id).await.unwrap().unwrap();
+    assert_eq!(product.stock, 3);
+    let history = find_stock_movements(&pool, product_id, None, None).await.unwrap();
+    assert!(history.data.iter().all(|m| m.kind != StockMovementKind::Return));
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use axum::extract::State;
+use axum::Json;
+use common::{auth_user, create_test_user, test_state};
+use sqlx::PgPool;
+use tests3::db::authq::{
+    confirm_email_verification, create_email_verification_token, find_email_verification_token,
+    EmailVerificationOutcome,
+};
+use tests3::db::userq::{find_by_id, set_pending_email};
+use tests3::models::auth::VerifyEmailRequest;
+use tests3::models::user::User;
+use tests3::services::verification::{resend_verification, verify_email};
+use tests3::utils::error::AppError;
+use tests3::AppState;
+
+/// Store a verification token for `email` the way the emailed link would carry it
+async fn verification_token(state: &AppState, user: &User, email: &str) -> String {
+    let (token, stored_token) = state.token_hasher.generate_split_token();
+    create_email_verification_token(&state.db_pool, user.id, email, &stored_token, 24)
+        .await
+        .unwrap();
+    token
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn verifying_a_pending_email_replaces_the_current_one(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = create_test_user(&pool).await;
+    set_pending_email(&pool, user.id, Some("new-address@example.com")).await.unwrap();
+    let token = verification_token(&state, &user, "new-address@example.com").await;
+
+    let Json(verified) = verify_email(State(state.clone()), Json(VerifyEmailRequest { token: token.clone() }))
+        .await
+        .unwrap();
+    assert_eq!(verified.email, "new-address@example.com");
+    assert_eq!(verified.pending_email, None);
+    assert!(verified.email_verified_at.is_some());
+
+    // The link only works once
+    let reused = verify_email(State(state), Json(VerifyEmailRequest { token })).await;
+    assert!(matches!(reused, Err(AppError::BadRequest(_))));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn a_pending_email_claimed_by_another_account_is_not_verified(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = create_test_user(&pool).await;
+    let other = create_test_user(&pool).await;
+    set_pending_email(&pool, user.id, Some(&other.email)).await.unwrap();
+    let token = verification_token(&state, &user, &other.email).await;
+
+    // Skip the service's own check, as if the other account took the address in between
+    let stored = find_email_verification_token(&pool, &state.token_hasher, &token)
+        .await
+        .unwrap()
+        .unwrap();
+    let outcome = confirm_email_verification(&pool, &stored).await.unwrap();
+    assert!(matches!(outcome, EmailVerificationOutcome::EmailTaken));
+
+    let unchanged = find_by_id(&pool, user.id).await.unwrap().unwrap();
+    assert_eq!(unchanged.email, user.email);
+    assert_eq!(unchanged.pending_email.as_deref(), Some(other.email.as_str()));
+
+    // The token is kept, so the service still reports the conflict
+    let result = verify_email(State(state), Json(VerifyEmailRequest { token })).await;
+    assert!(matches!(result, Err(AppError::Conflict(_))));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn resending_the_verification_email_is_throttled(pool: PgPool) {
+    let state = test_state(pool.clone());
+    let user = create_test_user(&pool).await;
+
+    let Json(sent) = resend_verification(State(state.clone()), auth_user(&user)).await.unwrap();
+    assert_eq!(sent["message"], format!("Verification email sent to {}", user.email));
+
+    match resend_verification(State(state.clone()), auth_user(&user)).await {
+        Err(AppError::TooManyRequests { retry_after_seconds, .. }) => {
+            assert!(retry_after_seconds > 0 && retry_after_seconds <= 60)
+        }
+        _ => panic!("second resend was not throttled"),
+    }
+
+    // A pending address is also subject to the same throttle
+    set_pending_email(&pool, user.id, Some("new-address@example.com")).await.unwrap();
+    let result = resend_verification(State(state), auth_user(&user)).await;
+    assert!(matches!(result, Err(AppError::TooManyRequests { .. })));
+}